@@ -0,0 +1,45 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use chat_spike::{Dictionary, MemoryDictionary};
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+
+fn tokens() -> Vec<String> {
+    (0..20_000).map(|i| format!("tok{}", i % 5_000)).collect()
+}
+
+fn bench_hasher<S: BuildHasher + Default + Clone>(c: &mut Criterion, name: &str) {
+    let tokens = tokens();
+    let mut group = c.benchmark_group("memory_dictionary");
+    group.bench_function(BenchmarkId::new("observe", name), |b| {
+        b.iter(|| {
+            let mut dict = MemoryDictionary::<1000, S>::default();
+            for (idx, token) in tokens.iter().enumerate() {
+                dict.observe(token, idx as u32);
+            }
+            dict
+        })
+    });
+    let mut dict = MemoryDictionary::<1000, S>::default();
+    for (idx, token) in tokens.iter().enumerate() {
+        dict.observe(token, idx as u32);
+    }
+    group.bench_function(BenchmarkId::new("count", name), |b| {
+        b.iter(|| {
+            tokens
+                .iter()
+                .map(|token| dict.count(black_box(token), 20_000))
+                .sum::<f64>()
+        })
+    });
+    group.finish();
+}
+
+fn dictionary(c: &mut Criterion) {
+    bench_hasher::<RandomState>(c, "siphash");
+    #[cfg(feature = "fast-hash")]
+    bench_hasher::<ahash::RandomState>(c, "ahash");
+}
+
+criterion_group!(benches, dictionary);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use chat_spike::math::{neg_ln_poisson_tail, neg_ln_poisson_tail_many};
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+/// A sweep mixing all three evaluation branches.
+fn sweep() -> (Vec<f64>, Vec<f64>) {
+    (0..4_096)
+        .map(|i| ((i % 64) as f64 * 2.5, 1. + (i / 64) as f64 * 1.5))
+        .unzip()
+}
+
+fn poisson_tail(c: &mut Criterion) {
+    let (ks, lambdas) = sweep();
+    let mut group = c.benchmark_group("neg_ln_poisson_tail");
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            ks.iter()
+                .zip(&lambdas)
+                .map(|(&k, &lambda)| neg_ln_poisson_tail(black_box(k), lambda))
+                .sum::<f64>()
+        })
+    });
+    let mut out = vec![0.; ks.len()];
+    group.bench_function("many", |b| {
+        b.iter(|| {
+            neg_ln_poisson_tail_many(black_box(&ks), &lambdas, &mut out);
+            out.iter().sum::<f64>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, poisson_tail);
+criterion_main!(benches);
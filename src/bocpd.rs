@@ -0,0 +1,182 @@
+//! Bayesian online changepoint detection over chat rates.
+//!
+//! [`Bocpd`] keeps a posterior over the *run length*, the number of events
+//! since the chat rate last changed, following Adams & MacKay (2007). Gaps
+//! between events are modelled as exponential with a Gamma prior on the
+//! rate, restarted at each changepoint.
+//!
+//! Run-length probabilities tell a new regime from a momentary burst: a
+//! burst pulls the mass towards short runs for a few events before it
+//! returns to the long run, while after a lasting change the most likely
+//! run length restarts near zero and keeps growing from there.
+//!
+//! ```rust
+//! use std::time::{Duration, Instant};
+//! use chat_spike::bocpd::Bocpd;
+//!
+//! let mut bocpd = Bocpd::new(200.);
+//! let mut ts = Instant::now();
+//! for i in 0..300 {
+//!     ts += Duration::from_millis(if i < 200 { 1000 } else { 200 });
+//!     bocpd.push(ts);
+//! }
+//! assert!(bocpd.most_likely_run_length() <= 100);
+//! assert!(bocpd.regime_rate() > 3.);
+//! ```
+
+use std::time::Instant;
+
+/// Sufficient statistics of one run-length hypothesis.
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    prob: f64,
+    /// Events in the run.
+    events: f64,
+    /// Seconds covered by the run's gaps.
+    secs: f64,
+}
+
+/// Run-length posterior over the gaps between events.
+#[derive(Clone, Debug)]
+pub struct Bocpd {
+    hazard: f64,
+    shape: f64,
+    rate: f64,
+    max_run: usize,
+    /// Hypotheses by run length, from zero.
+    runs: Vec<Run>,
+    last_ts: Option<Instant>,
+}
+
+impl Bocpd {
+    /// Expect a change every `expected_run` events on average.
+    pub fn new(expected_run: f64) -> Self {
+        Self {
+            hazard: 1. / expected_run.max(1.),
+            shape: 1.,
+            rate: 1.,
+            max_run: 1000,
+            runs: vec![Run {
+                prob: 1.,
+                events: 0.,
+                secs: 0.,
+            }],
+            last_ts: None,
+        }
+    }
+    /// Gamma prior on each regime's rate in events per second: `shape /
+    /// rate` is its mean, and `shape` weighs it in events.
+    pub fn with_prior(mut self, shape: f64, rate: f64) -> Self {
+        self.shape = shape;
+        self.rate = rate;
+        self
+    }
+    /// Track run lengths up to `max_run`; longer runs are lumped together,
+    /// bounding the cost of a push.
+    pub fn with_max_run(mut self, max_run: usize) -> Self {
+        self.max_run = max_run.max(1);
+        self
+    }
+    /// Feed the next timestamp. The first one only starts the clock, and a
+    /// timestamp earlier than the last counts as a zero gap.
+    pub fn push(&mut self, ts: Instant) {
+        let prev = self.last_ts;
+        self.last_ts = Some(prev.map_or(ts, |prev| prev.max(ts)));
+        if let Some(prev) = prev {
+            self.observe(ts.saturating_duration_since(prev).as_secs_f64());
+        }
+    }
+    fn observe(&mut self, gap: f64) {
+        let mut changed = 0.;
+        for run in &mut self.runs {
+            // Gamma-exponential predictive (Lomax) density of the gap.
+            let (a, b) = (self.shape + run.events, self.rate + run.secs);
+            let density = a / b * (b / (b + gap)).powf(a + 1.);
+            let joint = run.prob * density;
+            changed += joint * self.hazard;
+            *run = Run {
+                prob: joint * (1. - self.hazard),
+                events: run.events + 1.,
+                secs: run.secs + gap,
+            };
+        }
+        self.runs.insert(
+            0,
+            Run {
+                prob: changed,
+                events: 0.,
+                secs: 0.,
+            },
+        );
+        if self.runs.len() > self.max_run + 1
+            && let Some(oldest) = self.runs.pop()
+        {
+            self.runs[self.max_run].prob += oldest.prob;
+        }
+        let total: f64 = self.runs.iter().map(|run| run.prob).sum();
+        if total > 0. && total.is_finite() {
+            self.runs.iter_mut().for_each(|run| run.prob /= total);
+        }
+    }
+    /// Posterior probability of each run length, from zero events since
+    /// the last change.
+    pub fn run_length_probs(&self) -> impl ExactSizeIterator<Item = f64> + '_ {
+        self.runs.iter().map(|run| run.prob)
+    }
+    pub fn most_likely_run_length(&self) -> usize {
+        self.runs
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.prob.total_cmp(&b.prob))
+            .map_or(0, |(len, _)| len)
+    }
+    /// Probability that the rate changed within the last `within` events.
+    pub fn change_probability(&self, within: usize) -> f64 {
+        self.runs.iter().take(within).map(|run| run.prob).sum()
+    }
+    /// Posterior mean rate, in events per second, of the regime that has
+    /// lasted `run_length` events; the prior mean past the tracked runs.
+    pub fn rate(&self, run_length: usize) -> f64 {
+        self.runs
+            .get(run_length)
+            .map_or(self.shape / self.rate, |run| {
+                (self.shape + run.events) / (self.rate + run.secs)
+            })
+    }
+    /// Rate of the most likely current regime.
+    pub fn regime_rate(&self) -> f64 {
+        self.rate(self.most_likely_run_length())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn burst_is_not_a_new_regime() {
+        let mut bocpd = Bocpd::new(500.);
+        let mut ts = Instant::now();
+        let mut feed = |bocpd: &mut Bocpd, millis: u64, n: usize| {
+            for _ in 0..n {
+                ts += Duration::from_millis(millis);
+                bocpd.push(ts);
+            }
+        };
+        feed(&mut bocpd, 1000, 300);
+        assert!(bocpd.most_likely_run_length() > 250);
+        feed(&mut bocpd, 50, 4);
+        let during = bocpd.change_probability(10);
+        feed(&mut bocpd, 1000, 30);
+        assert!(bocpd.change_probability(10) < during);
+        assert!(bocpd.most_likely_run_length() > 250);
+        assert!((bocpd.regime_rate() - 1.).abs() < 0.2);
+
+        feed(&mut bocpd, 250, 100);
+        assert!(bocpd.most_likely_run_length() <= 100);
+        assert!((bocpd.regime_rate() - 4.).abs() < 0.8);
+        let total: f64 = bocpd.run_length_probs().sum();
+        assert!((total - 1.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,138 @@
+//! Chat platforms as asynchronous sources of chats.
+//!
+//! A connector implements [`ChatSource`], yielding each chat as a
+//! [`ChatMessage`] whatever platform it came from, and [`forward`] feeds a
+//! source into a [`DetectorTask`](crate::task::DetectorTask).
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "twitch")]
+pub mod twitch;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::time::Instant;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use crate::task::ChatMsg;
+
+/// One chat as received from a platform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatMessage {
+    pub text: String,
+    pub author: Option<String>,
+    /// When the chat was received, or sent if the platform says so.
+    pub timestamp: Instant,
+    /// Name of the platform, e.g. `"twitch"`.
+    pub platform: &'static str,
+    /// Channel, room or topic the chat was posted to.
+    pub channel: Option<String>,
+    /// Platform-specific fields, such as message ids or badges.
+    pub metadata: HashMap<String, String>,
+}
+
+impl ChatMessage {
+    pub fn new(platform: &'static str, text: impl Into<String>, timestamp: Instant) -> Self {
+        Self {
+            text: text.into(),
+            author: None,
+            timestamp,
+            platform,
+            channel: None,
+            metadata: HashMap::new(),
+        }
+    }
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl<D> From<ChatMessage> for ChatMsg<D> {
+    fn from(message: ChatMessage) -> Self {
+        ChatMsg {
+            chat: message.text,
+            ts: message.timestamp,
+            author: message.author,
+            data: None,
+        }
+    }
+}
+
+/// An asynchronous source of chats, such as a platform connection.
+pub trait ChatSource {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Wait for the next chat; `None` once the source is closed.
+    fn next_message(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<ChatMessage>, Self::Error>> + Send;
+}
+
+/// Chats handed over by another task.
+impl ChatSource for Receiver<ChatMessage> {
+    type Error = Infallible;
+
+    async fn next_message(&mut self) -> Result<Option<ChatMessage>, Infallible> {
+        Ok(self.recv().await)
+    }
+}
+
+/// Feed every chat of `source` to a detector task until the source closes
+/// or the task stops.
+pub async fn forward<Src: ChatSource, D>(
+    source: &mut Src,
+    chats: &Sender<ChatMsg<D>>,
+) -> Result<(), Src::Error> {
+    while let Some(message) = source.next_message().await? {
+        if chats.send(message.into()).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::DetectorTask;
+    use crate::{ChatSpikeDetector, OwnedEvent};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn forward_source_into_task() {
+        let (tx, mut source) = mpsc::channel(16);
+        let (chats, mut events) = DetectorTask::new(ChatSpikeDetector::<5, 100>::default()).spawn();
+        let feeder = tokio::spawn(async move {
+            let start = Instant::now();
+            for i in 0..220u64 {
+                let gap = if i < 200 {
+                    500 * i
+                } else {
+                    100_000 + 10 * (i - 200)
+                };
+                let message = ChatMessage::new("test", "pog", start + Duration::from_millis(gap))
+                    .with_author(format!("viewer{i}"))
+                    .with_metadata("id", i.to_string());
+                tx.send(message).await.unwrap();
+            }
+        });
+        forward(&mut source, &chats).await.unwrap();
+        feeder.await.unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(OwnedEvent::SpikeBegin { summary: Some(s), .. }) if s == "pog"
+        ));
+    }
+}
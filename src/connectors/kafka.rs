@@ -0,0 +1,409 @@
+//! Chats consumed from a Kafka (or Redpanda) topic of JSON records.
+//!
+//! Each record is a JSON object; a [`FieldMapping`] says where its text,
+//! author, timestamp and channel are. Records without text are skipped.
+//!
+//! Offsets are not committed automatically. Once every record handed out
+//! so far has reached the detector, take a
+//! [`checkpoint`](KafkaCommitter::checkpoint) along with its state, e.g. a
+//! [`DictSnapshot`](crate::dict::DictSnapshot), and
+//! [`commit`](KafkaCommitter::commit) it once that state is persisted: a
+//! restart then resumes from the first record the checkpoint does not
+//! cover. A [`KafkaCommitter`] does both while the source itself is lent to
+//! [`forward`](super::forward). Partitions revoked by a rebalance forget
+//! their uncommitted offsets, so their new owner replays the records since
+//! the last checkpoint.
+//!
+//! ```no_run
+//! use chat_spike::connectors::ChatSource;
+//! use chat_spike::connectors::kafka::{FieldMapping, KafkaSource};
+//!
+//! # async fn run() -> Result<(), rdkafka::error::KafkaError> {
+//! let mut kafka = KafkaSource::new("localhost:9092", "chat-spike", &["chat"])?
+//!     .with_mapping(FieldMapping::default().with_author("user.name"));
+//! let committer = kafka.committer();
+//! while let Some(message) = kafka.next_message().await? {
+//!     println!("{}", message.text);
+//!     // Feed the detector, and now and then:
+//!     let token = committer.checkpoint();
+//!     // ...persist the detector state, then:
+//!     committer.commit(token)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer};
+use rdkafka::error::{KafkaError, KafkaResult};
+use rdkafka::{ClientConfig, ClientContext, Message, Offset, TopicPartitionList};
+use serde_json::Value;
+
+use super::{ChatMessage, ChatSource};
+
+/// Where the fields of a chat are in a JSON record.
+///
+/// Paths are dot-separated keys into nested objects, e.g. `user.name`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMapping {
+    text: String,
+    author: Option<String>,
+    timestamp: Option<String>,
+    channel: Option<String>,
+    metadata: Vec<(String, String)>,
+}
+
+impl Default for FieldMapping {
+    /// `text`, `author`, `timestamp` and `channel` at the top level.
+    fn default() -> Self {
+        Self {
+            text: "text".to_owned(),
+            author: Some("author".to_owned()),
+            timestamp: Some("timestamp".to_owned()),
+            channel: Some("channel".to_owned()),
+            metadata: Vec::new(),
+        }
+    }
+}
+
+impl FieldMapping {
+    pub fn with_text(mut self, path: impl Into<String>) -> Self {
+        self.text = path.into();
+        self
+    }
+    pub fn with_author(mut self, path: impl Into<String>) -> Self {
+        self.author = Some(path.into());
+        self
+    }
+    /// Milliseconds since the Unix epoch. Records without one are stamped
+    /// with the Kafka timestamp, or else when they were received.
+    pub fn with_timestamp(mut self, path: impl Into<String>) -> Self {
+        self.timestamp = Some(path.into());
+        self
+    }
+    /// Channel of the chat; the topic name if missing.
+    pub fn with_channel(mut self, path: impl Into<String>) -> Self {
+        self.channel = Some(path.into());
+        self
+    }
+    /// Also copy the field at `path` into the metadata as `key`.
+    pub fn with_metadata(mut self, key: impl Into<String>, path: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), path.into()));
+        self
+    }
+    /// Build a chat from a JSON record; `None` if it has no text.
+    /// `fallback_ms` stamps a record without a timestamp field.
+    pub fn to_message(&self, record: &[u8], fallback_ms: Option<i64>) -> Option<ChatMessage> {
+        let record: Value = serde_json::from_slice(record).ok()?;
+        let field = |path: &Option<String>| lookup(&record, path.as_deref()?);
+        let text = lookup(&record, &self.text)?;
+        let millis = field(&self.timestamp)
+            .and_then(|ts| ts.parse().ok())
+            .or(fallback_ms);
+        let timestamp = millis.map_or_else(Instant::now, instant_from_unix_millis);
+        let mut message = ChatMessage::new("kafka", text, timestamp);
+        message.author = field(&self.author);
+        message.channel = field(&self.channel);
+        for (key, path) in &self.metadata {
+            if let Some(value) = lookup(&record, path) {
+                message.metadata.insert(key.clone(), value);
+            }
+        }
+        Some(message)
+    }
+}
+
+/// The field at a dotted `path`, with numbers and booleans as text.
+fn lookup(record: &Value, path: &str) -> Option<String> {
+    let value = path
+        .split('.')
+        .try_fold(record, |value, key| value.get(key))?;
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// The `Instant` a Unix timestamp corresponds to on this machine's clock.
+fn instant_from_unix_millis(millis: i64) -> Instant {
+    let now = Instant::now();
+    let Some(since_epoch) = SystemTime::now().duration_since(UNIX_EPOCH).ok() else {
+        return now;
+    };
+    let then = Duration::from_millis(millis.max(0) as u64);
+    match since_epoch.checked_sub(then) {
+        Some(age) => now.checked_sub(age).unwrap_or(now),
+        None => now + (then - since_epoch),
+    }
+}
+
+/// Next offset to commit per partition, for the records handed out since
+/// the last checkpoint.
+#[derive(Clone, Debug, Default)]
+struct Offsets(Arc<Mutex<HashMap<(String, i32), i64>>>);
+
+/// Offsets of the records a checkpoint covers, to
+/// [`commit`](KafkaCommitter::commit) once the state covering them is
+/// persisted.
+#[derive(Debug)]
+#[must_use]
+pub struct OffsetToken(HashMap<(String, i32), i64>);
+
+impl OffsetToken {
+    /// Whether the checkpoint covers no new records.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn to_list(&self) -> KafkaResult<TopicPartitionList> {
+        let mut list = TopicPartitionList::new();
+        for ((topic, partition), &offset) in &self.0 {
+            list.add_partition_offset(topic, *partition, Offset::Offset(offset))?;
+        }
+        Ok(list)
+    }
+}
+
+impl Offsets {
+    fn record(&self, topic: &str, partition: i32, offset: i64) {
+        let mut offsets = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        offsets.insert((topic.to_owned(), partition), offset + 1);
+    }
+    fn revoke(&self, partitions: &TopicPartitionList) {
+        let mut offsets = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for elem in partitions.elements() {
+            offsets.remove(&(elem.topic().to_owned(), elem.partition()));
+        }
+    }
+    /// Partition offsets to commit, clearing them.
+    fn take(&self) -> OffsetToken {
+        OffsetToken(std::mem::take(
+            &mut *self.0.lock().unwrap_or_else(PoisonError::into_inner),
+        ))
+    }
+    /// Put back the offsets of a checkpoint that failed to commit, for the
+    /// partitions `keep` accepts, unless newer ones were recorded since.
+    fn restore(&self, token: OffsetToken, keep: impl Fn(&str, i32) -> bool) {
+        let mut offsets = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for ((topic, partition), offset) in token.0 {
+            if keep(&topic, partition) {
+                let next = offsets.entry((topic, partition)).or_insert(offset);
+                *next = (*next).max(offset);
+            }
+        }
+    }
+}
+
+/// Consumer context that drops the pending offsets of revoked partitions.
+struct RebalanceContext {
+    offsets: Offsets,
+}
+
+impl ClientContext for RebalanceContext {}
+
+impl ConsumerContext for RebalanceContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance<'_>) {
+        if let Rebalance::Revoke(partitions) = rebalance {
+            self.offsets.revoke(partitions);
+        }
+    }
+}
+
+/// Chats of the subscribed topics, see the [module docs](self).
+pub struct KafkaSource {
+    consumer: Arc<StreamConsumer<RebalanceContext>>,
+    mapping: FieldMapping,
+    offsets: Offsets,
+    skipped: u64,
+}
+
+/// Takes and commits the checkpoints of a [`KafkaSource`], also while the
+/// source is busy.
+#[derive(Clone)]
+pub struct KafkaCommitter {
+    consumer: Arc<StreamConsumer<RebalanceContext>>,
+    offsets: Offsets,
+}
+
+impl KafkaCommitter {
+    /// Take the offsets of every record handed out since the last
+    /// checkpoint. Take it when the detector state about to be persisted
+    /// covers those records.
+    pub fn checkpoint(&self) -> OffsetToken {
+        self.offsets.take()
+    }
+    /// Commit a checkpoint once the state it covers is persisted. If the
+    /// commit fails, its offsets go back to the next checkpoint.
+    pub fn commit(&self, token: OffsetToken) -> KafkaResult<()> {
+        if token.is_empty() {
+            return Ok(());
+        }
+        let result = token
+            .to_list()
+            .and_then(|list| self.consumer.commit(&list, CommitMode::Sync));
+        if result.is_err() {
+            // Partitions revoked meanwhile belong to another consumer now.
+            let assigned = self.consumer.assignment().ok();
+            self.offsets.restore(token, |topic, partition| {
+                assigned
+                    .as_ref()
+                    .is_none_or(|a| a.find_partition(topic, partition).is_some())
+            });
+        }
+        result
+    }
+}
+
+impl KafkaSource {
+    /// Join consumer group `group_id` on `brokers` and subscribe to
+    /// `topics`, starting from the earliest record of partitions the group
+    /// has no offset for.
+    pub fn new(brokers: &str, group_id: &str, topics: &[&str]) -> KafkaResult<Self> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id)
+            .set("auto.offset.reset", "earliest");
+        Self::from_config(config, topics)
+    }
+    /// Subscribe to `topics` with a custom client configuration, e.g. for
+    /// TLS or SASL. Automatic commits are turned off.
+    pub fn from_config(mut config: ClientConfig, topics: &[&str]) -> KafkaResult<Self> {
+        let offsets = Offsets::default();
+        let context = RebalanceContext {
+            offsets: offsets.clone(),
+        };
+        let consumer: StreamConsumer<_> = config
+            .set("enable.auto.commit", "false")
+            .create_with_context(context)?;
+        consumer.subscribe(topics)?;
+        Ok(Self {
+            consumer: Arc::new(consumer),
+            mapping: FieldMapping::default(),
+            offsets,
+            skipped: 0,
+        })
+    }
+    pub fn with_mapping(mut self, mapping: FieldMapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+    /// Handle for checkpointing this source, see the [module docs](self).
+    pub fn committer(&self) -> KafkaCommitter {
+        KafkaCommitter {
+            consumer: self.consumer.clone(),
+            offsets: self.offsets.clone(),
+        }
+    }
+    /// Records skipped for having no text or not being JSON.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+impl ChatSource for KafkaSource {
+    type Error = KafkaError;
+
+    /// Wait for the next chat record. The subscription never closes on its
+    /// own, so this only ends with an error.
+    async fn next_message(&mut self) -> KafkaResult<Option<ChatMessage>> {
+        loop {
+            let record = self.consumer.recv().await?;
+            let (topic, partition, offset) = (record.topic(), record.partition(), record.offset());
+            self.offsets.record(topic, partition, offset);
+            let message = record.payload().and_then(|payload| {
+                self.mapping
+                    .to_message(payload, record.timestamp().to_millis())
+            });
+            let Some(mut message) = message else {
+                self.skipped += 1;
+                continue;
+            };
+            message.channel.get_or_insert_with(|| topic.to_owned());
+            message
+                .metadata
+                .insert("topic".to_owned(), topic.to_owned());
+            message
+                .metadata
+                .insert("partition".to_owned(), partition.to_string());
+            message
+                .metadata
+                .insert("offset".to_owned(), offset.to_string());
+            return Ok(Some(message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_json_fields() {
+        let mapping = FieldMapping::default()
+            .with_text("body.text")
+            .with_author("user.id")
+            .with_metadata("lang", "body.lang");
+        let record = br#"{"body": {"text": "pog", "lang": "en"}, "user": {"id": 42},
+            "timestamp": 1700000000000, "channel": "streamer"}"#;
+        let message = mapping.to_message(record, None).unwrap();
+        assert_eq!(message.text, "pog");
+        assert_eq!(message.author.as_deref(), Some("42"));
+        assert_eq!(message.channel.as_deref(), Some("streamer"));
+        assert_eq!(message.metadata["lang"], "en");
+        assert!(message.timestamp < Instant::now() - Duration::from_secs(3600));
+
+        let recent = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let message = mapping.to_message(br#"{"body": {"text": "kek"}}"#, Some(recent));
+        assert!(message.unwrap().timestamp.elapsed() < Duration::from_secs(60));
+        assert!(
+            mapping
+                .to_message(br#"{"text": "wrong path"}"#, None)
+                .is_none()
+        );
+        assert!(mapping.to_message(b"not json", None).is_none());
+    }
+
+    #[test]
+    fn revoked_partitions_drop_pending_offsets() {
+        let offsets = Offsets::default();
+        offsets.record("chat", 0, 10);
+        offsets.record("chat", 1, 5);
+        offsets.record("chat", 0, 11);
+        let mut revoked = TopicPartitionList::new();
+        revoked.add_partition("chat", 1);
+        offsets.revoke(&revoked);
+        let list = offsets.take().to_list().unwrap();
+        assert_eq!(list.count(), 1);
+        assert_eq!(
+            list.find_partition("chat", 0).unwrap().offset(),
+            Offset::Offset(12)
+        );
+        assert!(offsets.take().is_empty());
+    }
+
+    #[test]
+    fn failed_checkpoint_restores_offsets() {
+        let offsets = Offsets::default();
+        offsets.record("chat", 0, 10);
+        offsets.record("chat", 1, 5);
+        offsets.record("chat", 2, 7);
+        let token = offsets.take();
+        offsets.record("chat", 0, 20);
+        // Partition 2 was revoked before the commit failed.
+        offsets.restore(token, |_, partition| partition != 2);
+        let mut restored: Vec<_> = offsets.take().0.into_iter().collect();
+        restored.sort();
+        assert_eq!(
+            restored,
+            [(("chat".to_owned(), 0), 21), (("chat".to_owned(), 1), 6)]
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Twitch chat over IRC.
+//!
+//! [`TwitchSource`] logs in anonymously or with an OAuth token, joins
+//! channels and yields their `PRIVMSG` chats and `USERNOTICE`s such as
+//! resubscriptions. A `USERNOTICE` without a user message, e.g. a raid,
+//! takes its text from the `system-msg` tag. IRCv3 tags end up in
+//! [`ChatMessage::metadata`]; the author is the display name if the tags
+//! have one, else the login.
+//!
+//! ```no_run
+//! use chat_spike::connectors::ChatSource;
+//! use chat_spike::connectors::twitch::{TwitchAuth, TwitchSource};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut twitch = TwitchSource::connect(TwitchAuth::Anonymous, ["somechannel"]).await?;
+//! while let Some(message) = twitch.next_message().await? {
+//!     println!("{}: {}", message.author.unwrap_or_default(), message.text);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf,
+};
+use tokio::net::TcpStream;
+
+use super::{ChatMessage, ChatSource};
+
+/// Plain-text IRC endpoint of Twitch chat.
+pub const ADDRESS: &str = "irc.chat.twitch.tv:6667";
+/// TLS IRC endpoint of Twitch chat, for [`TwitchAuth::Token`] logins.
+pub const TLS_ADDRESS: &str = "irc.chat.twitch.tv:6697";
+
+/// How to log in to Twitch chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TwitchAuth {
+    /// Read-only guest login.
+    Anonymous,
+    /// Log in as `login` with a chat OAuth token, with or without its
+    /// `oauth:` prefix. The token is sent as is, so connect to
+    /// [`TLS_ADDRESS`] through a TLS library and pass the stream to
+    /// [`TwitchSource::with_stream`].
+    Token { login: String, token: String },
+}
+
+/// Chats of the joined Twitch channels, see the [module docs](self).
+///
+/// `PING`s are answered while waiting for a chat. The source closes when
+/// the server does or asks to reconnect.
+pub struct TwitchSource<T = TcpStream> {
+    reader: BufReader<ReadHalf<T>>,
+    writer: WriteHalf<T>,
+    line: String,
+}
+
+impl TwitchSource {
+    /// Connect to [`ADDRESS`], log in and join `channels`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] for a
+    /// [`Token`](TwitchAuth::Token) login, which would go out in the clear.
+    pub async fn connect(
+        auth: TwitchAuth,
+        channels: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> io::Result<Self> {
+        if matches!(auth, TwitchAuth::Token { .. }) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Twitch token logins need TLS, see TwitchSource::with_stream",
+            ));
+        }
+        Self::with_stream(TcpStream::connect(ADDRESS).await?, auth, channels).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Send> TwitchSource<T> {
+    /// Log in and join `channels` over an established connection, e.g. a
+    /// TLS stream.
+    pub async fn with_stream(
+        stream: T,
+        auth: TwitchAuth,
+        channels: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> io::Result<Self> {
+        let (reader, writer) = tokio::io::split(stream);
+        let mut source = Self {
+            reader: BufReader::new(reader),
+            writer,
+            line: String::new(),
+        };
+        source
+            .send("CAP REQ :twitch.tv/tags twitch.tv/commands")
+            .await?;
+        match auth {
+            TwitchAuth::Anonymous => {
+                let nonce = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.subsec_nanos() % 100_000);
+                source.send(&format!("NICK justinfan{nonce}")).await?;
+            }
+            TwitchAuth::Token { login, token } => {
+                let token = token.strip_prefix("oauth:").unwrap_or(&token);
+                source.send(&format!("PASS oauth:{token}")).await?;
+                source
+                    .send(&format!("NICK {}", login.to_lowercase()))
+                    .await?;
+            }
+        }
+        for channel in channels {
+            source.join(channel.as_ref()).await?;
+        }
+        Ok(source)
+    }
+    /// Join another channel, with or without its leading `#`.
+    pub async fn join(&mut self, channel: &str) -> io::Result<()> {
+        self.send(&format!("JOIN #{}", channel_name(channel))).await
+    }
+    pub async fn part(&mut self, channel: &str) -> io::Result<()> {
+        self.send(&format!("PART #{}", channel_name(channel))).await
+    }
+    async fn send(&mut self, line: &str) -> io::Result<()> {
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.write_all(b"\r\n").await?;
+        self.writer.flush().await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Send> ChatSource for TwitchSource<T> {
+    type Error = io::Error;
+
+    async fn next_message(&mut self) -> io::Result<Option<ChatMessage>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line).await? == 0 {
+                return Ok(None);
+            }
+            let Some(line) = IrcLine::parse(self.line.trim_end_matches(['\r', '\n'])) else {
+                continue;
+            };
+            match line.command {
+                "PRIVMSG" | "USERNOTICE" => {
+                    if let Some(message) = line.to_message(Instant::now()) {
+                        return Ok(Some(message));
+                    }
+                }
+                "PING" => {
+                    let pong = format!("PONG :{}", line.trailing.unwrap_or("tmi.twitch.tv"));
+                    self.send(&pong).await?;
+                }
+                "NOTICE"
+                    if line
+                        .trailing
+                        .is_some_and(|t| t.contains("authentication failed")) =>
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "Twitch login authentication failed",
+                    ));
+                }
+                "RECONNECT" => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn channel_name(channel: &str) -> String {
+    channel.trim_start_matches('#').to_lowercase()
+}
+
+/// One IRC line, with IRCv3 tags.
+#[derive(Debug, PartialEq)]
+struct IrcLine<'a> {
+    tags: Option<&'a str>,
+    prefix: Option<&'a str>,
+    command: &'a str,
+    /// First middle parameter, the channel for chat commands.
+    target: Option<&'a str>,
+    trailing: Option<&'a str>,
+}
+
+impl<'a> IrcLine<'a> {
+    fn parse(mut line: &'a str) -> Option<Self> {
+        let mut tags = None;
+        if let Some(rest) = line.strip_prefix('@') {
+            let (t, rest) = rest.split_once(' ')?;
+            tags = Some(t);
+            line = rest;
+        }
+        let mut prefix = None;
+        if let Some(rest) = line.strip_prefix(':') {
+            let (p, rest) = rest.split_once(' ')?;
+            prefix = Some(p);
+            line = rest;
+        }
+        let (params, trailing) = match line.split_once(" :") {
+            Some((params, trailing)) => (params, Some(trailing)),
+            None => (line, None),
+        };
+        let mut params = params.split(' ').filter(|p| !p.is_empty());
+        Some(Self {
+            tags,
+            prefix,
+            command: params.next()?,
+            target: params.next(),
+            trailing,
+        })
+    }
+    fn tags(&self) -> impl Iterator<Item = (&'a str, String)> {
+        self.tags
+            .into_iter()
+            .flat_map(|tags| tags.split(';'))
+            .map(|tag| {
+                let (key, value) = tag.split_once('=').unwrap_or((tag, ""));
+                (key, unescape_tag(value))
+            })
+    }
+    /// The chat on this line, or `None` if it has no text.
+    fn to_message(&self, timestamp: Instant) -> Option<ChatMessage> {
+        let metadata: HashMap<_, _> = self.tags().map(|(k, v)| (k.to_owned(), v)).collect();
+        let mut text = self.trailing.unwrap_or_default();
+        // `/me` chats.
+        if let Some(action) = text
+            .strip_prefix("\u{1}ACTION ")
+            .and_then(|t| t.strip_suffix('\u{1}'))
+        {
+            text = action;
+        }
+        if text.is_empty() && self.command == "USERNOTICE" {
+            text = metadata.get("system-msg").map_or("", String::as_str);
+        }
+        if text.is_empty() {
+            return None;
+        }
+        let mut message = ChatMessage::new("twitch", text, timestamp);
+        message.channel = self.target.map(channel_name);
+        message.metadata = metadata;
+        message
+            .metadata
+            .insert("command".to_owned(), self.command.to_owned());
+        message.author = ["display-name", "login"]
+            .iter()
+            .filter_map(|key| message.metadata.get(*key))
+            .find(|name| !name.is_empty())
+            .cloned()
+            .or_else(|| {
+                let (nick, _) = self.prefix?.split_once('!')?;
+                Some(nick.to_owned())
+            });
+        Some(message)
+    }
+}
+
+/// Undo the escaping of an IRCv3 tag value.
+fn unescape_tag(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some(':') => out.push(';'),
+            Some('s') => out.push(' '),
+            Some('r') => out.push('\r'),
+            Some('n') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_privmsg_and_usernotice() {
+        let now = Instant::now();
+        let line = "@badges=subscriber/12;display-name=Viewer_1;tmi-sent-ts=1700000000000 \
+                    :viewer_1!viewer_1@viewer_1.tmi.twitch.tv PRIVMSG #streamer :\u{1}ACTION waves\u{1}";
+        let message = IrcLine::parse(line).unwrap().to_message(now).unwrap();
+        assert_eq!(message.text, "waves");
+        assert_eq!(message.author.as_deref(), Some("Viewer_1"));
+        assert_eq!(message.channel.as_deref(), Some("streamer"));
+        assert_eq!(message.metadata["badges"], "subscriber/12");
+        assert_eq!(message.metadata["command"], "PRIVMSG");
+
+        let line = "@display-name=;login=viewer_2;msg-id=resub;system-msg=Viewer\\ssubscribed\\:\\s2\\smonths \
+                    :tmi.twitch.tv USERNOTICE #streamer";
+        let message = IrcLine::parse(line).unwrap().to_message(now).unwrap();
+        assert_eq!(message.text, "Viewer subscribed; 2 months");
+        assert_eq!(message.author.as_deref(), Some("viewer_2"));
+        assert_eq!(message.metadata["msg-id"], "resub");
+
+        let line = "@login=viewer_3 :tmi.twitch.tv USERNOTICE #streamer";
+        assert!(IrcLine::parse(line).unwrap().to_message(now).is_none());
+    }
+
+    #[tokio::test]
+    async fn connect_refuses_cleartext_tokens() {
+        let auth = TwitchAuth::Token {
+            login: "bot".to_owned(),
+            token: "secret".to_owned(),
+        };
+        let err = TwitchSource::connect(auth, ["streamer"])
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn login_join_and_answer_pings() {
+        let (client, server) = tokio::io::duplex(4096);
+        let auth = TwitchAuth::Token {
+            login: "Bot".to_owned(),
+            token: "oauth:secret".to_owned(),
+        };
+        let mut twitch = TwitchSource::with_stream(client, auth, ["#Streamer"])
+            .await
+            .unwrap();
+        let (server_read, mut server_write) = tokio::io::split(server);
+        server_write
+            .write_all(b"PING :tmi.twitch.tv\r\n:bot!bot@bot PRIVMSG #streamer :hi\r\n")
+            .await
+            .unwrap();
+        let message = twitch.next_message().await.unwrap().unwrap();
+        assert_eq!(message.text, "hi");
+        assert_eq!(message.author.as_deref(), Some("bot"));
+        drop(server_write);
+        drop(twitch);
+        let mut sent = Vec::new();
+        let mut lines = BufReader::new(server_read).lines();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            sent.push(line);
+        }
+        assert_eq!(
+            sent,
+            [
+                "CAP REQ :twitch.tv/tags twitch.tv/commands",
+                "PASS oauth:secret",
+                "NICK bot",
+                "JOIN #streamer",
+                "PONG :tmi.twitch.tv",
+            ]
+        );
+    }
+}
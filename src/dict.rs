@@ -0,0 +1,969 @@
+//! Token statistics backing the TF-IDF-like weights of `ChatWindow`.
+//!
+//! Counts are indexed by chat sequence number rather than wall time, and
+//! decay by `1 − 1/L` per chat so that `L` acts as the long horizon.
+//! [`TimeDecayDictionary`] decays by wall time instead.
+
+mod background;
+mod bloom;
+mod hashed;
+mod layered;
+#[cfg(feature = "redis")]
+mod redis;
+mod shards;
+mod sketch;
+#[cfg(feature = "sled")]
+mod sled;
+mod time;
+
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::intern::{Interner, Symbol, SymbolMap};
+use crate::math::Float;
+use crate::text::{char_ngrams_iter, normalize};
+
+pub use self::background::BackgroundDictionary;
+use self::bloom::Bloom;
+pub use self::hashed::{HashedDictionary, token_fingerprint};
+pub use self::layered::LayeredDictionary;
+#[cfg(feature = "redis")]
+pub use self::redis::RedisDictionary;
+pub use self::shards::DictionaryShards;
+pub use self::sketch::SketchDictionary;
+#[cfg(feature = "sled")]
+pub use self::sled::SledDictionary;
+pub use self::time::TimeDecayDictionary;
+
+/// Store of exponentially decayed token counts.
+///
+/// Tokens are looked up by `&K`, text by default. Backends keyed by
+/// something cheaper, such as the fingerprints of [`FingerprintKey`], plug
+/// into a `ChatWindow` with the matching [`TokenKey`].
+pub trait Dictionary<K: ?Sized = str> {
+    /// Record one occurrence of `token` in the chat numbered `idx`.
+    fn observe(&mut self, token: &K, idx: u32);
+    /// Decayed occurrence count of `token` as seen from chat `idx`.
+    fn count(&self, token: &K, idx: u32) -> f64;
+    /// Undo an earlier `observe(token, idx)`, e.g. for a deleted chat.
+    fn retract(&mut self, token: &K, idx: u32);
+    /// Record an occurrence counting as `weight` occurrences, e.g. for chats
+    /// from trusted users. Backends without fractional counts record a
+    /// single occurrence. `retract` only takes back a weight of one.
+    fn observe_weighted(&mut self, token: &K, idx: u32, weight: f64) {
+        let _ = weight;
+        self.observe(token, idx);
+    }
+    /// [`observe_weighted`](Self::observe_weighted) for a chat received at
+    /// `ts`. Backends decaying with wall time go by `ts` instead of `idx`.
+    fn observe_timed(&mut self, token: &K, idx: u32, ts: Instant, weight: f64) {
+        let _ = ts;
+        self.observe_weighted(token, idx, weight);
+    }
+    /// [`count`](Self::count) as seen from a chat received at `ts`.
+    fn count_timed(&self, token: &K, idx: u32, ts: Instant) -> f64 {
+        let _ = ts;
+        self.count(token, idx)
+    }
+    /// [`retract`](Self::retract) of an observation made at `ts`.
+    fn retract_timed(&mut self, token: &K, idx: u32, ts: Instant) {
+        let _ = ts;
+        self.retract(token, idx);
+    }
+    /// Shared handle to the backend's own copy of `token`, for backends
+    /// that intern their keys, so that `ChatWindow` holds the same
+    /// allocation instead of one of its own.
+    fn intern(&mut self, token: &str) -> Option<Arc<str>> {
+        let _ = token;
+        None
+    }
+    /// Hold off housekeeping such as vacuuming until
+    /// [`end_batch`](Self::end_batch), e.g. while backfilling a log.
+    fn begin_batch(&mut self) {}
+    /// Run the housekeeping deferred since
+    /// [`begin_batch`](Self::begin_batch).
+    fn end_batch(&mut self) {}
+    /// Number of distinct tokens stored, if the backend can tell.
+    fn len(&self) -> Option<usize> {
+        None
+    }
+    fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+    /// Rough heap usage in bytes, if the backend keeps its data in memory.
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        None
+    }
+    /// The `n` tokens with the highest decayed counts as of the newest chat,
+    /// highest first. Empty if the backend cannot list its tokens as text.
+    fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        let _ = n;
+        Vec::new()
+    }
+    /// How recently `token` first appeared, from 1 (just now) towards 0,
+    /// or `None` if unknown or not tracked.
+    fn novelty(&self, token: &K) -> Option<f64> {
+        let _ = token;
+        None
+    }
+    /// Every enumerable token with its idf weight `ln(long_horizon / count)`
+    /// as of the newest chat, most common token first. `ChatWindow` uses
+    /// its own `L` as `long_horizon`.
+    fn export_idf(&self, long_horizon: usize) -> Vec<(String, f64)> {
+        self.top_n(usize::MAX)
+            .into_iter()
+            .map(|(token, count)| (token, (long_horizon as f64 / count).ln()))
+            .collect()
+    }
+}
+
+/// How `ChatWindow` presents its tokens to a [`Dictionary<Self::Key>`].
+pub trait TokenKey {
+    type Key: ?Sized;
+    /// Whether keys are fingerprints, which `ChatWindow` then computes once
+    /// per token at push time and keeps in place of the text.
+    const FINGERPRINTED: bool = false;
+    /// Call `f` with the key of `token`.
+    fn with_key<R>(token: &str, f: impl FnOnce(&Self::Key) -> R) -> R;
+    /// Call `f` with the key of an opaque token known only by its
+    /// [`fingerprint`](crate::tokenizer::Token::fingerprint).
+    fn with_opaque<R>(fingerprint: u64, f: impl FnOnce(&Self::Key) -> R) -> R;
+}
+
+/// Tokens are keyed by their text.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TextKey;
+
+impl TokenKey for TextKey {
+    type Key = str;
+    fn with_key<R>(token: &str, f: impl FnOnce(&str) -> R) -> R {
+        f(token)
+    }
+    /// Keys the fingerprint by its hex text, rendered on the stack.
+    fn with_opaque<R>(fingerprint: u64, f: impl FnOnce(&str) -> R) -> R {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        let mut hex = [0; 16];
+        let mut start = hex.len();
+        let mut rest = fingerprint;
+        loop {
+            start -= 1;
+            hex[start] = DIGITS[(rest & 0xf) as usize];
+            rest >>= 4;
+            if rest == 0 {
+                break;
+            }
+        }
+        f(std::str::from_utf8(&hex[start..]).expect("hex digits are ASCII"))
+    }
+}
+
+/// Tokens are keyed by their [`token_fingerprint`], for dictionaries indexed
+/// by integers such as [`HashedDictionary`]. Windows hash each token once
+/// when its chat is pushed and keep only the fingerprint, unless the
+/// dictionary [interns](Dictionary::intern) the text, so their summaries
+/// show tokens in hex.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FingerprintKey;
+
+impl TokenKey for FingerprintKey {
+    type Key = u64;
+    const FINGERPRINTED: bool = true;
+    fn with_key<R>(token: &str, f: impl FnOnce(&u64) -> R) -> R {
+        f(&token_fingerprint(token))
+    }
+    fn with_opaque<R>(fingerprint: u64, f: impl FnOnce(&u64) -> R) -> R {
+        f(&fingerprint)
+    }
+}
+
+/// The `n` highest counts, highest first.
+fn top_counts<'a>(counts: impl Iterator<Item = (&'a str, f64)>, n: usize) -> Vec<(String, f64)> {
+    let mut counts: Vec<_> = counts.collect();
+    counts.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+    counts.truncate(n);
+    counts
+        .into_iter()
+        .map(|(token, count)| (token.to_owned(), count))
+        .collect()
+}
+
+/// Decayed count of one token; `F` is the float type the count is stored
+/// in.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TokenStats<F = f64> {
+    count: F,
+    last_idx: u32,
+}
+
+impl<F: Float> TokenStats<F> {
+    fn decayed<const L: usize>(&self, idx: u32) -> f64 {
+        let num_gap = idx.saturating_sub(self.last_idx) as f64;
+        if num_gap < 10. * L as f64 {
+            self.count.as_f64() * (1. - 1. / (L as f64)).powf(num_gap)
+        } else {
+            0.
+        }
+    }
+    /// Stats after `weight` more occurrences in chat `idx`.
+    fn observed<const L: usize>(&self, idx: u32, weight: f64) -> Self {
+        if idx < self.last_idx {
+            // A late observation, e.g. from another instance sharing a store.
+            let late = Self::from_f64(weight, idx).decayed::<L>(self.last_idx);
+            return Self::from_f64(self.count.as_f64() + late, self.last_idx);
+        }
+        Self {
+            count: F::from_f64_lossy(self.decayed::<L>(idx) + weight),
+            last_idx: idx,
+        }
+    }
+    fn from_f64(count: f64, last_idx: u32) -> Self {
+        Self {
+            count: F::from_f64_lossy(count),
+            last_idx,
+        }
+    }
+    /// Stats with the occurrence in chat `idx` taken back, or `None` once
+    /// nothing is left.
+    fn retracted<const L: usize>(&self, idx: u32) -> Option<Self> {
+        if idx > self.last_idx {
+            return Some(*self);
+        }
+        let share = Self::from_f64(1., idx).decayed::<L>(self.last_idx);
+        let count = self.count.as_f64() - share;
+        (count > 1e-9).then(|| Self::from_f64(count, self.last_idx))
+    }
+}
+
+impl TokenStats {
+    /// Fixed-size encoding used by the persistent backends.
+    #[cfg(any(feature = "redis", feature = "sled"))]
+    fn to_bytes(self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[..8].copy_from_slice(&self.count.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.last_idx.to_le_bytes());
+        bytes
+    }
+    #[cfg(any(feature = "redis", feature = "sled"))]
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            count: f64::from_le_bytes(bytes.get(..8)?.try_into().ok()?),
+            last_idx: u32::from_le_bytes(bytes.get(8..12)?.try_into().ok()?),
+        })
+    }
+}
+
+/// When `MemoryDictionary` drops tokens whose counts decayed to nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct VacuumPolicy {
+    /// No vacuuming below this many entries.
+    min_size: usize,
+    /// Vacuum again once the table grows by this factor since the last run.
+    growth_ratio: f64,
+    /// Tokens with a decayed count below this are dropped.
+    sigma: f64,
+}
+
+impl Default for VacuumPolicy {
+    fn default() -> Self {
+        Self {
+            min_size: 1 << 16,
+            growth_ratio: 1.5,
+            sigma: 1e-3,
+        }
+    }
+}
+
+/// Hash builder [`MemoryDictionary`] uses unless told otherwise: SipHash,
+/// or ahash with the `fast-hash` feature.
+#[cfg(not(feature = "fast-hash"))]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+#[cfg(feature = "fast-hash")]
+pub type DefaultHashBuilder = ahash::RandomState;
+
+/// In-memory dictionary with a long horizon of `L` chats.
+///
+/// Each token is interned once and its counts are keyed by the
+/// [`Symbol`]; `ChatWindow` shares the interned text through
+/// [`Dictionary::intern`].
+///
+/// `H` builds the hasher of the text-to-symbol table. SipHash resists hash
+/// flooding from crafted chats but is a measurable cost at high token
+/// rates; a faster hasher such as ahash or FxHash can be plugged in
+/// instead.
+///
+/// `F` is the float type counts are stored in; `f32` halves the count's
+/// share of each entry at the cost of precision.
+#[derive(Clone, Default, Debug)]
+pub struct MemoryDictionary<const L: usize, H = DefaultHashBuilder, F = f64> {
+    interner: Interner<H>,
+    entries: SymbolMap<TokenStats<F>>,
+    /// Interner size that triggers the next compaction.
+    compact_at: usize,
+    max_entries: Option<usize>,
+    vacuum_policy: VacuumPolicy,
+    /// Table size that triggers the next vacuum.
+    vacuum_at: usize,
+    /// Newest chat index observed.
+    newest_idx: u32,
+    /// Count each token at most once per chat index.
+    document_frequency: bool,
+    stopwords: HashSet<String>,
+    /// Index each live token was first observed at, if tracked.
+    first_seen: Option<SymbolMap<u32>>,
+    /// Filter of stored tokens letting `count` skip the map on misses.
+    miss_filter: Option<Bloom>,
+    /// Sketch counting tokens not yet stored, and the count that promotes
+    /// them into the map.
+    probation: Option<(f64, SketchDictionary<L>)>,
+    /// Vacuuming is deferred to the end of a batch.
+    batching: bool,
+}
+
+impl<const L: usize, H: BuildHasher + Default, F: Float> MemoryDictionary<L, H, F> {
+    /// Keep at most `max_entries` tokens. When a new token goes over the cap,
+    /// the tokens with the lowest decayed counts are evicted, down to 90% of
+    /// the cap so that eviction is amortised over many inserts.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self.evict(0);
+        self
+    }
+    fn evict(&mut self, idx: u32) {
+        let Some(max_entries) = self.max_entries else {
+            return;
+        };
+        if self.entries.len() <= max_entries {
+            return;
+        }
+        let target = max_entries - max_entries / 10;
+        let mut excess = self.entries.len() - target;
+        let mut counts: Vec<f64> = self
+            .entries
+            .values()
+            .map(|stats| stats.decayed::<L>(idx))
+            .collect();
+        let threshold = *counts.select_nth_unstable_by(excess - 1, f64::total_cmp).1;
+        self.entries.retain(|_, stats| {
+            let evict = excess > 0 && stats.decayed::<L>(idx) <= threshold;
+            excess -= evict as usize;
+            !evict
+        });
+        self.prune_first_seen();
+        self.rebuild_miss_filter();
+        self.compact_interner();
+    }
+    fn prune_first_seen(&mut self) {
+        if let Some(first_seen) = self.first_seen.as_mut() {
+            first_seen.retain(|symbol, _| self.entries.contains_key(symbol));
+        }
+    }
+    /// Free the interned text of tokens neither stored nor held by a window.
+    fn compact_interner(&mut self) {
+        let entries = &self.entries;
+        self.interner
+            .compact_where(|symbol| entries.contains_key(symbol));
+        self.compact_at = 2 * self.interner.len();
+    }
+    fn compact_interner_if_due(&mut self) {
+        if self.interner.len() >= self.compact_at.max(1024) {
+            self.compact_interner();
+        }
+    }
+    fn symbol(&self, token: &str) -> Option<Symbol> {
+        self.interner.get(token)
+    }
+    /// Never store the listed tokens and report their count as `L`, so they
+    /// get an idf weight of zero, e.g. for laughter or channel emotes.
+    pub fn with_stopwords(
+        mut self,
+        stopwords: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.stopwords.extend(stopwords.into_iter().map(Into::into));
+        let (interner, stopwords) = (&self.interner, &self.stopwords);
+        self.entries
+            .retain(|symbol, _| !stopwords.contains(interner.resolve(symbol)));
+        self.prune_first_seen();
+        self.compact_interner();
+        self
+    }
+    /// Keep a bloom filter of the stored tokens so that `count` answers
+    /// tokens never seen without probing the map. Pays off on large
+    /// dictionaries, where most n-grams of a chat are misses.
+    ///
+    /// A bloom filter cannot forget, so it is rebuilt after vacuums and
+    /// evictions and grown when it fills up.
+    pub fn with_miss_filter(mut self) -> Self {
+        self.miss_filter = Some(Bloom::with_capacity(2 * self.entries.len()));
+        self.rebuild_miss_filter();
+        self
+    }
+    /// Hold new tokens on probation in `sketch` until their decayed count
+    /// reaches `min_count`, e.g. 1.5 for tokens seen twice within a few
+    /// chats, and only then store them. One-off n-grams, most of a
+    /// dictionary's entries, then cost no memory beyond the sketch.
+    ///
+    /// Tokens on probation are counted by the sketch, which may
+    /// overestimate them.
+    pub fn with_probation(mut self, min_count: f64, sketch: SketchDictionary<L>) -> Self {
+        self.probation = Some((min_count, sketch));
+        self
+    }
+    fn rebuild_miss_filter(&mut self) {
+        if let Some(filter) = self.miss_filter.as_mut() {
+            let tokens = self.entries.iter().map(|(s, _)| self.interner.resolve(s));
+            filter.rebuild(self.entries.len(), tokens);
+        }
+    }
+    /// Remember the chat index each token was first observed at, for
+    /// [`first_seen`](Self::first_seen) and [`novelty`](Dictionary::novelty).
+    /// A token that faded out and returns counts as new again.
+    pub fn with_novelty_tracking(mut self) -> Self {
+        self.first_seen = Some(SymbolMap::default());
+        self
+    }
+    /// Chat index `token` was first observed at, with novelty tracking on.
+    pub fn first_seen(&self, token: &str) -> Option<u32> {
+        let symbol = self.symbol(token)?;
+        self.entries.get(symbol)?;
+        self.first_seen.as_ref()?.get(symbol).copied()
+    }
+    /// Count each token at most once per chat index, so counts estimate
+    /// document frequency rather than occurrences.
+    ///
+    /// `ChatWindow` already observes each distinct token of a chat once; this
+    /// matters when the dictionary is fed raw token streams.
+    pub fn with_document_frequency(mut self) -> Self {
+        self.document_frequency = true;
+        self
+    }
+    /// Drop tokens whose decayed count falls below `sigma`, once the table
+    /// has at least `min_size` entries and has grown by `growth_ratio` since
+    /// the last vacuum. Defaults to `(65536, 1.5, 1e-3)`.
+    pub fn with_vacuum_policy(mut self, min_size: usize, growth_ratio: f64, sigma: f64) -> Self {
+        self.vacuum_policy = VacuumPolicy {
+            min_size,
+            growth_ratio,
+            sigma,
+        };
+        self.vacuum_at = min_size;
+        self
+    }
+    /// Vacuum immediately, e.g. while the channel is idle. Returns the
+    /// number of tokens dropped.
+    pub fn vacuum_now(&mut self) -> usize {
+        let (idx, sigma) = (self.newest_idx, self.vacuum_policy.sigma);
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, stats| stats.decayed::<L>(idx) >= sigma);
+        self.prune_first_seen();
+        self.rebuild_miss_filter();
+        self.compact_interner();
+        let policy = self.vacuum_policy;
+        self.vacuum_at = policy
+            .min_size
+            .max((self.entries.len() as f64 * policy.growth_ratio) as usize);
+        before - self.entries.len()
+    }
+    fn vacuum_if_due(&mut self) {
+        if self.entries.len() > self.vacuum_at.max(self.vacuum_policy.min_size) {
+            self.vacuum_now();
+        }
+    }
+    /// Add the counts of `other` into this dictionary.
+    ///
+    /// The index clocks of the two dictionaries are aligned at their newest
+    /// chats: both are decayed to that moment and summed, and merged tokens
+    /// are stamped with this dictionary's newest index.
+    pub fn merge(&mut self, other: &Self) {
+        let (now, other_now) = (self.newest_idx, other.newest_idx);
+        for (symbol, theirs) in other.entries.iter() {
+            let token = other.interner.resolve(symbol);
+            if self.stopwords.contains(token) {
+                continue;
+            }
+            let symbol = self.interner.intern(token);
+            if let Some(filter) = self.miss_filter.as_mut()
+                && !self.entries.contains_key(symbol)
+            {
+                filter.insert(token);
+            }
+            let ours = self.entries.get_or_insert(symbol, TokenStats::default());
+            *ours =
+                TokenStats::from_f64(ours.decayed::<L>(now) + theirs.decayed::<L>(other_now), now);
+        }
+        if self.miss_filter.as_ref().is_some_and(Bloom::is_full) {
+            self.rebuild_miss_filter();
+        }
+        self.evict(now);
+    }
+    /// Warm up the counts from a historical log, one chat per line, tokenized
+    /// like `ChatWindow` does with the given n-gram range. Returns the number
+    /// of lines imported.
+    ///
+    /// The log is replayed on its own index clock and then
+    /// [merged](Self::merge) in, so its last line counts as the newest chat
+    /// seen so far and only about the last `L` lines carry weight.
+    pub fn import_corpus<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = &'a str>,
+        ngram_range: (usize, usize),
+    ) -> usize {
+        let mut corpus = Self {
+            document_frequency: self.document_frequency,
+            ..Default::default()
+        };
+        let mut imported = 0;
+        for line in lines {
+            imported += 1;
+            let chat = normalize(line);
+            for token in char_ngrams_iter(&chat, ngram_range.0, ngram_range.1) {
+                corpus.observe(token, imported);
+            }
+        }
+        self.merge(&corpus);
+        imported as usize
+    }
+    /// Copy the learned statistics out, e.g. to persist them. Configuration
+    /// such as the entry cap or vacuum policy is not included.
+    pub fn snapshot(&self) -> DictSnapshot {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(symbol, stats)| SnapshotEntry {
+                token: self.interner.resolve(symbol).to_owned(),
+                count: stats.count.as_f64(),
+                last_idx: stats.last_idx,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.token.cmp(&b.token));
+        DictSnapshot {
+            horizon: L,
+            entries,
+        }
+    }
+    /// Rebuild a dictionary from a snapshot. Counts keep decaying with this
+    /// dictionary's `L`, which should match `snapshot.horizon`.
+    pub fn from_snapshot(snapshot: DictSnapshot) -> Self {
+        let newest_idx = snapshot.entries.iter().map(|e| e.last_idx).max();
+        let mut dict = Self {
+            newest_idx: newest_idx.unwrap_or(0),
+            ..Default::default()
+        };
+        for e in snapshot.entries {
+            let symbol = dict.interner.intern(&e.token);
+            dict.entries
+                .insert(symbol, TokenStats::from_f64(e.count, e.last_idx));
+        }
+        dict
+    }
+}
+
+/// Plain copy of a [`MemoryDictionary`], serializable with the `serde`
+/// feature.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DictSnapshot {
+    /// The `L` the counts were decayed with.
+    pub horizon: usize,
+    /// Entries sorted by token.
+    pub entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotEntry {
+    pub token: String,
+    /// Count as of `last_idx`, before further decay.
+    pub count: f64,
+    pub last_idx: u32,
+}
+
+impl<const L: usize, H: BuildHasher + Default, F: Float> Dictionary for MemoryDictionary<L, H, F> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        if self.stopwords.contains(token) {
+            return;
+        }
+        self.newest_idx = self.newest_idx.max(idx);
+        let stored = self
+            .symbol(token)
+            .and_then(|s| Some((s, self.entries.get_mut(s)?)));
+        let (symbol, stats, promoted_now) = match stored {
+            Some((_, stats)) if self.document_frequency && stats.last_idx == idx => return,
+            Some((symbol, stats)) => (symbol, stats, false),
+            None => {
+                let mut promoted = TokenStats::default();
+                if let Some((min_count, sketch)) = self.probation.as_mut() {
+                    sketch.observe_weighted(token, idx, weight);
+                    let count = sketch.count(token, idx);
+                    if count < *min_count {
+                        return;
+                    }
+                    // The probation counts carry over; `weight` is added below.
+                    promoted = TokenStats::from_f64(count - weight, idx);
+                }
+                if let Some(filter) = self.miss_filter.as_mut() {
+                    filter.insert(token);
+                }
+                let promoted_now = self.probation.is_some();
+                let symbol = self.interner.intern(token);
+                let stats = self.entries.get_or_insert(symbol, promoted);
+                (symbol, stats, promoted_now)
+            }
+        };
+        if let Some(first_seen) = self.first_seen.as_mut()
+            && (promoted_now || stats.decayed::<L>(idx) <= 0.)
+        {
+            first_seen.insert(symbol, idx);
+        }
+        *stats = stats.observed::<L>(idx, weight);
+        if self.miss_filter.as_ref().is_some_and(Bloom::is_full) {
+            self.rebuild_miss_filter();
+        }
+        if !self.batching {
+            self.vacuum_if_due();
+        }
+        self.evict(idx);
+    }
+    fn intern(&mut self, token: &str) -> Option<Arc<str>> {
+        self.compact_interner_if_due();
+        Some(self.interner.intern_arc(token))
+    }
+    fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+    fn end_batch(&mut self) {
+        self.batching = false;
+        self.vacuum_if_due();
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        if self.stopwords.contains(token) {
+            return L as f64;
+        }
+        let stored = match &self.miss_filter {
+            Some(filter) if !filter.may_contain(token) => None,
+            _ => self.symbol(token).and_then(|s| self.entries.get(s)),
+        };
+        match (stored, &self.probation) {
+            (Some(stats), _) => stats.decayed::<L>(idx),
+            (None, Some((_, sketch))) => sketch.count(token, idx),
+            (None, None) => 0.,
+        }
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        let symbol = self.symbol(token);
+        let Some((symbol, stats)) = symbol.and_then(|s| Some((s, self.entries.get_mut(s)?))) else {
+            if let Some((_, sketch)) = self.probation.as_mut() {
+                sketch.retract(token, idx);
+            }
+            return;
+        };
+        match stats.retracted::<L>(idx) {
+            Some(retracted) => *stats = retracted,
+            None => {
+                self.entries.remove(symbol);
+            }
+        }
+    }
+    fn len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        let slot = size_of::<Option<TokenStats<F>>>();
+        let keys = self.interner.approx_memory_bytes();
+        let filter = self.miss_filter.as_ref().map_or(0, Bloom::memory_bytes);
+        let probation = self
+            .probation
+            .as_ref()
+            .and_then(|(_, sketch)| sketch.approx_memory_bytes())
+            .unwrap_or(0);
+        Some(self.entries.capacity() * slot + keys + filter + probation)
+    }
+    fn novelty(&self, token: &str) -> Option<f64> {
+        let age = self.newest_idx.saturating_sub(self.first_seen(token)?);
+        Some((1. - 1. / L as f64).powf(age as f64))
+    }
+    fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        let idx = self.newest_idx;
+        top_counts(
+            self.entries
+                .iter()
+                .map(|(symbol, stats)| (self.interner.resolve(symbol), stats.decayed::<L>(idx))),
+            n,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_dictionary_decays_per_chat() {
+        let mut dict = MemoryDictionary::<2>::default();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        assert_eq!(dict.count("a", 3), 0.75);
+        assert_eq!(dict.count("a", 100), 0.);
+        assert_eq!(dict.count("b", 2), 0.);
+    }
+
+    #[test]
+    fn text_key_renders_opaque_tokens_in_hex() {
+        for h in [0, 0xf, 0x1234_abcd, u64::MAX] {
+            assert_eq!(TextKey::with_opaque(h, str::to_owned), format!("{h:x}"));
+        }
+    }
+
+    #[test]
+    fn late_observations_keep_the_newest_index() {
+        let mut dict = MemoryDictionary::<2>::default();
+        dict.observe("a", 2);
+        dict.observe("a", 1);
+        assert_eq!(dict.count("a", 2), 1.5);
+        assert_eq!(dict.count("a", 3), 0.75);
+    }
+
+    #[test]
+    fn memory_dictionary_retract() {
+        let mut dict = MemoryDictionary::<2>::default();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        dict.retract("a", 1);
+        assert_eq!(dict.count("a", 2), 1.);
+        dict.retract("a", 2);
+        assert_eq!(dict.count("a", 2), 0.);
+        assert_eq!(dict.entries.len(), 0);
+    }
+
+    #[test]
+    fn memory_dictionary_keys_counts_by_symbol() {
+        let mut dict = MemoryDictionary::<2>::default();
+        let held = dict.intern("held").unwrap();
+        dict.observe("a", 1);
+        dict.observe("b", 1);
+        let a = dict.interner.get("a").unwrap();
+        assert!(dict.entries.contains_key(a));
+        dict.retract("b", 1);
+        dict.vacuum_now();
+        // Stored and window-held tokens keep their text and symbols.
+        assert_eq!(dict.interner.get("a"), Some(a));
+        assert!(Arc::ptr_eq(&held, &dict.intern("held").unwrap()));
+        assert_eq!(dict.interner.get("b"), None);
+        assert_eq!(dict.count("a", 1), 1.);
+    }
+
+    #[test]
+    fn memory_dictionary_snapshot_round_trip() {
+        let mut dict = MemoryDictionary::<2>::default();
+        dict.observe("b", 1);
+        dict.observe("a", 2);
+        let snapshot = dict.snapshot();
+        assert_eq!(snapshot.horizon, 2);
+        assert_eq!(snapshot.entries[0].token, "a");
+        #[cfg(feature = "serde")]
+        let snapshot: DictSnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let restored = MemoryDictionary::<2>::from_snapshot(snapshot);
+        assert_eq!(restored.count("b", 2), dict.count("b", 2));
+        assert_eq!(restored.count("a", 3), 0.5);
+    }
+
+    #[test]
+    fn memory_dictionary_merge_aligns_clocks() {
+        let mut day1 = MemoryDictionary::<2>::default();
+        day1.observe("a", 1);
+        day1.observe("b", 2);
+        let mut day2 = MemoryDictionary::<2>::default();
+        day2.observe("a", 10);
+        day2.observe("c", 11);
+        day1.merge(&day2);
+        assert_eq!(day1.count("a", 2), 0.5 + 0.5);
+        assert_eq!(day1.count("b", 2), 1.);
+        assert_eq!(day1.count("c", 2), 1.);
+    }
+
+    #[test]
+    fn memory_dictionary_vacuum() {
+        let mut dict = MemoryDictionary::<2>::default().with_vacuum_policy(4, 2., 0.1);
+        for idx in 0..4 {
+            dict.observe(&format!("t{idx}"), idx);
+        }
+        assert_eq!(dict.entries.len(), 4);
+        dict.observe("t4", 4);
+        // t0 decayed below 0.1 and was dropped; next run at 2 * 4 entries.
+        assert_eq!(dict.entries.len(), 4);
+        assert_eq!(dict.vacuum_at, 8);
+        dict.observe("t5", 10);
+        assert_eq!(dict.vacuum_now(), 4);
+        assert_eq!(dict.entries.len(), 1);
+
+        // Batches vacuum once at their end.
+        dict.begin_batch();
+        for idx in 20..30 {
+            dict.observe(&format!("t{idx}"), idx);
+        }
+        assert_eq!(dict.entries.len(), 11);
+        dict.end_batch();
+        assert_eq!(dict.entries.len(), 4);
+    }
+
+    #[test]
+    fn memory_dictionary_introspection() {
+        let mut dict = MemoryDictionary::<2>::default();
+        assert_eq!(dict.is_empty(), Some(true));
+        dict.observe("a", 1);
+        dict.observe("b", 2);
+        dict.observe("b", 2);
+        assert_eq!(dict.len(), Some(2));
+        assert!(dict.approx_memory_bytes().unwrap() > 0);
+        assert_eq!(dict.top_n(1), vec![("b".to_owned(), 2.)]);
+        assert_eq!(dict.top_n(5)[1], ("a".to_owned(), 0.5));
+    }
+
+    #[test]
+    fn memory_dictionary_document_frequency() {
+        let mut dict = MemoryDictionary::<2>::default().with_document_frequency();
+        dict.observe("a", 1);
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+    }
+
+    #[test]
+    fn memory_dictionary_weighted_observe() {
+        let mut dict = MemoryDictionary::<2>::default();
+        dict.observe_weighted("a", 1, 3.);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 2.5);
+    }
+
+    #[test]
+    fn memory_dictionary_stopwords() {
+        let mut dict = MemoryDictionary::<100>::default();
+        dict.observe("ㅋ", 1);
+        let mut dict = dict.with_stopwords(["ㅋ"]);
+        assert_eq!(dict.entries.len(), 0);
+        dict.observe("ㅋ", 2);
+        assert_eq!(dict.entries.len(), 0);
+        assert_eq!(dict.count("ㅋ", 2), 100.);
+    }
+
+    #[test]
+    fn memory_dictionary_import_corpus() {
+        let mut dict = MemoryDictionary::<2>::default().with_stopwords(["b"]);
+        let imported = dict.import_corpus(["ab", "a"], (1, 2));
+        assert_eq!(imported, 2);
+        assert_eq!(dict.count("a", 0), 1.5);
+        assert_eq!(dict.count("ab", 0), 0.5);
+        assert_eq!(dict.count("b", 0), 2.);
+        assert_eq!(dict.len(), Some(2));
+    }
+
+    #[test]
+    fn memory_dictionary_export_idf() {
+        let mut dict = MemoryDictionary::<4>::default();
+        dict.observe("a", 1);
+        dict.observe("b", 1);
+        dict.observe("b", 1);
+        let idf = dict.export_idf(4);
+        assert_eq!(idf[0], ("b".to_owned(), 2f64.ln()));
+        assert_eq!(idf[1], ("a".to_owned(), 4f64.ln()));
+    }
+
+    #[test]
+    fn memory_dictionary_novelty() {
+        let mut dict = MemoryDictionary::<2>::default().with_novelty_tracking();
+        dict.observe("old", 1);
+        dict.observe("old", 3);
+        dict.observe("new", 3);
+        assert_eq!(dict.first_seen("old"), Some(1));
+        assert_eq!(dict.novelty("new"), Some(1.));
+        assert_eq!(dict.novelty("old"), Some(0.25));
+        assert_eq!(dict.novelty("unknown"), None);
+        assert_eq!(MemoryDictionary::<2>::default().novelty("x"), None);
+    }
+
+    #[test]
+    fn memory_dictionary_evicts_rarest_tokens() {
+        let mut dict = MemoryDictionary::<100>::default().with_max_entries(10);
+        for idx in 0..20 {
+            dict.observe("common", idx);
+            dict.observe(&format!("rare{idx}"), idx);
+        }
+        assert!(dict.entries.len() <= 10);
+        assert!(dict.count("common", 20) > 10.);
+        assert!(dict.count("rare19", 20) > 0.);
+    }
+
+    #[test]
+    fn memory_dictionary_custom_hasher() {
+        use std::hash::{BuildHasherDefault, DefaultHasher};
+        let mut dict = MemoryDictionary::<2, BuildHasherDefault<DefaultHasher>>::default();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        assert_eq!(dict.top_n(1), vec![("a".to_owned(), 1.5)]);
+    }
+
+    #[test]
+    fn memory_dictionary_f32_counts() {
+        let mut wide = MemoryDictionary::<50>::default();
+        let mut narrow = MemoryDictionary::<50, DefaultHashBuilder, f32>::default();
+        for idx in 1..200 {
+            let token = if idx % 3 == 0 { "a" } else { "b" };
+            wide.observe(token, idx);
+            narrow.observe(token, idx);
+        }
+        for token in ["a", "b", "c"] {
+            let (a, b) = (wide.count(token, 200), narrow.count(token, 200));
+            assert!((a - b).abs() < 1e-4 * (1. + a), "{token}: {a} vs {b}");
+        }
+        assert!(narrow.approx_memory_bytes() < wide.approx_memory_bytes());
+    }
+
+    #[test]
+    fn miss_filter_keeps_counts() {
+        let mut plain = MemoryDictionary::<50>::default().with_max_entries(100);
+        let mut filtered = MemoryDictionary::<50>::default()
+            .with_max_entries(100)
+            .with_miss_filter();
+        for idx in 0..1000 {
+            for token in [format!("t{}", idx % 300), "common".to_owned()] {
+                plain.observe(&token, idx);
+                filtered.observe(&token, idx);
+            }
+        }
+        for i in 0..300 {
+            let token = format!("t{i}");
+            assert_eq!(plain.count(&token, 1000), filtered.count(&token, 1000));
+        }
+        assert_eq!(filtered.count("never", 1000), 0.);
+        let filter = filtered.miss_filter.as_ref().unwrap();
+        let false_positives = (0..1000)
+            .filter(|i| filter.may_contain(&format!("missing{i}")))
+            .count();
+        assert!(false_positives < 50, "{false_positives}");
+    }
+
+    #[test]
+    fn probation_stores_only_repeated_tokens() {
+        let mut dict =
+            MemoryDictionary::<1000>::default().with_probation(1.5, SketchDictionary::new(256, 4));
+        dict.observe("once", 1);
+        dict.observe("twice", 1);
+        dict.observe("twice", 2);
+        assert_eq!(dict.len(), Some(1));
+        assert!(dict.count("once", 2) > 0.99);
+        let twice = dict.count("twice", 2);
+        dict.observe("twice", 3);
+        assert!((dict.count("twice", 3) - (twice * 0.999 + 1.)).abs() < 1e-9);
+        dict.retract("once", 1);
+        assert_eq!(dict.count("once", 2), 0.);
+    }
+}
@@ -0,0 +1,220 @@
+//! Dictionary that vacuums on a worker thread.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use super::{Dictionary, TokenStats, VacuumPolicy};
+
+type Table = HashMap<String, TokenStats>;
+
+/// Decayed-count dictionary whose vacuum runs off the hot path.
+///
+/// Counts live in an immutable base table shared with the worker, plus
+/// additive delta tables for updates made since. When the tables grow past
+/// the vacuum policy, the current delta is frozen and a worker thread folds
+/// it into a fresh base without the faded tokens, while `observe` keeps
+/// writing to a new delta. The finished base is swapped in on a later call.
+#[derive(Debug)]
+pub struct BackgroundDictionary<const L: usize> {
+    base: Arc<Table>,
+    /// Delta being folded into the base by the worker.
+    pending: Option<Arc<Table>>,
+    delta: Table,
+    worker: Option<JoinHandle<Table>>,
+    vacuum_policy: VacuumPolicy,
+    vacuum_at: usize,
+    newest_idx: u32,
+}
+
+impl<const L: usize> Default for BackgroundDictionary<L> {
+    fn default() -> Self {
+        let vacuum_policy = VacuumPolicy::default();
+        Self {
+            base: Arc::default(),
+            pending: None,
+            delta: Table::new(),
+            worker: None,
+            vacuum_at: vacuum_policy.min_size,
+            vacuum_policy,
+            newest_idx: 0,
+        }
+    }
+}
+
+impl<const L: usize> BackgroundDictionary<L> {
+    /// Same meaning as
+    /// [`MemoryDictionary::with_vacuum_policy`](super::MemoryDictionary::with_vacuum_policy).
+    pub fn with_vacuum_policy(mut self, min_size: usize, growth_ratio: f64, sigma: f64) -> Self {
+        self.vacuum_policy = VacuumPolicy {
+            min_size,
+            growth_ratio,
+            sigma,
+        };
+        self.vacuum_at = min_size;
+        self
+    }
+    /// Whether a vacuum is running on the worker thread.
+    pub fn is_vacuuming(&self) -> bool {
+        self.worker.is_some()
+    }
+    /// Finish any running vacuum and vacuum everything, blocking.
+    pub fn vacuum_now(&mut self) {
+        self.join_worker();
+        self.start_vacuum();
+        self.join_worker();
+    }
+    fn size(&self) -> usize {
+        self.base.len() + self.pending.as_ref().map_or(0, |p| p.len()) + self.delta.len()
+    }
+    fn start_vacuum(&mut self) {
+        let base = self.base.clone();
+        let pending = Arc::new(std::mem::take(&mut self.delta));
+        self.pending = Some(pending.clone());
+        let (idx, sigma) = (self.newest_idx, self.vacuum_policy.sigma);
+        self.worker = Some(std::thread::spawn(move || {
+            let mut table = Table::with_capacity(base.len());
+            for token in base.keys().chain(pending.keys()) {
+                if table.contains_key(token) {
+                    continue;
+                }
+                let merged = merge::<L>(base.get(token), pending.get(token));
+                if merged.decayed::<L>(idx) >= sigma {
+                    table.insert(token.clone(), merged);
+                }
+            }
+            table
+        }));
+    }
+    fn join_worker(&mut self) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        match worker.join() {
+            Ok(table) => {
+                self.base = Arc::new(table);
+                self.pending = None;
+            }
+            // Keep serving from the unvacuumed tables.
+            Err(_) => {
+                if let Some(pending) = self.pending.take() {
+                    let mut base = (*self.base).clone();
+                    for (token, stats) in pending.iter() {
+                        let merged = merge::<L>(base.get(token), Some(stats));
+                        base.insert(token.clone(), merged);
+                    }
+                    self.base = Arc::new(base);
+                }
+            }
+        }
+        let policy = self.vacuum_policy;
+        self.vacuum_at = policy
+            .min_size
+            .max((self.size() as f64 * policy.growth_ratio) as usize);
+    }
+    /// Add `amount` occurrences at `idx` to the delta table.
+    fn add(&mut self, token: &str, idx: u32, amount: f64) {
+        let stats = match self.delta.get_mut(token) {
+            Some(stats) => stats,
+            None => self.delta.entry(token.to_owned()).or_default(),
+        };
+        *stats = if idx >= stats.last_idx {
+            TokenStats {
+                count: stats.decayed::<L>(idx) + amount,
+                last_idx: idx,
+            }
+        } else {
+            let late = TokenStats {
+                count: amount,
+                last_idx: idx,
+            };
+            TokenStats {
+                count: stats.count + late.decayed::<L>(stats.last_idx),
+                ..*stats
+            }
+        };
+    }
+    fn maintain(&mut self) {
+        if self.worker.as_ref().is_some_and(JoinHandle::is_finished) {
+            self.join_worker();
+        }
+        if self.worker.is_none() && self.size() > self.vacuum_at {
+            self.start_vacuum();
+        }
+    }
+}
+
+/// Sum of two additive stats, stamped at the later index.
+fn merge<const L: usize>(a: Option<&TokenStats>, b: Option<&TokenStats>) -> TokenStats {
+    let (a, b) = (
+        a.copied().unwrap_or_default(),
+        b.copied().unwrap_or_default(),
+    );
+    let idx = a.last_idx.max(b.last_idx);
+    TokenStats {
+        count: a.decayed::<L>(idx) + b.decayed::<L>(idx),
+        last_idx: idx,
+    }
+}
+
+impl<const L: usize> Dictionary for BackgroundDictionary<L> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        self.newest_idx = self.newest_idx.max(idx);
+        self.add(token, idx, weight);
+        self.maintain();
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        let count = |table: &Table| table.get(token).map_or(0., |s| s.decayed::<L>(idx));
+        let total =
+            count(&self.base) + self.pending.as_deref().map_or(0., count) + count(&self.delta);
+        total.max(0.)
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        if self.count(token, idx.max(self.newest_idx)) > 0. {
+            self.add(token, idx, -1.);
+        }
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        let table = |t: &Table| {
+            t.capacity() * (size_of::<(String, TokenStats)>() + 1)
+                + t.keys().map(String::capacity).sum::<usize>()
+        };
+        Some(table(&self.base) + self.pending.as_deref().map_or(0, table) + table(&self.delta))
+    }
+}
+
+impl<const L: usize> Drop for BackgroundDictionary<L> {
+    fn drop(&mut self) {
+        self.join_worker();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict::MemoryDictionary;
+
+    #[test]
+    fn background_dictionary_matches_memory_dictionary() {
+        let mut background = BackgroundDictionary::<8>::default().with_vacuum_policy(16, 1.5, 1e-2);
+        let mut memory = MemoryDictionary::<8>::default();
+        for idx in 0..500 {
+            for token in [format!("t{}", idx % 40), "common".to_owned()] {
+                background.observe(&token, idx);
+                memory.observe(&token, idx);
+            }
+        }
+        background.retract("common", 499);
+        memory.retract("common", 499);
+        background.vacuum_now();
+        assert!(!background.is_vacuuming());
+        for token in ["common", "t19", "t0", "t39"] {
+            let (b, m) = (background.count(token, 500), memory.count(token, 500));
+            assert!((b - m).abs() < 1e-9, "{token}: {b} vs {m}");
+        }
+        assert!(background.base.len() < 41);
+    }
+}
@@ -0,0 +1,56 @@
+//! Bloom filter answering definite misses for dictionary lookups.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+const HASHES: u32 = 4;
+const BITS_PER_ITEM: usize = 10;
+
+/// Bloom filter sized for `capacity` items at about 1% false positives.
+#[derive(Clone, Debug)]
+pub(crate) struct Bloom {
+    bits: Vec<u64>,
+    capacity: usize,
+    items: usize,
+}
+
+impl Bloom {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(64);
+        Self {
+            bits: vec![0; (capacity * BITS_PER_ITEM).div_ceil(64)],
+            capacity,
+            items: 0,
+        }
+    }
+    /// Rebuild from the live keys, growing if they no longer fit.
+    pub(crate) fn rebuild<'a>(&mut self, len: usize, keys: impl Iterator<Item = &'a str>) {
+        *self = Self::with_capacity(self.capacity.max(2 * len));
+        keys.for_each(|key| self.insert(key));
+    }
+    pub(crate) fn is_full(&self) -> bool {
+        self.items >= self.capacity
+    }
+    pub(crate) fn memory_bytes(&self) -> usize {
+        self.bits.capacity() * size_of::<u64>()
+    }
+    pub(crate) fn insert(&mut self, key: &str) {
+        for bit in self.bits_of(key) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+        self.items += 1;
+    }
+    /// `false` means `key` was definitely never inserted.
+    pub(crate) fn may_contain(&self, key: &str) -> bool {
+        self.bits_of(key)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+    fn bits_of(&self, key: &str) -> impl Iterator<Item = usize> + use<> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let h = hasher.finish();
+        // Double hashing: h1 + i * h2.
+        let (h1, h2) = (h as u32 as u64, (h >> 32) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..HASHES as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
@@ -0,0 +1,223 @@
+//! Dictionary keyed by 64-bit token fingerprints instead of owned strings.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, DefaultHasher, Hash, Hasher};
+
+use super::{Dictionary, TokenStats};
+
+/// Passes already-hashed `u64` keys through unchanged.
+#[derive(Clone, Copy, Default, Debug)]
+struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ b as u64;
+        }
+    }
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+/// Fingerprint of a token as stored by [`HashedDictionary`].
+pub fn token_fingerprint(token: &str) -> u64 {
+    #[cfg(test)]
+    tests::FINGERPRINTS.with(|n| n.set(n.get() + 1));
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Like [`MemoryDictionary`](super::MemoryDictionary), but stores a 64-bit
+/// fingerprint per token instead of the token itself.
+///
+/// This saves the string allocation and most of the memory per entry. Two
+/// tokens whose fingerprints collide share a count; with 64-bit hashes this
+/// is vanishingly rare, but it is why the hashed layout is opt-in. Tokens
+/// cannot be listed back, so [`top_n`](Dictionary::top_n) is empty.
+/// Fingerprints are not stable across Rust releases and should not be
+/// persisted. It also implements `Dictionary<u64>` for windows keyed by
+/// [`FingerprintKey`](super::FingerprintKey).
+///
+/// [`token_fingerprint`] hashes with fixed keys and the table uses the
+/// fingerprint as its own hash, so chats crafted to collide can slow
+/// lookups to linear scans. Prefer
+/// [`MemoryDictionary`](super::MemoryDictionary) for untrusted input.
+#[derive(Clone, Default, Debug)]
+pub struct HashedDictionary<const L: usize> {
+    entries: HashMap<u64, TokenStats, BuildHasherDefault<IdentityHasher>>,
+}
+
+impl<const L: usize> HashedDictionary<L> {
+    /// Number of fingerprints stored, without naming the key type.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<const L: usize> Dictionary<u64> for HashedDictionary<L> {
+    fn observe(&mut self, key: &u64, idx: u32) {
+        self.observe_weighted(key, idx, 1.);
+    }
+    fn observe_weighted(&mut self, key: &u64, idx: u32, weight: f64) {
+        let stats = self.entries.entry(*key).or_default();
+        *stats = stats.observed::<L>(idx, weight);
+    }
+    fn count(&self, key: &u64, idx: u32) -> f64 {
+        self.entries
+            .get(key)
+            .map_or(0., |stats| stats.decayed::<L>(idx))
+    }
+    fn retract(&mut self, key: &u64, idx: u32) {
+        let Some(stats) = self.entries.get_mut(key) else {
+            return;
+        };
+        match stats.retracted::<L>(idx) {
+            Some(retracted) => *stats = retracted,
+            None => {
+                self.entries.remove(key);
+            }
+        }
+    }
+    fn len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        Some(self.entries.capacity() * (size_of::<(u64, TokenStats)>() + 1))
+    }
+}
+
+impl<const L: usize> Dictionary for HashedDictionary<L> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        Dictionary::<u64>::observe_weighted(self, &token_fingerprint(token), idx, weight);
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        Dictionary::<u64>::count(self, &token_fingerprint(token), idx)
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        Dictionary::<u64>::retract(self, &token_fingerprint(token), idx);
+    }
+    fn len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        Dictionary::<u64>::approx_memory_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict::FingerprintKey;
+    use crate::spike::{ChatSpikeDetector, ChatToken, ChatWindow, Event};
+    use crate::tokenizer::{ByteNgrams, Tokenizer};
+    use std::cell::Cell;
+    use std::time::Instant;
+
+    thread_local! {
+        /// Calls to `token_fingerprint` on this thread.
+        pub(super) static FINGERPRINTS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[test]
+    fn hashed_dictionary_drives_chat_window() {
+        let mut dict = HashedDictionary::<2>::default();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        dict.retract("a", 1);
+        dict.retract("a", 2);
+        assert_eq!(dict.len(), 0);
+        assert_eq!(Dictionary::<str>::len(&dict), Some(0));
+
+        let mut cw = ChatWindow::<3, 12>::default();
+        let mut dict = HashedDictionary::<12>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("some noises".into(), &mut dict);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "hello world");
+
+        let mut det = ChatSpikeDetector::<1, 2>::default()
+            .with_threshold(0.0, f64::INFINITY)
+            .with_dictionary(HashedDictionary::<2>::default());
+        let event = det.update_and_detect("hi".into(), Instant::now());
+        assert!(matches!(
+            event,
+            Event::SpikeBegin {
+                summary: Some("hi"),
+                ..
+            }
+        ));
+        assert!(det.dictionary().count("hi", 1) > 0.);
+    }
+
+    #[test]
+    fn fingerprint_keyed_window_matches_text_keyed() {
+        let mut by_text = ChatWindow::<3, 12>::default();
+        let mut by_key = ChatWindow::<3, 12, (), FingerprintKey>::default();
+        let (mut text_dict, mut key_dict) = (
+            HashedDictionary::<12>::default(),
+            HashedDictionary::<12>::default(),
+        );
+        for chat in ["hello world", "hello world", "some noises"] {
+            by_text.push_with_dict(chat.into(), &mut text_dict);
+            by_key.push_with_dict(chat.into(), &mut key_dict);
+        }
+        let text_score = by_text.summary_with_dict(&text_dict).unwrap().2;
+        let (summary, _, key_score) = by_key.summary_with_dict(&key_dict).unwrap();
+        assert_eq!(summary, "hello world");
+        assert_eq!(text_score, key_score);
+        assert_eq!(
+            Dictionary::<u64>::count(&key_dict, &token_fingerprint("hello"), 3),
+            text_dict.count("hello", 3)
+        );
+    }
+
+    #[test]
+    fn fingerprint_keyed_window_hashes_tokens_once() {
+        let mut cw = ChatWindow::<3, 12, (), FingerprintKey>::default();
+        let mut dict = HashedDictionary::<12>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello there".into(), &mut dict);
+        let before = FINGERPRINTS.with(Cell::get);
+        cw.push_with_dict("some noises".into(), &mut dict);
+        let per_push = FINGERPRINTS.with(Cell::get) - before;
+        assert_eq!(per_push, cw.chat_caches().last().unwrap().tokens().len());
+
+        let before = FINGERPRINTS.with(Cell::get);
+        cw.summary_with_dict(&dict).unwrap();
+        cw.trending_tokens_with_dict(&dict, 3, 1);
+        cw.token_divergence_with_dict(&dict, 0.5);
+        // Pushing again evicts, which retracts through the cached keys.
+        cw.push_with_dict("some noises".into(), &mut dict);
+        assert_eq!(FINGERPRINTS.with(Cell::get) - before, per_push);
+    }
+
+    #[test]
+    fn byte_ngram_fingerprints_key_the_dictionary() {
+        let tokenizer = ByteNgrams {
+            min: 2,
+            max: 2,
+            buckets: None,
+        };
+        let h = tokenizer.tokens("gg")[0].fingerprint.unwrap();
+        let mut cw = ChatWindow::<3, 12, (), FingerprintKey>::default().with_tokenizer(tokenizer);
+        let mut dict = HashedDictionary::<12>::default();
+        let before = FINGERPRINTS.with(Cell::get);
+        cw.push_with_dict("gg".into(), &mut dict);
+        assert_eq!(FINGERPRINTS.with(Cell::get), before);
+        assert_eq!(Dictionary::<u64>::count(&dict, &h, 1), 1.);
+        let cache = cw.chat_caches().next().unwrap();
+        assert_eq!(cache.tokens()[0], ChatToken::Fingerprint(h));
+    }
+}
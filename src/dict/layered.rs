@@ -0,0 +1,119 @@
+//! A shared read-only dictionary with a small per-channel overlay.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::Dictionary;
+
+/// Answers `count` from a shared global dictionary plus a mutable overlay.
+///
+/// Most idf signal is language-wide, so many channels can share one large
+/// `global` dictionary built offline (e.g. with
+/// [`import_corpus`](super::MemoryDictionary::import_corpus)) while each
+/// keeps only its own recent tokens in `overlay`. Observations and
+/// retractions go to the overlay; the global layer is never modified and is
+/// always queried at the fixed index `global_idx`.
+#[derive(Clone, Debug)]
+pub struct LayeredDictionary<G, O> {
+    global: Arc<G>,
+    global_idx: u32,
+    global_weight: f64,
+    overlay: O,
+}
+
+impl<G, O> LayeredDictionary<G, O> {
+    pub fn new(global: Arc<G>, global_idx: u32, overlay: O) -> Self {
+        Self {
+            global,
+            global_idx,
+            global_weight: 1.,
+            overlay,
+        }
+    }
+    /// Scale global counts before adding them to the overlay's, e.g. to
+    /// bring a corpus built with a different horizon to the channel's scale.
+    pub fn with_global_weight(mut self, weight: f64) -> Self {
+        self.global_weight = weight;
+        self
+    }
+    pub fn global(&self) -> &G {
+        &self.global
+    }
+    pub fn overlay(&self) -> &O {
+        &self.overlay
+    }
+}
+
+impl<K: ?Sized, G: Dictionary<K>, O: Dictionary<K>> Dictionary<K> for LayeredDictionary<G, O> {
+    fn observe(&mut self, token: &K, idx: u32) {
+        self.overlay.observe(token, idx);
+    }
+    fn observe_weighted(&mut self, token: &K, idx: u32, weight: f64) {
+        self.overlay.observe_weighted(token, idx, weight);
+    }
+    fn count(&self, token: &K, idx: u32) -> f64 {
+        self.global_weight * self.global.count(token, self.global_idx)
+            + self.overlay.count(token, idx)
+    }
+    fn retract(&mut self, token: &K, idx: u32) {
+        self.overlay.retract(token, idx);
+    }
+    fn observe_timed(&mut self, token: &K, idx: u32, ts: Instant, weight: f64) {
+        self.overlay.observe_timed(token, idx, ts, weight);
+    }
+    fn count_timed(&self, token: &K, idx: u32, ts: Instant) -> f64 {
+        self.global_weight * self.global.count(token, self.global_idx)
+            + self.overlay.count_timed(token, idx, ts)
+    }
+    fn retract_timed(&mut self, token: &K, idx: u32, ts: Instant) {
+        self.overlay.retract_timed(token, idx, ts);
+    }
+    fn intern(&mut self, token: &str) -> Option<Arc<str>> {
+        self.overlay.intern(token)
+    }
+    fn begin_batch(&mut self) {
+        self.overlay.begin_batch();
+    }
+    fn end_batch(&mut self) {
+        self.overlay.end_batch();
+    }
+    /// Tokens in the overlay only.
+    fn len(&self) -> Option<usize> {
+        self.overlay.len()
+    }
+    /// Memory of the overlay only; the global layer is shared.
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        self.overlay.approx_memory_bytes()
+    }
+    /// Novelty within the overlay.
+    fn novelty(&self, token: &K) -> Option<f64> {
+        self.overlay.novelty(token)
+    }
+    /// Top tokens of the overlay only.
+    fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        self.overlay.top_n(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict::MemoryDictionary;
+
+    #[test]
+    fn layered_dictionary_adds_overlay_to_global() {
+        let mut global = MemoryDictionary::<2>::default();
+        global.observe("a", 1);
+        let global = Arc::new(global);
+        let mut channel =
+            LayeredDictionary::new(global.clone(), 1, MemoryDictionary::<2>::default())
+                .with_global_weight(2.);
+        channel.observe("a", 5);
+        assert_eq!(channel.count("a", 5), 3.);
+        assert_eq!(channel.count("a", 6), 2.5);
+        channel.retract("a", 5);
+        assert_eq!(channel.count("a", 5), 2.);
+        assert_eq!(global.count("a", 1), 1.);
+        assert_eq!(channel.len(), Some(0));
+    }
+}
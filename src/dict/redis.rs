@@ -0,0 +1,170 @@
+//! Dictionary shared between processes through a Redis hash.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+use ::redis::{Commands, Connection, RedisError, RedisResult};
+
+use super::{Dictionary, TokenStats};
+
+/// Dictionary stored in one Redis hash, with the same per-chat decay as
+/// [`MemoryDictionary`](super::MemoryDictionary).
+///
+/// Each field holds a token's count together with the index it was last
+/// updated at, and updates run as optimistic `WATCH`/`MULTI` transactions,
+/// so detector instances for the same channel can share a baseline.
+///
+/// Counts decay by a shared chat index kept at `<key>:clock`, which each
+/// instance increments once for every chat it observes, so the baseline
+/// ages by the chats of all instances together. Each instance maps its own
+/// chat indices onto the shared ones, remembering the last `L`.
+///
+/// `Dictionary` methods cannot fail: read errors count as an unknown token
+/// and write errors are kept for [`take_error`](Self::take_error).
+pub struct RedisDictionary<const L: usize> {
+    con: Mutex<Connection>,
+    key: String,
+    clock_key: String,
+    /// Recent local chat indices with the shared index drawn for each,
+    /// oldest first.
+    clock: VecDeque<(u32, u32)>,
+    error: Option<RedisError>,
+}
+
+impl<const L: usize> RedisDictionary<L> {
+    /// Connect to `url` and use the hash stored at `key`.
+    pub fn open(url: &str, key: impl Into<String>) -> RedisResult<Self> {
+        let con = ::redis::Client::open(url)?.get_connection()?;
+        Ok(Self::from_connection(con, key))
+    }
+    pub fn from_connection(con: Connection, key: impl Into<String>) -> Self {
+        let key = key.into();
+        Self {
+            con: Mutex::new(con),
+            clock_key: format!("{key}:clock"),
+            key,
+            clock: VecDeque::new(),
+            error: None,
+        }
+    }
+    /// The first write error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<RedisError> {
+        self.error.take()
+    }
+    /// Shared index of local chat `idx`, drawing a new one from the clock
+    /// for a chat newer than any seen.
+    fn tick(&mut self, idx: u32) -> u32 {
+        if self.clock.back().is_some_and(|&(local, _)| idx <= local) {
+            return self.shared_idx(idx);
+        }
+        let con = self.con.get_mut().unwrap_or_else(PoisonError::into_inner);
+        match con.incr::<_, _, u32>(&self.clock_key, 1) {
+            Ok(shared) => {
+                self.clock.push_back((idx, shared));
+                if self.clock.len() > L {
+                    self.clock.pop_front();
+                }
+                shared
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self.shared_idx(idx)
+            }
+        }
+    }
+    /// Shared index of local chat `idx`, counted from the nearest chat
+    /// remembered if it is not.
+    fn shared_idx(&self, idx: u32) -> u32 {
+        let i = self.clock.partition_point(|&(local, _)| local < idx);
+        match (self.clock.get(i), i.checked_sub(1).map(|i| self.clock[i])) {
+            (Some(&(local, shared)), _) => shared.saturating_sub(local - idx),
+            (None, Some((local, shared))) => shared.saturating_add(idx - local),
+            (None, None) => idx,
+        }
+    }
+    fn update(&mut self, token: &str, f: impl Fn(Option<TokenStats>) -> Option<TokenStats>) {
+        let con = self.con.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let key = self.key.as_str();
+        let result = ::redis::transaction(con, &[key], |con, pipe| {
+            let old: Option<Vec<u8>> = con.hget(key, token)?;
+            match f(old.as_deref().and_then(TokenStats::from_bytes)) {
+                Some(stats) => pipe.hset(key, token, &stats.to_bytes()[..]),
+                None => pipe.hdel(key, token),
+            }
+            .query::<Option<(i64,)>>(con)
+        });
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl<const L: usize> Dictionary for RedisDictionary<L> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        let idx = self.tick(idx);
+        self.update(token, |stats| {
+            Some(stats.unwrap_or_default().observed::<L>(idx, weight))
+        });
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        let idx = self.shared_idx(idx);
+        let mut con = self.con.lock().unwrap_or_else(PoisonError::into_inner);
+        match con.hget::<_, _, Option<Vec<u8>>>(&self.key, token) {
+            Ok(Some(bytes)) => TokenStats::from_bytes(&bytes).map_or(0., |s| s.decayed::<L>(idx)),
+            _ => 0.,
+        }
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        let idx = self.shared_idx(idx);
+        self.update(token, |stats| stats.and_then(|s| s.retracted::<L>(idx)));
+    }
+    fn len(&self) -> Option<usize> {
+        let mut con = self.con.lock().unwrap_or_else(PoisonError::into_inner);
+        con.hlen(&self.key).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Redis server on localhost"]
+    fn redis_dictionary_round_trip() {
+        let mut dict = RedisDictionary::<2>::open("redis://127.0.0.1/", "chat-spike-test").unwrap();
+        let con = dict.con.get_mut().unwrap();
+        let _: () = con
+            .del(&["chat-spike-test", "chat-spike-test:clock"])
+            .unwrap();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        dict.retract("a", 1);
+        dict.retract("a", 2);
+        assert_eq!(dict.count("a", 2), 0.);
+        assert!(dict.take_error().is_none());
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server on localhost"]
+    fn redis_dictionaries_share_a_clock() {
+        let url = "redis://127.0.0.1/";
+        let mut a = RedisDictionary::<2>::open(url, "chat-spike-clock-test").unwrap();
+        let mut b = RedisDictionary::<2>::open(url, "chat-spike-clock-test").unwrap();
+        let con = a.con.get_mut().unwrap();
+        let _: () = con
+            .del(&["chat-spike-clock-test", "chat-spike-clock-test:clock"])
+            .unwrap();
+        // Each instance's first chat, one after the other on the shared clock.
+        a.observe("x", 1);
+        b.observe("x", 1);
+        assert_eq!(b.count("x", 1), 1.5);
+        assert_eq!(a.count("x", 1), 1.5);
+        b.retract("x", 1);
+        assert_eq!(a.count("x", 1), 0.5);
+        assert!(a.take_error().is_none() && b.take_error().is_none());
+    }
+}
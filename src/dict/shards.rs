@@ -0,0 +1,176 @@
+//! One dictionary per channel, created on demand.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use super::Dictionary;
+
+type Factory<Dc> = Arc<dyn Fn() -> Dc + Send + Sync>;
+
+struct Shard<Dc> {
+    dict: Dc,
+    /// Index of the newest chat observed in this channel.
+    clock: u32,
+}
+
+/// Dictionaries keyed by channel id, each decaying on its own chat clock.
+///
+/// A shard is built by the factory the first time its channel is touched,
+/// so a quiet channel does not fade counts of a busy one and idle channels
+/// cost nothing until they chat. [`observe_chat`](Self::observe_chat)
+/// advances the channel's clock by one chat; reads are answered as of the
+/// channel's newest chat.
+pub struct DictionaryShards<C, Dc> {
+    shards: HashMap<C, Shard<Dc>>,
+    make: Factory<Dc>,
+}
+
+impl<C: Eq + Hash, Dc: Dictionary + Default + 'static> Default for DictionaryShards<C, Dc> {
+    fn default() -> Self {
+        Self::new(Dc::default)
+    }
+}
+
+impl<C: Clone + Eq + Hash, Dc: Clone> Clone for DictionaryShards<C, Dc> {
+    fn clone(&self) -> Self {
+        Self {
+            shards: self
+                .shards
+                .iter()
+                .map(|(channel, shard)| {
+                    let shard = Shard {
+                        dict: shard.dict.clone(),
+                        clock: shard.clock,
+                    };
+                    (channel.clone(), shard)
+                })
+                .collect(),
+            make: self.make.clone(),
+        }
+    }
+}
+
+impl<C: fmt::Debug, Dc> fmt::Debug for DictionaryShards<C, Dc> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DictionaryShards")
+            .field("channels", &self.shards.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Eq + Hash, Dc: Dictionary> DictionaryShards<C, Dc> {
+    /// Build new shards with `make`, e.g. to apply an entry cap to each.
+    pub fn new(make: impl Fn() -> Dc + Send + Sync + 'static) -> Self {
+        Self {
+            shards: HashMap::new(),
+            make: Arc::new(make),
+        }
+    }
+    fn shard(&mut self, channel: C) -> &mut Shard<Dc> {
+        let make = &self.make;
+        self.shards.entry(channel).or_insert_with(|| Shard {
+            dict: make(),
+            clock: 0,
+        })
+    }
+    /// Record the tokens of one chat in `channel`, creating its dictionary
+    /// if needed. Returns the chat's index on the channel's clock.
+    pub fn observe_chat<'a>(
+        &mut self,
+        channel: C,
+        tokens: impl IntoIterator<Item = &'a str>,
+    ) -> u32 {
+        let shard = self.shard(channel);
+        shard.clock += 1;
+        for token in tokens {
+            shard.dict.observe(token, shard.clock);
+        }
+        shard.clock
+    }
+    /// Count of `token` in `channel` as of its newest chat; zero for a
+    /// channel not seen yet.
+    pub fn count(&self, channel: &C, token: &str) -> f64 {
+        self.shards
+            .get(channel)
+            .map_or(0., |shard| shard.dict.count(token, shard.clock))
+    }
+    /// Index of the newest chat observed in `channel`.
+    pub fn clock(&self, channel: &C) -> Option<u32> {
+        self.shards.get(channel).map(|shard| shard.clock)
+    }
+    pub fn get(&self, channel: &C) -> Option<&Dc> {
+        self.shards.get(channel).map(|shard| &shard.dict)
+    }
+    /// Dictionary of `channel`, created if needed, e.g. to hand to a
+    /// `ChatWindow` that keeps its own chat clock.
+    pub fn get_or_create(&mut self, channel: C) -> &mut Dc {
+        &mut self.shard(channel).dict
+    }
+    /// Drop the dictionary of a channel that went away.
+    pub fn remove(&mut self, channel: &C) -> Option<Dc> {
+        self.shards.remove(channel).map(|shard| shard.dict)
+    }
+    pub fn channels(&self) -> impl Iterator<Item = &C> {
+        self.shards.keys()
+    }
+    /// Number of channels with a dictionary.
+    pub fn len(&self) -> usize {
+        self.shards.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+    /// Sum of [`approx_memory_bytes`](Dictionary::approx_memory_bytes) over
+    /// the shards that report it.
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.shards
+            .values()
+            .filter_map(|shard| shard.dict.approx_memory_bytes())
+            .sum()
+    }
+    /// Channels by memory use, largest first, e.g. to pick which to evict.
+    pub fn memory_by_channel(&self) -> Vec<(&C, usize)> {
+        let mut usage: Vec<_> = self
+            .shards
+            .iter()
+            .map(|(channel, shard)| (channel, shard.dict.approx_memory_bytes().unwrap_or(0)))
+            .collect();
+        usage.sort_by_key(|&(_, bytes)| std::cmp::Reverse(bytes));
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict::MemoryDictionary;
+
+    #[test]
+    fn shards_keep_separate_clocks() {
+        let mut shards = DictionaryShards::<&str, _>::new(|| {
+            MemoryDictionary::<2>::default().with_max_entries(100)
+        });
+        assert_eq!(shards.observe_chat("busy", ["pog"]), 1);
+        assert_eq!(shards.observe_chat("quiet", ["pog"]), 1);
+        shards.observe_chat("busy", ["kek"]);
+        assert_eq!(shards.count(&"busy", "pog"), 0.5);
+        assert_eq!(shards.count(&"quiet", "pog"), 1.);
+        assert_eq!(shards.count(&"unknown", "pog"), 0.);
+        assert_eq!(shards.clock(&"busy"), Some(2));
+        assert_eq!(shards.len(), 2);
+        let total = shards.approx_memory_bytes();
+        assert!(total > 0);
+        assert_eq!(
+            shards
+                .memory_by_channel()
+                .iter()
+                .map(|(_, b)| b)
+                .sum::<usize>(),
+            total
+        );
+        assert!(shards.remove(&"quiet").is_some());
+        assert_eq!(shards.channels().collect::<Vec<_>>(), [&"busy"]);
+    }
+}
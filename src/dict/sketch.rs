@@ -0,0 +1,129 @@
+//! Fixed-memory dictionary backed by a count-min sketch.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::{Dictionary, TokenStats};
+
+/// Count-min sketch of decayed token counts with conservative update.
+///
+/// Memory is `width × depth` cells regardless of how many distinct tokens
+/// are seen. Until a token is retracted, counts are never underestimated;
+/// with `width = ⌈e/ε⌉` and `depth = ⌈ln(1/δ)⌉` the overestimate exceeds `ε`
+/// times the total decayed count with probability at most `δ`.
+///
+/// [`retract`](Dictionary::retract) lowers every cell the token maps to.
+/// Conservative update leaves a cell shared by two tokens below their sum,
+/// so retracting one of them can make the other read low.
+#[derive(Clone, Debug)]
+pub struct SketchDictionary<const L: usize> {
+    width: usize,
+    depth: usize,
+    cells: Vec<TokenStats>,
+}
+
+impl<const L: usize> SketchDictionary<L> {
+    pub fn new(width: usize, depth: usize) -> Self {
+        let (width, depth) = (width.max(1), depth.max(1));
+        Self {
+            width,
+            depth,
+            cells: vec![TokenStats::default(); width * depth],
+        }
+    }
+    /// Size the sketch for relative error `epsilon` with probability
+    /// `1 - delta`.
+    pub fn with_error_bounds(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1. / delta).ln().ceil() as usize;
+        Self::new(width, depth)
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+    /// Index of the cell `token` maps to in each row.
+    fn slots<'a>(&self, token: &'a str) -> impl Iterator<Item = usize> + use<'a, L> {
+        let width = self.width;
+        (0..self.depth).map(move |row| {
+            let mut hasher = DefaultHasher::new();
+            row.hash(&mut hasher);
+            token.hash(&mut hasher);
+            row * width + (hasher.finish() % width as u64) as usize
+        })
+    }
+}
+
+impl<const L: usize> Dictionary for SketchDictionary<L> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        let target = self.count(token, idx) + weight;
+        for slot in self.slots(token) {
+            let cell = &mut self.cells[slot];
+            // Conservative update: raise each cell only as far as needed.
+            *cell = TokenStats {
+                count: cell.decayed::<L>(idx).max(target),
+                last_idx: idx.max(cell.last_idx),
+            };
+        }
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        self.slots(token)
+            .map(|slot| self.cells[slot].decayed::<L>(idx))
+            .fold(f64::INFINITY, f64::min)
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        for slot in self.slots(token) {
+            let cell = &mut self.cells[slot];
+            *cell = cell.retracted::<L>(idx).unwrap_or_default();
+        }
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        Some(self.cells.capacity() * size_of::<TokenStats>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sketch_dictionary_approximates_counts() {
+        let mut dict = SketchDictionary::<2>::new(64, 4);
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        assert_eq!(dict.count("a", 3), 0.75);
+        dict.retract("a", 2);
+        assert_eq!(dict.count("a", 2), 0.5);
+
+        let mut small = SketchDictionary::<1000>::with_error_bounds(0.1, 0.01);
+        assert_eq!((small.width(), small.depth()), (28, 5));
+        for i in 0..200 {
+            small.observe(&format!("t{i}"), i);
+        }
+        small.observe("x", 200);
+        assert!(small.count("x", 200) >= 1.);
+    }
+
+    #[test]
+    fn sketch_retract_can_underestimate_colliding_tokens() {
+        let dict = SketchDictionary::<100>::new(2, 2);
+        let slots = |t: &str| dict.slots(t).collect::<Vec<_>>();
+        // `a` shares its first-row cell with `b` but not its second.
+        let b = "b".to_owned();
+        let a = (0..)
+            .map(|i| format!("a{i}"))
+            .find(|a| slots(a)[0] == slots(&b)[0] && slots(a)[1] != slots(&b)[1])
+            .unwrap();
+        let mut dict = dict;
+        dict.observe(&b, 1);
+        dict.observe(&a, 1);
+        assert_eq!(dict.count(&b, 1), 1.);
+        dict.retract(&a, 1);
+        assert_eq!(dict.count(&b, 1), 0.);
+    }
+}
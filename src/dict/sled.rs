@@ -0,0 +1,117 @@
+//! On-disk dictionary backed by a sled tree.
+
+use std::path::Path;
+
+use ::sled::{Db, Tree};
+
+use super::{Dictionary, TokenStats, top_counts};
+
+const TREE_NAME: &str = "chat-spike-dictionary";
+
+/// Dictionary persisted with sled, with the same per-chat decay as
+/// [`MemoryDictionary`](super::MemoryDictionary).
+///
+/// Updates are applied atomically per token, so several detectors may share
+/// one tree. `Dictionary` methods cannot fail: read errors count as an
+/// unknown token and write errors are kept for [`take_error`](Self::take_error).
+#[derive(Clone, Debug)]
+pub struct SledDictionary<const L: usize> {
+    tree: Tree,
+    error: Option<::sled::Error>,
+}
+
+impl<const L: usize> SledDictionary<L> {
+    /// Open or create the dictionary stored at `path`.
+    pub fn open(path: impl AsRef<Path>) -> ::sled::Result<Self> {
+        Self::from_db(&::sled::open(path)?)
+    }
+    /// Use the dictionary tree of an already opened database.
+    pub fn from_db(db: &Db) -> ::sled::Result<Self> {
+        Ok(Self::from_tree(db.open_tree(TREE_NAME)?))
+    }
+    pub fn from_tree(tree: Tree) -> Self {
+        Self { tree, error: None }
+    }
+    /// Flush pending writes to disk, returning the number of bytes flushed.
+    pub fn flush(&self) -> ::sled::Result<usize> {
+        self.tree.flush()
+    }
+    /// The first write error since the last call, if any.
+    pub fn take_error(&mut self) -> Option<::sled::Error> {
+        self.error.take()
+    }
+    fn update(&mut self, token: &str, f: impl Fn(TokenStats) -> Option<TokenStats>) {
+        let result = self.tree.update_and_fetch(token, |old| {
+            let stats = old.and_then(TokenStats::from_bytes).unwrap_or_default();
+            f(stats).map(|stats| stats.to_bytes().to_vec())
+        });
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl<const L: usize> Dictionary for SledDictionary<L> {
+    fn observe(&mut self, token: &str, idx: u32) {
+        self.observe_weighted(token, idx, 1.);
+    }
+    fn observe_weighted(&mut self, token: &str, idx: u32, weight: f64) {
+        self.update(token, |stats| Some(stats.observed::<L>(idx, weight)));
+    }
+    fn count(&self, token: &str, idx: u32) -> f64 {
+        match self.tree.get(token) {
+            Ok(Some(bytes)) => {
+                TokenStats::from_bytes(&bytes).map_or(0., |stats| stats.decayed::<L>(idx))
+            }
+            _ => 0.,
+        }
+    }
+    fn retract(&mut self, token: &str, idx: u32) {
+        if self.tree.contains_key(token).unwrap_or(false) {
+            self.update(token, |stats| stats.retracted::<L>(idx));
+        }
+    }
+    fn len(&self) -> Option<usize> {
+        Some(self.tree.len())
+    }
+    /// Scans the whole tree.
+    fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        let entries: Vec<(String, TokenStats)> = self
+            .tree
+            .iter()
+            .filter_map(Result::ok)
+            .filter_map(|(key, value)| {
+                let token = String::from_utf8(key.to_vec()).ok()?;
+                Some((token, TokenStats::from_bytes(&value)?))
+            })
+            .collect();
+        let idx = entries.iter().map(|(_, s)| s.last_idx).max().unwrap_or(0);
+        top_counts(
+            entries
+                .iter()
+                .map(|(token, stats)| (token.as_str(), stats.decayed::<L>(idx))),
+            n,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sled_dictionary_matches_memory_dictionary() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        let mut dict = SledDictionary::<2>::from_db(&db).unwrap();
+        dict.observe("a", 1);
+        dict.observe("a", 2);
+        assert_eq!(dict.count("a", 2), 1.5);
+        assert_eq!(dict.count("a", 3), 0.75);
+        dict.retract("a", 1);
+        dict.retract("a", 2);
+        dict.retract("b", 2);
+        assert_eq!(dict.count("a", 2), 0.);
+        assert!(db.open_tree(TREE_NAME).unwrap().is_empty());
+        assert!(dict.take_error().is_none());
+    }
+}
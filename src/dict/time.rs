@@ -0,0 +1,154 @@
+//! Dictionary whose counts decay with wall time instead of chat count.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{Dictionary, top_counts};
+
+#[derive(Clone, Copy, Debug)]
+struct TimedStats {
+    count: f64,
+    last_ts: Instant,
+}
+
+/// Token counts halving every `half_life` of wall time.
+///
+/// A quiet channel then forgets as fast as a busy one. The chat index
+/// passed through [`Dictionary`] is ignored: the `*_timed` methods, which
+/// `ChatWindow` uses for timestamped chats, go by the chat's time, and the
+/// others by `Instant::now()`. The inherent `*_at` methods take a time too.
+/// `ChatWindow`'s weight `ln(L / count)` still uses its own `L`, which should
+/// be on the order of the chats expected per `half_life / ln 2`.
+#[derive(Clone, Debug)]
+pub struct TimeDecayDictionary {
+    half_life: Duration,
+    entries: HashMap<String, TimedStats>,
+}
+
+impl TimeDecayDictionary {
+    pub fn with_half_life(half_life: Duration) -> Self {
+        Self {
+            half_life,
+            entries: HashMap::new(),
+        }
+    }
+    pub fn half_life(&self) -> Duration {
+        self.half_life
+    }
+    fn decayed(&self, stats: &TimedStats, ts: Instant) -> f64 {
+        let elapsed = ts.saturating_duration_since(stats.last_ts);
+        stats.count * 0.5f64.powf(elapsed.as_secs_f64() / self.half_life.as_secs_f64())
+    }
+    /// Record one occurrence of `token` at `ts`.
+    pub fn observe_at(&mut self, token: &str, ts: Instant) {
+        self.observe_weighted_at(token, ts, 1.);
+    }
+    /// Record an occurrence of `token` at `ts` counting as `weight`.
+    pub fn observe_weighted_at(&mut self, token: &str, ts: Instant, weight: f64) {
+        let count = self.count_at(token, ts) + weight;
+        let last_ts = self.entries.get(token).map_or(ts, |s| s.last_ts.max(ts));
+        self.entries
+            .insert(token.to_owned(), TimedStats { count, last_ts });
+    }
+    /// Decayed count of `token` as of `ts`.
+    pub fn count_at(&self, token: &str, ts: Instant) -> f64 {
+        self.entries
+            .get(token)
+            .map_or(0., |stats| self.decayed(stats, ts))
+    }
+    /// Take back one occurrence of `token`, assumed to have been observed at
+    /// its last update.
+    pub fn retract_at(&mut self, token: &str, ts: Instant) {
+        let Some(stats) = self.entries.get(token).copied() else {
+            return;
+        };
+        let count = self.decayed(&stats, ts) - self.decayed(&TimedStats { count: 1., ..stats }, ts);
+        if count <= 1e-9 {
+            self.entries.remove(token);
+        } else {
+            self.entries.insert(
+                token.to_owned(),
+                TimedStats {
+                    count,
+                    last_ts: stats.last_ts.max(ts),
+                },
+            );
+        }
+    }
+}
+
+impl Dictionary for TimeDecayDictionary {
+    fn observe(&mut self, token: &str, _idx: u32) {
+        self.observe_at(token, Instant::now());
+    }
+    fn count(&self, token: &str, _idx: u32) -> f64 {
+        self.count_at(token, Instant::now())
+    }
+    fn observe_weighted(&mut self, token: &str, _idx: u32, weight: f64) {
+        self.observe_weighted_at(token, Instant::now(), weight);
+    }
+    fn retract(&mut self, token: &str, _idx: u32) {
+        self.retract_at(token, Instant::now());
+    }
+    fn observe_timed(&mut self, token: &str, _idx: u32, ts: Instant, weight: f64) {
+        self.observe_weighted_at(token, ts, weight);
+    }
+    fn count_timed(&self, token: &str, _idx: u32, ts: Instant) -> f64 {
+        self.count_at(token, ts)
+    }
+    fn retract_timed(&mut self, token: &str, _idx: u32, ts: Instant) {
+        self.retract_at(token, ts);
+    }
+    fn len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+    fn approx_memory_bytes(&self) -> Option<usize> {
+        let slot = size_of::<(String, TimedStats)>() + 1;
+        let keys: usize = self.entries.keys().map(String::capacity).sum();
+        Some(self.entries.capacity() * slot + keys)
+    }
+    fn top_n(&self, n: usize) -> Vec<(String, f64)> {
+        let now = Instant::now();
+        top_counts(
+            self.entries
+                .iter()
+                .map(|(token, stats)| (token.as_str(), self.decayed(stats, now))),
+            n,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spike::ChatWindow;
+
+    #[test]
+    fn time_decay_dictionary_halves_per_half_life() {
+        let mut dict = TimeDecayDictionary::with_half_life(Duration::from_secs(10));
+        let t0 = Instant::now();
+        dict.observe_at("a", t0);
+        dict.observe_at("a", t0);
+        assert_eq!(dict.count_at("a", t0), 2.);
+        assert!((dict.count_at("a", t0 + Duration::from_secs(10)) - 1.).abs() < 1e-12);
+        assert!((dict.count_at("a", t0 + Duration::from_secs(20)) - 0.5).abs() < 1e-12);
+        dict.retract_at("a", t0);
+        assert_eq!(dict.count_at("a", t0), 1.);
+        dict.retract_at("a", t0);
+        assert_eq!(dict.count_at("a", t0), 0.);
+    }
+
+    #[test]
+    fn chat_window_decays_by_chat_timestamps() {
+        let mut dict = TimeDecayDictionary::with_half_life(Duration::from_secs(10));
+        let mut cw = ChatWindow::<4, 16>::default();
+        let t0 = Instant::now() + Duration::from_secs(3600);
+        cw.push_at_with_dict("a".into(), t0, None, &mut dict);
+        cw.push_at_with_dict("b".into(), t0 + Duration::from_secs(10), None, &mut dict);
+        // Counted as of the newest chat, not of the wall clock.
+        assert!((cw.token_weight("a", &dict) - (16f64 / 0.5).ln()).abs() < 1e-9);
+        assert_eq!(cw.token_weight("b", &dict), 16f64.ln());
+        cw.retract_with_dict(|c| c.chat() == "a", &mut dict);
+        assert_eq!(dict.count_at("a", t0), 0.);
+    }
+}
@@ -0,0 +1,90 @@
+//! Pre-detection message filters.
+//!
+//! A chat rejected by any filter installed with
+//! `ChatSpikeDetector::with_filter` is dropped before it touches the burst
+//! statistics, the dictionary or the summary window.
+
+use std::collections::HashSet;
+
+/// Decides whether a chat takes part in detection.
+pub trait MessageFilter: Send {
+    /// Return `false` to drop the chat.
+    fn accept(&self, chat: &str, author: Option<&str>) -> bool;
+}
+
+impl<F: Fn(&str, Option<&str>) -> bool + Send> MessageFilter for F {
+    fn accept(&self, chat: &str, author: Option<&str>) -> bool {
+        self(chat, author)
+    }
+}
+
+/// Drops bot commands such as `!drops`.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandFilter {
+    pub prefix: char,
+}
+
+impl Default for CommandFilter {
+    fn default() -> Self {
+        Self { prefix: '!' }
+    }
+}
+
+impl MessageFilter for CommandFilter {
+    fn accept(&self, chat: &str, _author: Option<&str>) -> bool {
+        !chat.trim_start().starts_with(self.prefix)
+    }
+}
+
+/// Drops chats from the listed authors, e.g. known bot accounts.
+#[derive(Clone, Default, Debug)]
+pub struct AuthorBlocklist {
+    authors: HashSet<String>,
+}
+
+impl AuthorBlocklist {
+    pub fn new(authors: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            authors: authors.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl MessageFilter for AuthorBlocklist {
+    fn accept(&self, _chat: &str, author: Option<&str>) -> bool {
+        author.is_none_or(|a| !self.authors.contains(a))
+    }
+}
+
+/// Drops chats made of nothing but links.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct UrlOnlyFilter;
+
+impl MessageFilter for UrlOnlyFilter {
+    fn accept(&self, chat: &str, _author: Option<&str>) -> bool {
+        let mut words = chat.split_whitespace().peekable();
+        words.peek().is_none() || !words.all(is_url)
+    }
+}
+
+pub(crate) fn is_url(word: &str) -> bool {
+    word.starts_with("http://") || word.starts_with("https://") || word.starts_with("www.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_filters() {
+        assert!(!CommandFilter::default().accept("  !drops", None));
+        assert!(CommandFilter::default().accept("wow!", None));
+        let bots = AuthorBlocklist::new(["nightbot"]);
+        assert!(!bots.accept("hi", Some("nightbot")));
+        assert!(bots.accept("hi", Some("viewer")));
+        assert!(bots.accept("hi", None));
+        assert!(!UrlOnlyFilter.accept("https://a.b www.c.d", None));
+        assert!(UrlOnlyFilter.accept("look https://a.b", None));
+        assert!(UrlOnlyFilter.accept("", None));
+    }
+}
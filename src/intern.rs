@@ -0,0 +1,210 @@
+//! String interning for tokens.
+//!
+//! Each distinct token is allocated once as an `Arc<str>`; chats holding
+//! the same n-gram share that allocation, and symbols compare by id.
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+/// Id of an interned string, valid for the [`Interner`] that issued it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Arena of interned strings addressed by [`Symbol`].
+///
+/// `S` builds the hasher of the text-to-symbol table.
+#[derive(Clone, Default, Debug)]
+pub struct Interner<S = RandomState> {
+    strings: Vec<Option<Arc<str>>>,
+    ids: HashMap<Arc<str>, Symbol, S>,
+    /// Slots freed by compaction, reused before growing.
+    free: Vec<Symbol>,
+}
+
+impl<S: BuildHasher> Interner<S> {
+    /// Symbol for `s`, allocating it on first sight.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&symbol) = self.ids.get(s) {
+            return symbol;
+        }
+        let s: Arc<str> = Arc::from(s);
+        let symbol = match self.free.pop() {
+            Some(symbol) => {
+                self.strings[symbol.index()] = Some(s.clone());
+                symbol
+            }
+            None => {
+                self.strings.push(Some(s.clone()));
+                Symbol(self.strings.len() as u32 - 1)
+            }
+        };
+        self.ids.insert(s, symbol);
+        symbol
+    }
+    /// Shared handle to the interned copy of `s`.
+    pub fn intern_arc(&mut self, s: &str) -> Arc<str> {
+        let symbol = self.intern(s);
+        self.arc(symbol)
+    }
+    /// Symbol of `s` if it is interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.ids.get(s).copied()
+    }
+    /// Text of a live symbol.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` was freed by a compaction.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.strings[symbol.index()]
+            .as_deref()
+            .expect("symbol was compacted away")
+    }
+    /// Shared handle to the text of a live symbol.
+    fn arc(&self, symbol: Symbol) -> Arc<str> {
+        self.strings[symbol.index()]
+            .clone()
+            .expect("symbol was compacted away")
+    }
+    /// Number of live strings.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+    /// Drop strings no longer referenced outside the interner. Symbols of
+    /// the strings kept stay valid; freed ones are reused.
+    pub fn compact(&mut self) {
+        self.compact_where(|_| false);
+    }
+    /// Like [`compact`](Self::compact), also keeping the symbols `keep`
+    /// holds on to, e.g. ones used as keys elsewhere.
+    pub fn compact_where(&mut self, mut keep: impl FnMut(Symbol) -> bool) {
+        for (i, slot) in self.strings.iter_mut().enumerate() {
+            let symbol = Symbol(i as u32);
+            // One reference each is held by `strings` and `ids`.
+            if let Some(s) = slot
+                && Arc::strong_count(s) <= 2
+                && !keep(symbol)
+            {
+                self.ids.remove(&**s);
+                *slot = None;
+                self.free.push(symbol);
+            }
+        }
+    }
+    /// Rough heap footprint in bytes.
+    pub fn approx_memory_bytes(&self) -> usize {
+        let text: usize = self.ids.keys().map(|s| s.len()).sum();
+        self.strings.capacity() * size_of::<Option<Arc<str>>>()
+            + self.ids.capacity() * (size_of::<(Arc<str>, Symbol)>() + 1)
+            + self.free.capacity() * size_of::<Symbol>()
+            + text
+    }
+}
+
+/// Map from [`Symbol`] to `V`, stored densely by symbol index.
+#[derive(Clone, Debug)]
+pub(crate) struct SymbolMap<V> {
+    slots: Vec<Option<V>>,
+    len: usize,
+}
+
+impl<V> Default for SymbolMap<V> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<V> SymbolMap<V> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+    pub fn get(&self, symbol: Symbol) -> Option<&V> {
+        self.slots.get(symbol.index())?.as_ref()
+    }
+    pub fn get_mut(&mut self, symbol: Symbol) -> Option<&mut V> {
+        self.slots.get_mut(symbol.index())?.as_mut()
+    }
+    pub fn contains_key(&self, symbol: Symbol) -> bool {
+        self.get(symbol).is_some()
+    }
+    fn grow_to(&mut self, symbol: Symbol) {
+        if self.slots.len() <= symbol.index() {
+            self.slots.resize_with(symbol.index() + 1, || None);
+        }
+    }
+    /// The value at `symbol`, inserting `value` if there is none.
+    pub fn get_or_insert(&mut self, symbol: Symbol, value: V) -> &mut V {
+        self.grow_to(symbol);
+        let slot = &mut self.slots[symbol.index()];
+        self.len += slot.is_none() as usize;
+        slot.get_or_insert(value)
+    }
+    pub fn insert(&mut self, symbol: Symbol, value: V) {
+        self.grow_to(symbol);
+        self.len += self.slots[symbol.index()].replace(value).is_none() as usize;
+    }
+    pub fn remove(&mut self, symbol: Symbol) -> Option<V> {
+        let removed = self.slots.get_mut(symbol.index())?.take();
+        self.len -= removed.is_some() as usize;
+        removed
+    }
+    pub fn retain(&mut self, mut f: impl FnMut(Symbol, &mut V) -> bool) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if let Some(v) = slot
+                && !f(Symbol(i as u32), v)
+            {
+                *slot = None;
+                self.len -= 1;
+            }
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &V)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| Some((Symbol(i as u32), v.as_ref()?)))
+    }
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.slots.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interner_shares_allocations() {
+        let mut interner = Interner::<RandomState>::default();
+        let a = interner.intern("pog");
+        assert_eq!(interner.intern("pog"), a);
+        assert_eq!(interner.resolve(a), "pog");
+        assert_eq!(interner.get("kek"), None);
+        let held = interner.intern_arc("kek");
+        assert!(Arc::ptr_eq(&held, &interner.intern_arc("kek")));
+        interner.compact();
+        assert_eq!(interner.len(), 1);
+        // Live symbols survive compaction and freed slots are reused.
+        assert_eq!(interner.get("kek"), Some(Symbol(1)));
+        assert_eq!(interner.intern("lul"), a);
+        interner.compact_where(|symbol| symbol == a);
+        assert_eq!(interner.resolve(a), "lul");
+    }
+}
@@ -1,6 +1,25 @@
+pub mod bocpd;
+#[cfg(feature = "async")]
+pub mod connectors;
+pub mod dict;
+pub mod filter;
+pub mod intern;
 pub mod math;
+pub mod normalizer;
 pub mod ring;
 pub mod spike;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "async")]
+pub mod task;
 pub mod text;
+pub mod tokenizer;
 
-pub use spike::{ChatSpikeDetector, Event, Phase};
+pub use dict::{Dictionary, MemoryDictionary};
+pub use filter::MessageFilter;
+pub use spike::{
+    AuthorPolicy, ChatSpikeDetector, ChatToken, ClockPolicy, CusumDetector, DegreeCentrality,
+    Event, Model, OwnedEvent, Phase, PushOutcome, SpikeReport, Summarizer, TokenDivergence,
+    TokenWeights,
+};
+pub use tokenizer::{Tokenizer, Tokens};
@@ -1,8 +1,32 @@
 use core::cmp::{Ordering, Ordering::*};
 use core::ops::{Deref, Neg};
-use statrs::distribution::{DiscreteCDF, Poisson};
-use statrs::function::erf::erfc;
 use std::f64::consts::{FRAC_1_SQRT_2, PI};
+use std::fmt::Debug;
+
+/// Float type of counts and scores: `f64` by default, or `f32` to halve
+/// memory on constrained targets.
+pub trait Float: num_traits::Float + Default + Debug + Send + Sync + 'static {
+    fn from_f64_lossy(x: f64) -> Self;
+    fn as_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn from_f64_lossy(x: f64) -> Self {
+        x as f32
+    }
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    fn from_f64_lossy(x: f64) -> Self {
+        x
+    }
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct Ordf64 {
@@ -28,7 +52,7 @@ impl Neg for Ordf64 {
 }
 impl PartialOrd for Ordf64 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some((*self).cmp(other))
+        Some(self.cmp(other))
     }
 }
 impl PartialEq for Ordf64 {
@@ -43,26 +67,522 @@ impl Ord for Ordf64 {
 }
 impl Eq for Ordf64 {}
 
-pub fn neg_ln_poisson_tail(k: f64, lambda: f64) -> f64 {
-    // ----- Small λ: exact ----------------------------
-    if lambda < 20.0 {
-        let p = Poisson::new(lambda).unwrap().sf(k.ceil() as u64);
-        -p.max(1e-308).ln()
-    } else if k < lambda || (k - lambda).abs() <= 4. * lambda.sqrt() {
-        // ---- Normal tail with continuity correction ----
-        let z = (k - lambda + 0.5) / lambda.sqrt();
-        let p = 0.5 * erfc(z * FRAC_1_SQRT_2);
-        -p.max(1e-308).ln()
+const LANCZOS_G: f64 = 7.;
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// `ln Γ(x)` for `x > 0`, by the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        // Reflection: Γ(x) Γ(1 - x) = π / sin(πx).
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1. - x);
+    }
+    let x = x - 1.;
+    let t = x + LANCZOS_G + 0.5;
+    let sum = LANCZOS[0]
+        + (1..LANCZOS.len())
+            .map(|i| LANCZOS[i] / (x + i as f64))
+            .sum::<f64>();
+    0.5 * (2. * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// `ln P(a, x)` by its power series, converging fast for `x < a + 1`.
+fn ln_gamma_p_series(a: f64, x: f64) -> f64 {
+    let (mut term, mut sum, mut n) = (1. / a, 1. / a, a);
+    for _ in 0..10_000 {
+        n += 1.;
+        term *= x / n;
+        sum += term;
+        if term.abs() < sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+    sum.ln() - x + a * x.ln() - ln_gamma(a)
+}
+
+/// `ln Q(a, x)` by its continued fraction (modified Lentz), converging
+/// fast for `x ≥ a + 1`.
+fn ln_gamma_q_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut b = x + 1. - a;
+    let (mut c, mut d) = (1. / TINY, 1. / b);
+    let mut h = d;
+    for i in 1..10_000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.;
+        d = an * d + b;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = b + an / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1. / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h.ln() - x + a * x.ln() - ln_gamma(a)
+}
+
+/// Log of the regularized lower incomplete gamma function `P(a, x)`.
+///
+/// Computed in log space, so tails far below `f64::MIN_POSITIVE` stay
+/// finite.
+pub fn ln_gamma_p(a: f64, x: f64) -> f64 {
+    if x <= 0. {
+        f64::NEG_INFINITY
+    } else if x < a + 1. {
+        ln_gamma_p_series(a, x)
+    } else {
+        (-ln_gamma_q_fraction(a, x).exp()).ln_1p()
+    }
+}
+
+/// Log of the regularized upper incomplete gamma function
+/// `Q(a, x) = 1 - P(a, x)`.
+pub fn ln_gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0. {
+        0.
+    } else if x < a + 1. {
+        (-ln_gamma_p_series(a, x).exp()).ln_1p()
+    } else {
+        ln_gamma_q_fraction(a, x)
+    }
+}
+
+/// Continued fraction of the incomplete beta function (modified Lentz),
+/// converging fast for `x < (a + 1) / (a + b + 2)`.
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+    let mut c = 1.;
+    let mut d = 1. / clamp(1. - (a + b) * x / (a + 1.));
+    let mut h = d;
+    for m in 1..10_000 {
+        let m = m as f64;
+        let m2 = 2. * m;
+        let even = m * (b - m) * x / ((a + m2 - 1.) * (a + m2));
+        d = 1. / clamp(1. + even * d);
+        c = clamp(1. + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.));
+        d = 1. / clamp(1. + odd * d);
+        c = clamp(1. + odd / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Log of the regularized incomplete beta function `I_x(a, b)`.
+pub fn ln_beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return f64::NEG_INFINITY;
+    } else if x >= 1. {
+        return 0.;
+    }
+    let ln_front = a * x.ln() + b * (-x).ln_1p() - ln_gamma(a) - ln_gamma(b) + ln_gamma(a + b);
+    if x < (a + 1.) / (a + b + 2.) {
+        ln_front + beta_fraction(a, b, x).ln() - a.ln()
     } else {
-        // ---- Saddle-point (Lugannani–Rice) ----
-        let s = k / lambda;
-        let t = (2.0 * lambda * (s - 1.0 - s.ln())).sqrt();
-        let w = t + (1.0 / s - 1.0) / t;
-        let ln_sf = -lambda * (s - 1.0 - s.ln()) - w.ln() - 0.5 * (2.0 * PI * k).ln();
-        -ln_sf
+        // I_x(a, b) = 1 - I_{1-x}(b, a).
+        let ln_rest = ln_front + beta_fraction(b, a, 1. - x).ln() - b.ln();
+        (-ln_rest.exp()).ln_1p()
+    }
+}
+
+/// `ln Q(z)`, the log upper tail of the standard normal distribution.
+fn ln_normal_sf(z: f64) -> f64 {
+    let ln_upper = 0.5f64.ln() + ln_gamma_q(0.5, 0.5 * z * z);
+    if z >= 0. {
+        ln_upper
+    } else {
+        (-ln_upper.exp()).ln_1p()
+    }
+}
+
+/// A surprise score `-ln p` of a tail probability `p`, as reported by the
+/// tail-based [`Model`](crate::spike::Model)s.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Surprise(pub f64);
+
+impl From<f64> for Surprise {
+    fn from(surprise: f64) -> Self {
+        Self(surprise)
+    }
+}
+
+impl Surprise {
+    /// The tail probability `p`.
+    pub fn p_value(self) -> f64 {
+        (-self.0).exp()
+    }
+    /// How many standard deviations out a normal variable has the same
+    /// one-sided tail probability, e.g. `3.0` for `p ≈ 0.00135`.
+    pub fn sigmas(self) -> f64 {
+        let target = -self.0;
+        // Newton on ln Q(z) = -surprise, from the tail asymptote.
+        let mut z = (2. * self.0).sqrt().max(-0.5);
+        for _ in 0..100 {
+            let ln_q = ln_normal_sf(z);
+            let ln_phi = -0.5 * z * z - 0.5 * (2. * PI).ln();
+            // d/dz ln Q(z) = -φ(z) / Q(z).
+            let step = (ln_q - target) / -(ln_phi - ln_q).exp();
+            z -= step;
+            if step.abs() < 1e-12 * (1. + z.abs()) {
+                break;
+            }
+        }
+        z
+    }
+    /// The surprise of a tail probability `p`.
+    pub fn from_p_value(p: f64) -> Self {
+        Self(-p.ln())
+    }
+    /// The surprise of a normal variable `sigmas` standard deviations out.
+    pub fn from_sigmas(sigmas: f64) -> Self {
+        Self(-ln_normal_sf(sigmas))
+    }
+}
+
+/// Start threshold for `with_threshold` that triggers at tail
+/// probabilities below `p`, e.g. `threshold_from_p(1e-4)`.
+pub fn threshold_from_p(p: f64) -> f64 {
+    Surprise::from_p_value(p).0
+}
+
+/// Start threshold for `with_threshold` that triggers beyond `sigmas`
+/// standard deviations, one-sided.
+pub fn threshold_from_sigmas(sigmas: f64) -> f64 {
+    Surprise::from_sigmas(sigmas).0
+}
+
+/// Complementary error function, as `Q(1/2, x²)`.
+pub fn erfc(x: f64) -> f64 {
+    let q = ln_gamma_q(0.5, x * x).exp();
+    if x >= 0. { q } else { 2. - q }
+}
+
+/// `-ln P(X > n)` for `X ~ Poisson(λ)`, exactly: `P(X > n) = P(n + 1, λ)`.
+pub fn neg_ln_poisson_sf(n: u64, lambda: f64) -> f64 {
+    -ln_gamma_p(n as f64 + 1., lambda)
+}
+
+/// `-ln P(X > k)` for `X ~ Poisson(λ)`, exact for small `λ` and
+/// approximated otherwise.
+///
+/// Fractional `k`, as left by EWMA smoothing, is not rounded: the exact
+/// branch uses `P(X > k) = P(k + 1, λ)` with the regularized incomplete
+/// gamma function, which is continuous in `k` like the approximations.
+///
+/// The special functions are evaluated in `f64` whatever `F` is, as single
+/// precision loses the far tails, and the result is rounded to `F`.
+///
+/// NaN, negative or infinite `λ` and NaN `k` give an unspecified value;
+/// use [`try_neg_ln_poisson_tail`] when inputs may be degenerate.
+pub fn neg_ln_poisson_tail<F: Float>(k: F, lambda: F) -> F {
+    neg_ln_poisson_tail_with(k, lambda, TailAccuracy::Balanced)
+}
+
+/// Which evaluations [`neg_ln_poisson_tail_with`] may use.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum TailAccuracy {
+    /// Normal and saddle-point approximations only: cheapest, but off by
+    /// about a nat in the tail for small `λ`.
+    Fast,
+    /// Exact for `λ < 20`, approximated above, as [`neg_ln_poisson_tail`].
+    #[default]
+    Balanced,
+    /// Always the incomplete gamma function: slowest, and the reference
+    /// for diagnosing threshold drift caused by the approximations.
+    Exact,
+}
+
+impl TailAccuracy {
+    fn branch(self, k: f64, lambda: f64) -> TailBranch {
+        match (self, TailBranch::of(k, lambda)) {
+            (TailAccuracy::Exact, _) => TailBranch::Exact,
+            (TailAccuracy::Fast, TailBranch::Exact) => TailBranch::approximate(k, lambda),
+            (_, branch) => branch,
+        }
+    }
+}
+
+/// [`neg_ln_poisson_tail`] with a chosen trade of speed for precision.
+pub fn neg_ln_poisson_tail_with<F: Float>(k: F, lambda: F, accuracy: TailAccuracy) -> F {
+    let (k, lambda) = (k.as_f64(), lambda.as_f64());
+    F::from_f64_lossy(accuracy.branch(k, lambda).eval(k, lambda))
+}
+
+/// [`neg_ln_poisson_tail`], or `None` if `k` is NaN or `λ` is NaN,
+/// negative or infinite. `λ = 0` expects no events, so any count scores the
+/// cap of `-ln 1e-308`.
+pub fn try_neg_ln_poisson_tail<F: Float>(k: F, lambda: F) -> Option<F> {
+    let valid = !k.is_nan() && lambda >= F::zero() && lambda.is_finite();
+    valid.then(|| neg_ln_poisson_tail(k, lambda))
+}
+
+/// `-ln P(X > k)` for a negative binomial `X` with the given mean and
+/// index of dispersion `variance / mean`, continuous in `k`.
+///
+/// A dispersion of one or less is Poisson and falls back to
+/// [`neg_ln_poisson_tail`].
+pub fn neg_ln_neg_binomial_tail<F: Float>(k: F, mean: F, dispersion: F) -> F {
+    let (k, mean, dispersion) = (k.as_f64(), mean.as_f64(), dispersion.as_f64());
+    if dispersion.is_nan() || dispersion <= 1. + 1e-9 {
+        return F::from_f64_lossy(neg_ln_poisson_tail_f64(k, mean));
+    }
+    // Variance `mean + mean² / r`; failure probability `1 - 1 / dispersion`.
+    let r = mean / (dispersion - 1.);
+    let q = 1. - 1. / dispersion;
+    let neg_ln = -ln_beta_inc(k + 1., r, q);
+    F::from_f64_lossy(neg_ln.min(-(1e-308f64).ln()))
+}
+
+/// Log-likelihood ratio of `k1` events in time `t1` and `k2` events in time
+/// `t2` having their own Poisson rates rather than a shared one.
+///
+/// Zero when the rates are equal and growing as they part, in either
+/// direction.
+pub fn ln_rate_ratio<F: Float>(k1: F, t1: F, k2: F, t2: F) -> F {
+    let [k1, t1, k2, t2] = [k1, t1, k2, t2].map(F::as_f64);
+    let pooled = (k1 + k2) / (t1 + t2);
+    let term = |k: f64, t: f64| {
+        if k > 0. {
+            k * (k / (t * pooled)).ln()
+        } else {
+            0.
+        }
+    };
+    F::from_f64_lossy((term(k1, t1) + term(k2, t2)).max(0.))
+}
+
+/// Terms `pᵢ ln(pᵢ / qᵢ)` of the KL divergence of `p` from `q`, after
+/// normalizing both weight vectors to sum to one; they sum to the
+/// divergence. A term is infinite where `q` is zero and `p` is not.
+pub fn kl_terms(p: &[f64], q: &[f64]) -> Vec<f64> {
+    let (p_sum, q_sum) = (p.iter().sum::<f64>(), q.iter().sum::<f64>());
+    p.iter()
+        .zip(q)
+        .map(|(&p, &q)| {
+            let (p, q) = (p / p_sum, q / q_sum);
+            if p > 0. { p * (p / q).ln() } else { 0. }
+        })
+        .collect()
+}
+
+/// Kullback-Leibler divergence `KL(p ‖ q)` in nats between two weight
+/// vectors over the same outcomes, see [`kl_terms`].
+pub fn kl_divergence(p: &[f64], q: &[f64]) -> f64 {
+    kl_terms(p, q).iter().sum()
+}
+
+/// Jensen-Shannon divergence in nats: symmetric, finite, and at most
+/// `ln 2`.
+pub fn js_divergence(p: &[f64], q: &[f64]) -> f64 {
+    let (p_sum, q_sum) = (p.iter().sum::<f64>(), q.iter().sum::<f64>());
+    let p: Vec<f64> = p.iter().map(|x| x / p_sum).collect();
+    let q: Vec<f64> = q.iter().map(|x| x / q_sum).collect();
+    let m: Vec<f64> = p.iter().zip(&q).map(|(a, b)| (a + b) / 2.).collect();
+    (kl_divergence(&p, &m) + kl_divergence(&q, &m)) / 2.
+}
+
+/// Streaming estimate of one quantile in constant memory, by the P²
+/// algorithm (Jain & Chlamtac, 1985).
+#[derive(Clone, Debug)]
+pub struct P2Quantile {
+    p: f64,
+    /// Marker heights: min, p/2, p, (1+p)/2 and max quantile estimates.
+    heights: [f64; 5],
+    /// Marker positions, from one.
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+    count: usize,
+}
+
+impl P2Quantile {
+    /// Track the `p` quantile, `0 ≤ p ≤ 1`.
+    pub fn new(p: f64) -> Self {
+        let p = p.clamp(0., 1.);
+        Self {
+            p,
+            heights: [0.; 5],
+            positions: [1., 2., 3., 4., 5.],
+            desired: [1., 1. + 2. * p, 1. + 4. * p, 3. + 2. * p, 5.],
+            increments: [0., p / 2., p, (1. + p) / 2., 1.],
+            count: 0,
+        }
+    }
+    /// Add a sample; NaN is ignored.
+    pub fn push(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+        let h = &mut self.heights;
+        let cell = if x < h[0] {
+            h[0] = x;
+            0
+        } else if x >= h[4] {
+            h[4] = x;
+            3
+        } else {
+            (0..4).rfind(|&i| h[i] <= x).unwrap_or(0)
+        };
+        self.positions[cell + 1..].iter_mut().for_each(|n| *n += 1.);
+        for (desired, inc) in self.desired.iter_mut().zip(self.increments) {
+            *desired += inc;
+        }
+        for i in 1..4 {
+            let n = &mut self.positions;
+            let d = self.desired[i] - n[i];
+            if (d >= 1. && n[i + 1] - n[i] > 1.) || (d <= -1. && n[i - 1] - n[i] < -1.) {
+                let s = d.signum();
+                let parabolic = h[i]
+                    + s / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + s) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - s) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]));
+                h[i] = if h[i - 1] < parabolic && parabolic < h[i + 1] {
+                    parabolic
+                } else {
+                    let j = if s > 0. { i + 1 } else { i - 1 };
+                    h[i] + s * (h[j] - h[i]) / (n[j] - n[i])
+                };
+                n[i] += s;
+            }
+        }
+    }
+    /// The quantile estimate, exact while fewer than five samples are in;
+    /// `None` before any.
+    pub fn estimate(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            n @ 1..5 => {
+                let mut seen = self.heights;
+                seen[..n].sort_by(f64::total_cmp);
+                Some(seen[(self.p * (n - 1) as f64).round() as usize])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+    /// Number of samples pushed.
+    pub fn count(&self) -> usize {
+        self.count
     }
 }
 
+/// [`neg_ln_poisson_tail`] over many `(k, λ)` pairs, written to `out`.
+///
+/// Pairs are grouped by the branch that evaluates them, so each branch runs
+/// as one tight loop instead of being picked anew per pair; worthwhile for
+/// parameter sweeps over thousands of pairs.
+///
+/// # Panics
+///
+/// If `ks`, `lambdas` and `out` differ in length.
+pub fn neg_ln_poisson_tail_many<F: Float>(ks: &[F], lambdas: &[F], out: &mut [F]) {
+    assert!(
+        ks.len() == lambdas.len() && ks.len() == out.len(),
+        "length mismatch: {} ks, {} lambdas, {} outputs",
+        ks.len(),
+        lambdas.len(),
+        out.len()
+    );
+    let mut groups: [Vec<usize>; 3] = Default::default();
+    for (i, (k, lambda)) in ks.iter().zip(lambdas).enumerate() {
+        groups[TailBranch::of(k.as_f64(), lambda.as_f64()) as usize].push(i);
+    }
+    for (branch, group) in [
+        TailBranch::Exact,
+        TailBranch::Normal,
+        TailBranch::SaddlePoint,
+    ]
+    .into_iter()
+    .zip(&groups)
+    {
+        for &i in group {
+            let value = branch.eval(ks[i].as_f64(), lambdas[i].as_f64());
+            out[i] = F::from_f64_lossy(value);
+        }
+    }
+}
+
+/// How [`neg_ln_poisson_tail`] evaluates a `(k, λ)` pair.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TailBranch {
+    /// Small `λ`: the incomplete gamma function.
+    Exact,
+    /// Normal tail with continuity correction.
+    Normal,
+    /// Saddle-point (Lugannani–Rice) far in the upper tail.
+    SaddlePoint,
+}
+
+impl TailBranch {
+    fn of(k: f64, lambda: f64) -> Self {
+        if lambda < 20.0 {
+            TailBranch::Exact
+        } else {
+            Self::approximate(k, lambda)
+        }
+    }
+    fn approximate(k: f64, lambda: f64) -> Self {
+        if k < lambda || (k - lambda).abs() <= 4. * lambda.sqrt() {
+            TailBranch::Normal
+        } else {
+            TailBranch::SaddlePoint
+        }
+    }
+    fn eval(self, k: f64, lambda: f64) -> f64 {
+        match self {
+            // Capped at p = 1e-308 like the approximate branches.
+            TailBranch::Exact => (-ln_gamma_p(k + 1., lambda)).min(-(1e-308f64).ln()),
+            TailBranch::Normal => {
+                let z = (k - lambda + 0.5) / lambda.sqrt();
+                let p = 0.5 * erfc(z * FRAC_1_SQRT_2);
+                -p.max(1e-308).ln()
+            }
+            TailBranch::SaddlePoint => {
+                // Lattice Lugannani–Rice for P(X ≥ m), m = k + 1 > λ:
+                // Q(w) + φ(w) (1/u - 1/w), evaluated relative to φ(w).
+                let m = k + 1.;
+                let s = m / lambda;
+                let w = (2. * lambda * (s * s.ln() - s + 1.)).sqrt();
+                let u = (1. - 1. / s) * m.sqrt();
+                let ln_phi = -0.5 * w * w - 0.5 * (2. * PI).ln();
+                let ln_q = 0.5f64.ln() + ln_gamma_q(0.5, 0.5 * w * w);
+                let mills = (ln_q - ln_phi).exp();
+                -(ln_phi + (mills + 1. / u - 1. / w).ln())
+            }
+        }
+    }
+}
+
+fn neg_ln_poisson_tail_f64(k: f64, lambda: f64) -> f64 {
+    TailBranch::of(k, lambda).eval(k, lambda)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,6 +599,208 @@ mod tests {
         (p_est - p_exact).abs()
     }
 
+    #[test]
+    fn special_functions_match_statrs() {
+        use statrs::function::{erf, gamma};
+        // Compared in log space, i.e. to a relative error.
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9 * (1. + b.abs());
+        for x in [0.1, 0.5, 1., 2.5, 7., 20., 150.] {
+            assert!(close(ln_gamma(x), gamma::ln_gamma(x)), "x={x}");
+            assert!(close(erfc(x / 10.).ln(), erf::erfc(x / 10.).ln()), "x={x}");
+            assert!(
+                close(erfc(-x / 10.).ln(), erf::erfc(-x / 10.).ln()),
+                "x={x}"
+            );
+            for a in [0.5, 1., 3., 12.5, 40.] {
+                let (p, q) = (gamma::gamma_lr(a, x), gamma::gamma_ur(a, x));
+                assert!(close(ln_gamma_p(a, x), p.ln()), "a={a} x={x}");
+                assert!(close(ln_gamma_q(a, x), q.ln()), "a={a} x={x}");
+            }
+        }
+        for (n, lambda) in [(0, 0.3), (3, 2.), (12, 7.3), (40, 19.9)] {
+            let exact = Poisson::new(lambda).unwrap().sf(n);
+            assert!(close(neg_ln_poisson_sf(n, lambda), -exact.ln()));
+        }
+    }
+
+    #[test]
+    fn neg_binomial_tail_matches_statrs() {
+        use statrs::distribution::NegativeBinomial;
+        use statrs::function::beta;
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-8 * (1. + b.abs());
+        for (a, b, x) in [(0.5, 2., 0.3), (4., 1.5, 0.9), (30., 12., 0.6)] {
+            assert!(close(ln_beta_inc(a, b, x), beta::beta_reg(a, b, x).ln()));
+        }
+        for (k, mean, dispersion) in [(3, 2., 1.5), (30, 10., 4.), (8, 40., 2.)] {
+            let p = NegativeBinomial::new(mean / (dispersion - 1.), 1. / dispersion)
+                .unwrap()
+                .sf(k);
+            let est = neg_ln_neg_binomial_tail(k as f64, mean, dispersion);
+            assert!(close(est, -p.ln()), "k={k}: {est} vs {}", -p.ln());
+        }
+        // Overdispersion makes the same count less surprising.
+        assert!(neg_ln_neg_binomial_tail(30., 10., 4.) < neg_ln_poisson_tail(30., 10.));
+        assert_eq!(
+            neg_ln_neg_binomial_tail(5., 3., 1.),
+            neg_ln_poisson_tail(5., 3.)
+        );
+    }
+
+    #[test]
+    fn rate_ratio_is_zero_at_equal_rates() {
+        assert_eq!(ln_rate_ratio(5., 10., 50., 100.), 0.);
+        let faster = ln_rate_ratio(5., 2., 50., 100.);
+        let hand = 5. * (5f64 / (2. * 55. / 102.)).ln() + 50. * (50f64 / (100. * 55. / 102.)).ln();
+        assert!((faster - hand).abs() < 1e-12);
+        assert!(ln_rate_ratio(5., 1., 50., 100.) > faster);
+        assert!(ln_rate_ratio(0., 1., 50., 100.) > 0.);
+    }
+
+    #[test]
+    fn divergences() {
+        let (p, q) = ([1., 1., 2.], [2., 2., 4.]);
+        assert!(kl_divergence(&p, &q).abs() < 1e-12);
+        let (p, q) = ([3., 1.], [1., 1.]);
+        let kl = 0.75 * 1.5f64.ln() + 0.25 * 0.5f64.ln();
+        assert!((kl_divergence(&p, &q) - kl).abs() < 1e-12);
+        assert_eq!(kl_divergence(&[1., 1.], &[1., 0.]), f64::INFINITY);
+        let js = js_divergence(&[1., 0.], &[0., 1.]);
+        assert!((js - 2f64.ln()).abs() < 1e-12);
+        assert_eq!(js_divergence(&p, &q), js_divergence(&q, &p));
+    }
+
+    #[test]
+    fn p2_quantile_tracks_exact() {
+        let mut state = 42u64;
+        let mut samples = Vec::new();
+        let mut estimators = [P2Quantile::new(0.5), P2Quantile::new(0.995)];
+        assert_eq!(estimators[0].estimate(), None);
+        for _ in 0..20_000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // Exponential samples, skewed like surprises.
+            let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+            let x = -(-u).ln_1p();
+            samples.push(x);
+            estimators.iter_mut().for_each(|e| e.push(x));
+        }
+        samples.sort_by(f64::total_cmp);
+        for (e, p) in estimators.iter().zip([0.5, 0.995]) {
+            let exact = samples[(p * (samples.len() - 1) as f64) as usize];
+            let est = e.estimate().unwrap();
+            assert!(
+                (est - exact).abs() < 0.05 * exact,
+                "p={p}: {est} vs {exact}"
+            );
+        }
+        let mut few = P2Quantile::new(1.);
+        [3., 1., 2.].into_iter().for_each(|x| few.push(x));
+        assert_eq!(few.estimate(), Some(3.));
+    }
+
+    #[test]
+    fn degenerate_tail_inputs() {
+        assert_eq!(try_neg_ln_poisson_tail(3., f64::NAN), None);
+        assert_eq!(try_neg_ln_poisson_tail(3., -1.), None);
+        assert_eq!(try_neg_ln_poisson_tail(3., f64::INFINITY), None);
+        assert_eq!(try_neg_ln_poisson_tail(f64::NAN, 1.), None);
+        assert_eq!(try_neg_ln_poisson_tail(3., 0.), Some(-(1e-308f64).ln()));
+        assert_eq!(
+            try_neg_ln_poisson_tail(3., 2.),
+            Some(neg_ln_poisson_tail(3., 2.))
+        );
+    }
+
+    #[test]
+    fn tail_accuracy_modes() {
+        let exact = |k: f64, lambda: f64| -ln_gamma_p(k + 1., lambda);
+        for (k, lambda) in [(5., 2.), (30., 50.), (80., 50.), (200., 60.), (2000., 900.)] {
+            let got = neg_ln_poisson_tail_with(k, lambda, TailAccuracy::Exact);
+            assert!((got - exact(k, lambda)).abs() < 1e-9 * exact(k, lambda));
+            let fast = neg_ln_poisson_tail_with(k, lambda, TailAccuracy::Fast);
+            assert!(
+                (fast - exact(k, lambda)).abs() < 1.,
+                "k={k} λ={lambda}: {fast} vs {}",
+                exact(k, lambda)
+            );
+        }
+        assert_ne!(
+            neg_ln_poisson_tail_with(5., 2., TailAccuracy::Fast),
+            neg_ln_poisson_tail(5., 2.)
+        );
+        assert_eq!(
+            neg_ln_poisson_tail_with(30., 50., TailAccuracy::Fast),
+            neg_ln_poisson_tail(30., 50.)
+        );
+    }
+
+    #[test]
+    fn saddle_point_tail_accuracy() {
+        // The previous saddle-point formula, from the continuous rate function.
+        let previous = |k: f64, lambda: f64| {
+            let s = k / lambda;
+            let t = (2.0 * lambda * (s - 1.0 - s.ln())).sqrt();
+            let w = t + (1.0 / s - 1.0) / t;
+            lambda * (s - 1.0 - s.ln()) + w.ln() + 0.5 * (2.0 * PI * k).ln()
+        };
+        for (k, lambda) in [(80., 50.), (150., 20.), (200., 60.), (2000., 900.)] {
+            assert_eq!(TailBranch::of(k, lambda), TailBranch::SaddlePoint);
+            let exact = -ln_gamma_p(k + 1., lambda);
+            let now = neg_ln_poisson_tail(k, lambda);
+            assert!(
+                (now - exact).abs() < 1e-4,
+                "k={k} λ={lambda}: {now} vs {exact}"
+            );
+            assert!((previous(k, lambda) - exact).abs() > 0.5);
+        }
+    }
+
+    #[test]
+    fn surprise_conversions() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9 * (1. + b.abs());
+        assert!(close(threshold_from_p(1e-4), 4. * 10f64.ln()));
+        assert!(close(Surprise(threshold_from_p(1e-4)).p_value(), 1e-4));
+        // One-sided normal tails.
+        assert!(close(threshold_from_sigmas(0.), 2f64.ln()));
+        assert!(close(
+            Surprise::from_p_value(0.001_349_898_031_630_094_6).sigmas(),
+            3.
+        ));
+        for sigmas in [-1.5, 0.3, 4., 12., 30.] {
+            assert!(
+                close(Surprise::from_sigmas(sigmas).sigmas(), sigmas),
+                "{sigmas}"
+            );
+        }
+    }
+
+    #[test]
+    fn batch_tail_matches_scalar() {
+        let ks = [5., 20., 80., 3.5, 300., 0.];
+        let lambdas = [7.3, 50., 50., 1., 100., 25.];
+        let mut out = [0.; 6];
+        neg_ln_poisson_tail_many(&ks, &lambdas, &mut out);
+        for ((k, lambda), got) in ks.iter().zip(&lambdas).zip(out) {
+            assert_eq!(got, neg_ln_poisson_tail(*k, *lambda));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "length mismatch")]
+    fn batch_tail_checks_lengths() {
+        neg_ln_poisson_tail_many(&[1f64], &[1.], &mut []);
+    }
+
+    #[test]
+    fn fractional_k_is_continuous() {
+        let lam = 3.;
+        let at = |k: f64| neg_ln_poisson_tail(k, lam);
+        assert!((at(4. + 1e-9) - at(4.)).abs() < 1e-6);
+        assert!((at(4.) - neg_ln_poisson_sf(4, lam)).abs() < 1e-12);
+        assert!(at(4.) < at(4.5) && at(4.5) < at(5.));
+    }
+
     #[test]
     fn small_lambda_branch() {
         let k = 5.0;
@@ -0,0 +1,372 @@
+//! Configurable normalization of chats before tokenization.
+
+use std::sync::Arc;
+use std::{fmt, mem};
+
+#[cfg(feature = "korean")]
+use crate::text::decompose_jamo;
+use crate::text::{
+    ByLanguage, CaseFold, Interjections, collapse_whitespace, decode_markup, derepeat_periods,
+    derepeat_periods_into, fold_case, fold_confusables, fold_width, limit_combining_marks,
+    space_around_interjections, space_around_interjections_into, truncate_chars,
+};
+#[cfg(feature = "unicode-normalization")]
+use crate::text::{UnicodeForm, normalize_unicode};
+
+/// One normalization step.
+pub trait Normalizer {
+    fn normalize(&self, text: &str) -> String;
+    /// Write the normalized `text` to `out`, replacing its contents. Steps
+    /// that can reuse the allocation of `out` override this.
+    fn normalize_into(&self, text: &str, out: &mut String) {
+        *out = self.normalize(text);
+    }
+}
+
+impl<F: Fn(&str) -> String> Normalizer for F {
+    fn normalize(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Collapse repeats as [`derepeat_periods`] does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Derepeat {
+    /// Copies of a repeated sequence to keep.
+    pub n: usize,
+    /// Longest repeated sequence to look for.
+    pub max_period: usize,
+}
+
+impl Normalizer for Derepeat {
+    fn normalize(&self, text: &str) -> String {
+        derepeat_periods(text, self.n, self.max_period)
+    }
+    fn normalize_into(&self, text: &str, out: &mut String) {
+        derepeat_periods_into(text, self.n, self.max_period, out);
+    }
+}
+
+/// Strip zalgo text: keep at most `max_per_char` combining marks after
+/// each base character, so stacked diacritics do not explode into unique
+/// n-grams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitCombiningMarks {
+    pub max_per_char: usize,
+}
+
+impl Normalizer for LimitCombiningMarks {
+    fn normalize(&self, text: &str) -> String {
+        limit_combining_marks(text, self.max_per_char)
+    }
+}
+
+/// Fold full- and half-width forms, see [`fold_width`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct FoldWidth;
+
+impl Normalizer for FoldWidth {
+    fn normalize(&self, text: &str) -> String {
+        fold_width(text)
+    }
+}
+
+/// Fold lookalike characters to Latin, see [`fold_confusables`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct FoldConfusables;
+
+impl Normalizer for FoldConfusables {
+    fn normalize(&self, text: &str) -> String {
+        fold_confusables(text)
+    }
+}
+
+/// Collapse whitespace runs to single spaces, see [`collapse_whitespace`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct CollapseWhitespace;
+
+impl Normalizer for CollapseWhitespace {
+    fn normalize(&self, text: &str) -> String {
+        collapse_whitespace(text)
+    }
+}
+
+/// Keep the first `max_chars` characters of a chat, see
+/// [`truncate_chars`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Truncate {
+    pub max_chars: usize,
+}
+
+impl Normalizer for Truncate {
+    fn normalize(&self, text: &str) -> String {
+        truncate_chars(text, self.max_chars).to_owned()
+    }
+}
+
+/// Decode HTML entities and markdown escapes, see [`decode_markup`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct DecodeMarkup;
+
+impl Normalizer for DecodeMarkup {
+    fn normalize(&self, text: &str) -> String {
+        decode_markup(text)
+    }
+}
+
+impl Normalizer for CaseFold {
+    fn normalize(&self, text: &str) -> String {
+        fold_case(text, *self)
+    }
+}
+
+/// Split Hangul syllables into jamo, see [`decompose_jamo`]. Summaries
+/// then show the decomposed text; use
+/// [`JamoNgrams`](crate::tokenizer::JamoNgrams) to keep them readable.
+#[cfg(feature = "korean")]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct DecomposeJamo;
+
+#[cfg(feature = "korean")]
+impl Normalizer for DecomposeJamo {
+    fn normalize(&self, text: &str) -> String {
+        decompose_jamo(text)
+    }
+}
+
+impl<T: Normalizer> Normalizer for ByLanguage<T> {
+    fn normalize(&self, text: &str) -> String {
+        self.pick(text).normalize(text)
+    }
+}
+
+impl Normalizer for Interjections {
+    fn normalize(&self, text: &str) -> String {
+        space_around_interjections(text, self)
+    }
+    fn normalize_into(&self, text: &str, out: &mut String) {
+        space_around_interjections_into(text, self, out);
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl Normalizer for UnicodeForm {
+    fn normalize(&self, text: &str) -> String {
+        normalize_unicode(text, *self)
+    }
+}
+
+/// Kind of word removed by [`Strip`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// `http(s)://…`, `www.…` or anything else with a `://` scheme.
+    Url,
+    /// `@username`.
+    Mention,
+    /// Bot commands such as `!uptime`.
+    Command,
+}
+
+impl Pattern {
+    pub fn matches(self, word: &str) -> bool {
+        match self {
+            Pattern::Url => word.contains("://") || word.starts_with("www."),
+            Pattern::Mention => word.len() > 1 && word.starts_with('@'),
+            Pattern::Command => word
+                .strip_prefix('!')
+                .and_then(|rest| rest.chars().next())
+                .is_some_and(|c| c.is_ascii_alphabetic()),
+        }
+    }
+}
+
+/// Drop whitespace-separated words matching a [`Pattern`], or replace them
+/// with a placeholder token such as `<url>`.
+///
+/// Whitespace between the remaining words is collapsed to single spaces.
+/// Runs best first in the pipeline, before repeat collapsing mangles links.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Strip {
+    pub pattern: Pattern,
+    pub placeholder: Option<String>,
+}
+
+impl Strip {
+    pub fn urls() -> Self {
+        Self {
+            pattern: Pattern::Url,
+            placeholder: None,
+        }
+    }
+    pub fn mentions() -> Self {
+        Self {
+            pattern: Pattern::Mention,
+            placeholder: None,
+        }
+    }
+    pub fn commands() -> Self {
+        Self {
+            pattern: Pattern::Command,
+            placeholder: None,
+        }
+    }
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+}
+
+impl Normalizer for Strip {
+    fn normalize(&self, text: &str) -> String {
+        let words: Vec<&str> = text
+            .split_whitespace()
+            .filter_map(
+                |word| match (self.pattern.matches(word), &self.placeholder) {
+                    (false, _) => Some(word),
+                    (true, placeholder) => placeholder.as_deref(),
+                },
+            )
+            .collect();
+        words.join(" ")
+    }
+}
+
+/// Normalization steps applied in order.
+///
+/// The default pipeline is what [`normalize`](crate::text::normalize) does:
+/// collapse repeats, then split off Korean interjections.
+///
+/// ```rust
+/// use chat_spike::normalizer::NormalizerPipeline;
+/// use chat_spike::text::Interjections;
+///
+/// let pipeline = NormalizerPipeline::new()
+///     .derepeat(3, 4)
+///     .interjections(Interjections::english())
+///     .then(|s: &str| s.to_lowercase());
+/// ```
+#[derive(Clone)]
+pub struct NormalizerPipeline {
+    steps: Vec<Arc<dyn Normalizer + Send + Sync>>,
+}
+
+impl Default for NormalizerPipeline {
+    fn default() -> Self {
+        Self::new()
+            .derepeat(3, 4)
+            .interjections(Interjections::korean())
+    }
+}
+
+impl fmt::Debug for NormalizerPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NormalizerPipeline")
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
+
+impl NormalizerPipeline {
+    /// A pipeline with no steps, leaving chats as they are.
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+    /// Append a step, e.g. a closure or another pipeline.
+    pub fn then(mut self, step: impl Normalizer + Send + Sync + 'static) -> Self {
+        self.steps.push(Arc::new(step));
+        self
+    }
+    pub fn derepeat(self, n: usize, max_period: usize) -> Self {
+        self.then(Derepeat { n, max_period })
+    }
+    pub fn limit_combining_marks(self, max_per_char: usize) -> Self {
+        self.then(LimitCombiningMarks { max_per_char })
+    }
+    /// Decode HTML entities and markdown escapes; best placed first.
+    pub fn decode_markup(self) -> Self {
+        self.then(DecodeMarkup)
+    }
+    pub fn collapse_whitespace(self) -> Self {
+        self.then(CollapseWhitespace)
+    }
+    /// Cut chats to `max_chars` characters, so one huge paste does not
+    /// dominate the window's token statistics.
+    pub fn truncate(self, max_chars: usize) -> Self {
+        self.then(Truncate { max_chars })
+    }
+    pub fn fold_confusables(self) -> Self {
+        self.then(FoldConfusables)
+    }
+    pub fn fold_width(self) -> Self {
+        self.then(FoldWidth)
+    }
+    pub fn fold_case(self, fold: CaseFold) -> Self {
+        self.then(fold)
+    }
+    pub fn interjections(self, interjections: Interjections) -> Self {
+        self.then(interjections)
+    }
+    #[cfg(feature = "unicode-normalization")]
+    pub fn unicode(self, form: UnicodeForm) -> Self {
+        self.then(form)
+    }
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    /// Normalize `text` into `out`, passing it between the steps through
+    /// `scratch`. Once both buffers have grown to fit, steps that override
+    /// [`normalize_into`](Normalizer::normalize_into), such as the default
+    /// ones, do not allocate.
+    pub fn normalize_with_scratch(&self, text: &str, out: &mut String, scratch: &mut String) {
+        out.clear();
+        out.push_str(text);
+        for step in &self.steps {
+            step.normalize_into(out, scratch);
+            mem::swap(out, scratch);
+        }
+    }
+}
+
+impl Normalizer for NormalizerPipeline {
+    fn normalize(&self, text: &str) -> String {
+        let mut out = String::new();
+        self.normalize_into(text, &mut out);
+        out
+    }
+    fn normalize_into(&self, text: &str, out: &mut String) {
+        self.normalize_with_scratch(text, out, &mut String::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::normalize;
+
+    #[test]
+    fn strip_urls_mentions_and_commands() {
+        let chat = "@mod !uptime check https://x.io/a?b=1 www.x.io !!! wow@";
+        let pipeline = NormalizerPipeline::new()
+            .then(Strip::urls().with_placeholder("<url>"))
+            .then(Strip::mentions())
+            .then(Strip::commands());
+        assert_eq!(pipeline.normalize(chat), "check <url> <url> !!! wow@");
+    }
+
+    #[test]
+    fn pipeline_runs_steps_in_order() {
+        let chat = "대박ㅋㅋㅋㅋㅋ lolololol";
+        assert_eq!(
+            NormalizerPipeline::default().normalize(chat),
+            normalize(chat)
+        );
+        assert_eq!(NormalizerPipeline::new().normalize(chat), chat);
+        let pipeline = NormalizerPipeline::new()
+            .then(|s: &str| s.to_uppercase())
+            .derepeat(1, 2);
+        assert_eq!(pipeline.normalize(chat), "대박ㅋ LOL");
+    }
+}
@@ -1,8 +1,125 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+use std::{iter, slice};
+
+/// What `push` does when the ring is full.
+#[derive(Default)]
+pub enum OverflowPolicy<T> {
+    /// Overwrite the oldest element and return it.
+    #[default]
+    Overwrite,
+    /// Leave the ring as it is and return the pushed element.
+    Reject,
+    /// Overwrite the oldest element and hand it to the callback, e.g. to
+    /// undo its counts elsewhere.
+    Callback(Arc<dyn Fn(T) + Send + Sync>),
+}
+
+impl<T> Clone for OverflowPolicy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Overwrite => Self::Overwrite,
+            Self::Reject => Self::Reject,
+            Self::Callback(f) => Self::Callback(f.clone()),
+        }
+    }
+}
+
+impl<T> fmt::Debug for OverflowPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overwrite => f.write_str("Overwrite"),
+            Self::Reject => f.write_str("Reject"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Where a ring's elements sit in its slots: `size` elements ending just
+/// before `offset`, wrapping around.
+#[derive(Clone, Copy, Debug, Default)]
+struct Cursor {
+    offset: usize,
+    size: usize,
+}
+
+impl Cursor {
+    fn head(self, capacity: usize) -> usize {
+        (self.offset + capacity - self.size) % capacity
+    }
+    fn push<T>(&mut self, buf: &mut [Option<T>], t: T, overflow: &OverflowPolicy<T>) -> Option<T> {
+        if self.size == buf.len()
+            && let OverflowPolicy::Reject = overflow
+        {
+            return Some(t);
+        }
+        let last = buf[self.offset].replace(t);
+        self.offset = (self.offset + 1) % buf.len();
+        self.size = (self.size + 1).min(buf.len());
+        match overflow {
+            OverflowPolicy::Callback(f) => {
+                last.into_iter().for_each(|t| f(t));
+                None
+            }
+            _ => last,
+        }
+    }
+    fn pop_oldest<T>(&mut self, buf: &mut [Option<T>]) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        let head = self.head(buf.len());
+        self.size -= 1;
+        buf[head].take()
+    }
+    fn pop_newest<T>(&mut self, buf: &mut [Option<T>]) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        self.offset = (self.offset + buf.len() - 1) % buf.len();
+        self.size -= 1;
+        buf[self.offset].take()
+    }
+    fn get<T>(self, buf: &[Option<T>], i: usize) -> Option<&T> {
+        if i >= self.size {
+            return None;
+        }
+        buf[(self.head(buf.len()) + i) % buf.len()].as_ref()
+    }
+    fn get_mut<T>(self, buf: &mut [Option<T>], i: usize) -> Option<&mut T> {
+        if i >= self.size {
+            return None;
+        }
+        buf[(self.head(buf.len()) + i) % buf.len()].as_mut()
+    }
+    fn iter<T>(self, buf: &[Option<T>]) -> RingIterator<'_, T> {
+        RingIterator {
+            buf,
+            cursor: self,
+            front: 0,
+            back: self.size,
+        }
+    }
+    fn iter_mut<T>(self, buf: &mut [Option<T>]) -> RingIterMut<'_, T> {
+        let head = self.head(buf.len());
+        let first = self.size.min(buf.len() - head);
+        let (wrapped, from_head) = buf.split_at_mut(head);
+        RingIterMut {
+            inner: from_head[..first]
+                .iter_mut()
+                .chain(wrapped[..self.size - first].iter_mut()),
+            len: self.size,
+        }
+    }
+}
+
+/// Fixed-capacity buffer keeping the newest `S` elements.
 #[derive(Clone, Debug)]
 pub struct Ring<T, const S: usize> {
     buf: [Option<T>; S],
-    offset: usize,
-    size: usize,
+    cursor: Cursor,
+    overflow: OverflowPolicy<T>,
 }
 
 impl<T, const S: usize> Default for Ring<T, S> {
@@ -15,43 +132,448 @@ impl<T, const S: usize> Ring<T, S> {
     pub fn new() -> Self {
         Self {
             buf: [const { None }; S],
-            offset: 0,
-            size: 0,
+            cursor: Cursor::default(),
+            overflow: OverflowPolicy::Overwrite,
         }
     }
+    /// What to do on `push` when full; overwrite the oldest by default.
+    pub fn with_overflow(mut self, overflow: OverflowPolicy<T>) -> Self {
+        self.overflow = overflow;
+        self
+    }
+    pub fn overflow(&self) -> &OverflowPolicy<T> {
+        &self.overflow
+    }
+    /// Add `t` as the newest element. When full, returns the element that
+    /// did not fit under the [`OverflowPolicy`]: the overwritten oldest, or
+    /// `t` itself if rejected.
     pub fn push(&mut self, t: T) -> Option<T> {
-        let last = self.buf[self.offset].take();
-        self.buf[self.offset] = Some(t);
-        self.offset = (self.offset + 1) % S;
-        self.size = (self.size + 1).min(S);
-        last
+        self.cursor.push(&mut self.buf, t, &self.overflow)
     }
-    pub fn iter(&self) -> RingIterator<'_, T, S> {
-        RingIterator {
-            ring: self,
-            index: 0,
+    /// Remove and return the oldest element.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        self.cursor.pop_oldest(&mut self.buf)
+    }
+    /// Remove and return the newest element.
+    pub fn pop_newest(&mut self) -> Option<T> {
+        self.cursor.pop_newest(&mut self.buf)
+    }
+    /// Remove all elements, yielding them oldest first. Elements not
+    /// consumed are dropped with the iterator.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            buf: &mut self.buf,
+            cursor: &mut self.cursor,
+        }
+    }
+    /// Number of elements held.
+    pub fn len(&self) -> usize {
+        self.cursor.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cursor.size == 0
+    }
+    /// Number of elements held before `push` overwrites the oldest.
+    pub fn capacity(&self) -> usize {
+        S
+    }
+    /// The `i`-th element, counting from the oldest.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.cursor.get(&self.buf, i)
+    }
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.cursor.get_mut(&mut self.buf, i)
+    }
+    /// The oldest element.
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+    /// The newest element.
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+    pub fn iter(&self) -> RingIterator<'_, T> {
+        self.cursor.iter(&self.buf)
+    }
+    /// Iterate mutably from oldest to newest, e.g. to update cached fields
+    /// in place.
+    pub fn iter_mut(&mut self) -> RingIterMut<'_, T> {
+        self.cursor.iter_mut(&mut self.buf)
+    }
+}
+
+/// [`Ring`] with its capacity chosen at runtime, e.g. from configuration.
+#[derive(Clone, Debug)]
+pub struct DynRing<T> {
+    buf: Box<[Option<T>]>,
+    cursor: Cursor,
+    overflow: OverflowPolicy<T>,
+}
+
+impl<T> DynRing<T> {
+    /// A ring holding up to `capacity` elements, at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: iter::repeat_with(|| None).take(capacity.max(1)).collect(),
+            cursor: Cursor::default(),
+            overflow: OverflowPolicy::Overwrite,
+        }
+    }
+    /// What to do on `push` when full; overwrite the oldest by default.
+    pub fn with_overflow(mut self, overflow: OverflowPolicy<T>) -> Self {
+        self.overflow = overflow;
+        self
+    }
+    pub fn overflow(&self) -> &OverflowPolicy<T> {
+        &self.overflow
+    }
+    /// Add `t` as the newest element. When full, returns the element that
+    /// did not fit under the [`OverflowPolicy`]: the overwritten oldest, or
+    /// `t` itself if rejected.
+    pub fn push(&mut self, t: T) -> Option<T> {
+        self.cursor.push(&mut self.buf, t, &self.overflow)
+    }
+    /// Remove and return the oldest element.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        self.cursor.pop_oldest(&mut self.buf)
+    }
+    /// Remove and return the newest element.
+    pub fn pop_newest(&mut self) -> Option<T> {
+        self.cursor.pop_newest(&mut self.buf)
+    }
+    /// Remove all elements, yielding them oldest first. Elements not
+    /// consumed are dropped with the iterator.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            buf: &mut self.buf,
+            cursor: &mut self.cursor,
         }
     }
+    /// Number of elements held.
+    pub fn len(&self) -> usize {
+        self.cursor.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cursor.size == 0
+    }
+    /// Number of elements held before `push` overwrites the oldest.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+    /// The `i`-th element, counting from the oldest.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.cursor.get(&self.buf, i)
+    }
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.cursor.get_mut(&mut self.buf, i)
+    }
+    /// The oldest element.
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+    /// The newest element.
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+    pub fn iter(&self) -> RingIterator<'_, T> {
+        self.cursor.iter(&self.buf)
+    }
+    /// Iterate mutably from oldest to newest.
+    pub fn iter_mut(&mut self) -> RingIterMut<'_, T> {
+        self.cursor.iter_mut(&mut self.buf)
+    }
+}
+
+/// Storage of a `ChatWindow`: a [`Ring`] sized at compile time or a
+/// [`DynRing`] sized at runtime.
+pub trait RingBuffer<T> {
+    fn push(&mut self, t: T) -> Option<T>;
+    fn pop_oldest(&mut self) -> Option<T>;
+    fn pop_newest(&mut self) -> Option<T>;
+    fn drain(&mut self) -> Drain<'_, T>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn capacity(&self) -> usize;
+    fn overflow(&self) -> &OverflowPolicy<T>;
+    fn get(&self, i: usize) -> Option<&T>;
+    fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+    fn back(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+    fn iter(&self) -> RingIterator<'_, T>;
+    fn iter_mut(&mut self) -> RingIterMut<'_, T>;
+}
+
+impl<T, const S: usize> RingBuffer<T> for Ring<T, S> {
+    fn push(&mut self, t: T) -> Option<T> {
+        Ring::push(self, t)
+    }
+    fn pop_oldest(&mut self) -> Option<T> {
+        Ring::pop_oldest(self)
+    }
+    fn pop_newest(&mut self) -> Option<T> {
+        Ring::pop_newest(self)
+    }
+    fn drain(&mut self) -> Drain<'_, T> {
+        Ring::drain(self)
+    }
+    fn len(&self) -> usize {
+        Ring::len(self)
+    }
+    fn capacity(&self) -> usize {
+        S
+    }
+    fn overflow(&self) -> &OverflowPolicy<T> {
+        Ring::overflow(self)
+    }
+    fn get(&self, i: usize) -> Option<&T> {
+        Ring::get(self, i)
+    }
+    fn iter(&self) -> RingIterator<'_, T> {
+        Ring::iter(self)
+    }
+    fn iter_mut(&mut self) -> RingIterMut<'_, T> {
+        Ring::iter_mut(self)
+    }
+}
+
+impl<T> RingBuffer<T> for DynRing<T> {
+    fn push(&mut self, t: T) -> Option<T> {
+        DynRing::push(self, t)
+    }
+    fn pop_oldest(&mut self) -> Option<T> {
+        DynRing::pop_oldest(self)
+    }
+    fn pop_newest(&mut self) -> Option<T> {
+        DynRing::pop_newest(self)
+    }
+    fn drain(&mut self) -> Drain<'_, T> {
+        DynRing::drain(self)
+    }
+    fn len(&self) -> usize {
+        DynRing::len(self)
+    }
+    fn capacity(&self) -> usize {
+        DynRing::capacity(self)
+    }
+    fn overflow(&self) -> &OverflowPolicy<T> {
+        DynRing::overflow(self)
+    }
+    fn get(&self, i: usize) -> Option<&T> {
+        DynRing::get(self, i)
+    }
+    fn iter(&self) -> RingIterator<'_, T> {
+        DynRing::iter(self)
+    }
+    fn iter_mut(&mut self) -> RingIterMut<'_, T> {
+        DynRing::iter_mut(self)
+    }
+}
+
+impl<T, const S: usize> Index<usize> for Ring<T, S> {
+    type Output = T;
+    /// The `i`-th element, counting from the oldest.
+    fn index(&self, i: usize) -> &T {
+        let len = self.len();
+        self.get(i)
+            .unwrap_or_else(|| panic!("index {i} out of range for ring of length {len}"))
+    }
+}
+
+impl<T, const S: usize> IndexMut<usize> for Ring<T, S> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        let len = self.len();
+        self.get_mut(i)
+            .unwrap_or_else(|| panic!("index {i} out of range for ring of length {len}"))
+    }
+}
+
+impl<T> Index<usize> for DynRing<T> {
+    type Output = T;
+    /// The `i`-th element, counting from the oldest.
+    fn index(&self, i: usize) -> &T {
+        let len = self.len();
+        self.get(i)
+            .unwrap_or_else(|| panic!("index {i} out of range for ring of length {len}"))
+    }
+}
+
+impl<T> IndexMut<usize> for DynRing<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        let len = self.len();
+        self.get_mut(i)
+            .unwrap_or_else(|| panic!("index {i} out of range for ring of length {len}"))
+    }
 }
 
-pub struct RingIterator<'a, T, const S: usize> {
-    ring: &'a Ring<T, S>,
-    index: usize,
+impl<T, const S: usize> Extend<T> for Ring<T, S> {
+    /// Push each element in turn; only the newest `S` remain.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|t| {
+            self.push(t);
+        });
+    }
 }
 
-impl<'a, T, const S: usize> Iterator for RingIterator<'a, T, S> {
+impl<T, const S: usize> FromIterator<T> for Ring<T, S> {
+    /// Ring of the last `S` elements, e.g. to warm start from a log.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut ring = Self::new();
+        ring.extend(iter);
+        ring
+    }
+}
+
+impl<T> Extend<T> for DynRing<T> {
+    /// Push each element in turn; only the newest `capacity` remain.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|t| {
+            self.push(t);
+        });
+    }
+}
+
+impl<T> FromIterator<T> for DynRing<T> {
+    /// Full ring holding all the elements.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items: Vec<T> = iter.into_iter().collect();
+        let mut ring = Self::new(items.len());
+        ring.extend(items);
+        ring
+    }
+}
+
+impl<'a, T, const S: usize> IntoIterator for &'a Ring<T, S> {
+    type Item = &'a T;
+    type IntoIter = RingIterator<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const S: usize> IntoIterator for &'a mut Ring<T, S> {
+    type Item = &'a mut T;
+    type IntoIter = RingIterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a DynRing<T> {
+    type Item = &'a T;
+    type IntoIter = RingIterator<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut DynRing<T> {
+    type Item = &'a mut T;
+    type IntoIter = RingIterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Iterator over a [`Ring`] from oldest to newest; reverse it to scan the
+/// newest first.
+pub struct RingIterator<'a, T> {
+    buf: &'a [Option<T>],
+    cursor: Cursor,
+    /// Logical positions left to yield, `front..back`.
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iterator for RingIterator<'a, T> {
     type Item = &'a T;
     fn next(&mut self) -> Option<&'a T> {
-        let res = if self.index >= self.ring.size {
-            None
-        } else {
-            self.ring.buf[(self.ring.offset + self.index) % self.ring.size].as_ref()
-        };
-        self.index += 1;
-        res
+        if self.front >= self.back {
+            return None;
+        }
+        self.front += 1;
+        self.cursor.get(self.buf, self.front - 1)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for RingIterator<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.cursor.get(self.buf, self.back)
+    }
+}
+
+impl<T> ExactSizeIterator for RingIterator<'_, T> {}
+
+/// Draining iterator of a [`Ring`], see [`Ring::drain`].
+pub struct Drain<'a, T> {
+    buf: &'a mut [Option<T>],
+    cursor: &'a mut Cursor,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.cursor.pop_oldest(self.buf)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.cursor.size, Some(self.cursor.size))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.cursor.pop_newest(self.buf)
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+/// Mutable iterator over a [`Ring`] from oldest to newest.
+pub struct RingIterMut<'a, T> {
+    inner: iter::Chain<slice::IterMut<'a, Option<T>>, slice::IterMut<'a, Option<T>>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for RingIterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        let next = self.inner.next()?.as_mut();
+        self.len -= 1;
+        next
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
 }
 
+impl<T> DoubleEndedIterator for RingIterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let next = self.inner.next_back()?.as_mut();
+        self.len -= 1;
+        next
+    }
+}
+
+impl<T> ExactSizeIterator for RingIterMut<'_, T> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,4 +599,141 @@ mod tests {
         ring.push("2".to_string());
         assert_eq!(ring.iter().collect::<Vec<_>>(), vec!["1", "2"]);
     }
+    #[test]
+    fn ring_accessors_wrap_around() {
+        let mut ring = Ring::<u32, 3>::new();
+        assert!(ring.is_empty());
+        assert_eq!((ring.front(), ring.back(), ring.get(0)), (None, None, None));
+        for i in 1..=5 {
+            ring.push(i);
+        }
+        assert_eq!((ring.len(), ring.capacity()), (3, 3));
+        assert_eq!((ring.front(), ring.back()), (Some(&3), Some(&5)));
+        assert_eq!((ring.get(1), ring.get(3)), (Some(&4), None));
+        ring.pop_oldest();
+        assert_eq!(
+            (ring.len(), ring.front(), ring.get(1)),
+            (2, Some(&4), Some(&5))
+        );
+    }
+    #[test]
+    fn ring_iter_both_ends() {
+        let mut ring = Ring::<u32, 4>::new();
+        for i in 1..=6 {
+            ring.push(i);
+        }
+        assert_eq!(ring.iter().rev().collect::<Vec<_>>(), [&6, &5, &4, &3]);
+        let mut iter = (&ring).into_iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!((iter.next(), iter.next_back()), (Some(&3), Some(&6)));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.collect::<Vec<_>>(), [&4, &5]);
+    }
+    #[test]
+    fn ring_iter_mut_in_place() {
+        let mut ring = Ring::<u32, 3>::new();
+        for i in 1..=4 {
+            ring.push(i);
+        }
+        ring.iter_mut().for_each(|x| *x *= 10);
+        if let Some(newest) = ring.iter_mut().next_back() {
+            *newest += 1;
+        }
+        assert_eq!(ring.iter_mut().len(), 3);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&20, &30, &41]);
+        ring.pop_oldest();
+        for x in &mut ring {
+            *x += 1;
+        }
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&31, &42]);
+    }
+    #[test]
+    fn dyn_ring_matches_ring() {
+        let mut ring = Ring::<u32, 3>::new();
+        let mut dyn_ring = DynRing::new(3);
+        for i in 1..=5 {
+            assert_eq!(ring.push(i), dyn_ring.push(i));
+        }
+        assert_eq!(dyn_ring.capacity(), 3);
+        assert!(ring.iter().eq(dyn_ring.iter()));
+        assert_eq!(dyn_ring.pop_oldest(), Some(3));
+        assert_eq!(dyn_ring.iter().rev().collect::<Vec<_>>(), [&5, &4]);
+        assert_eq!(DynRing::<u32>::new(0).capacity(), 1);
+    }
+    #[test]
+    fn ring_collect_and_extend() {
+        let mut ring: Ring<u32, 3> = (1..=5).collect();
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&3, &4, &5]);
+        ring.extend([6, 7]);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&5, &6, &7]);
+        let mut dyn_ring: DynRing<u32> = (1..=2).collect();
+        assert_eq!(dyn_ring.capacity(), 2);
+        dyn_ring.extend([3]);
+        assert_eq!(dyn_ring.iter().collect::<Vec<_>>(), [&2, &3]);
+    }
+    #[test]
+    fn ring_pop_newest_and_drain() {
+        let mut ring: Ring<u32, 3> = (1..=4).collect();
+        assert_eq!(ring.pop_newest(), Some(4));
+        ring.push(5);
+        assert_eq!(ring.back(), Some(&5));
+        let mut drain = ring.drain();
+        assert_eq!((drain.len(), drain.next_back()), (3, Some(5)));
+        assert_eq!(drain.next(), Some(2));
+        drop(drain);
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop_newest(), None);
+        ring.extend([6, 7]);
+        assert_eq!(ring.drain().collect::<Vec<_>>(), [6, 7]);
+    }
+    #[test]
+    fn ring_index_in_logical_order() {
+        let mut ring: Ring<u32, 3> = (1..=5).collect();
+        assert_eq!((ring[0], ring[2]), (3, 5));
+        ring[1] += 10;
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&3, &14, &5]);
+        let mut dyn_ring: DynRing<u32> = (1..=2).collect();
+        dyn_ring[0] = 9;
+        assert_eq!((dyn_ring[0], dyn_ring[1]), (9, 2));
+    }
+    #[test]
+    #[should_panic(expected = "index 2 out of range")]
+    fn ring_index_past_len_panics() {
+        let ring: Ring<u32, 3> = (1..=2).collect();
+        let _ = ring[2];
+    }
+    #[test]
+    fn ring_overflow_policies() {
+        let mut rejecting = Ring::<u32, 2>::new().with_overflow(OverflowPolicy::Reject);
+        rejecting.extend([1, 2]);
+        assert_eq!(rejecting.push(3), Some(3));
+        assert_eq!(rejecting.iter().collect::<Vec<_>>(), [&1, &2]);
+        let evicted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = evicted.clone();
+        let mut ring =
+            DynRing::new(2).with_overflow(OverflowPolicy::Callback(Arc::new(move |t| {
+                sink.lock().unwrap().push(t)
+            })));
+        for i in 1..=4 {
+            assert_eq!(ring.push(i), None);
+        }
+        assert_eq!(*evicted.lock().unwrap(), [1, 2]);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), [&3, &4]);
+    }
+    #[test]
+    fn ring_pop_oldest() {
+        let mut ring = Ring::<String, 3>::new();
+        ring.push("1".to_string());
+        ring.push("2".to_string());
+        ring.push("3".to_string());
+        ring.push("4".to_string());
+        assert_eq!(ring.pop_oldest().as_deref(), Some("2"));
+        assert_eq!(ring.iter().collect::<Vec<_>>(), vec!["3", "4"]);
+        ring.push("5".to_string());
+        assert_eq!(ring.iter().collect::<Vec<_>>(), vec!["3", "4", "5"]);
+        assert_eq!(ring.pop_oldest().as_deref(), Some("3"));
+        assert_eq!(ring.pop_oldest().as_deref(), Some("4"));
+        assert_eq!(ring.pop_oldest().as_deref(), Some("5"));
+        assert_eq!(ring.pop_oldest(), None);
+    }
 }
@@ -25,11 +25,26 @@
 //! assert!(matches!(det.current_phase(), chat_spike::Phase::InSpike));
 //! ```
 
-use crate::math::neg_ln_poisson_tail;
-use crate::ring::Ring;
-use crate::text::{normalize, unique_char_ngrams};
-use std::collections::HashMap;
-use std::time::Instant;
+use crate::dict::{Dictionary, MemoryDictionary, TextKey, TokenKey, token_fingerprint};
+use crate::filter::MessageFilter;
+use crate::intern::Interner;
+use crate::math::{
+    Float, P2Quantile, Surprise, TailAccuracy, js_divergence, kl_terms, ln_rate_ratio,
+    neg_ln_neg_binomial_tail, neg_ln_poisson_tail_with,
+};
+use crate::normalizer::{Normalizer, NormalizerPipeline};
+use crate::ring::{OverflowPolicy, Ring, RingBuffer};
+use crate::text::{Interjections, TokenKind, simhash_hashed};
+#[cfg(feature = "unicode-normalization")]
+use crate::text::{UnicodeForm, normalize_unicode};
+use crate::tokenizer::{CharNgrams, Stopwords, Token, Tokenizer, TypedCharNgrams, WordNgrams};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Range, RangeInclusive};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Detects bursts of activity in a stream of timestamps.
 ///
@@ -39,18 +54,186 @@ use std::time::Instant;
 /// When the surprise score  
 /// `−ln P(X ≥ S | λ = dur_s · L / dur_l)`  
 /// rises above `start_t`, a *spike* begins; it ends once the score
-/// drops below `end_t`.
+/// drops below `end_t`. [`with_model`](Self::with_model) scores the count
+/// against another [`Model`] than Poisson.
+///
+/// `F` is the float type of the rate state and thresholds; surprises are
+/// reported in `f64` either way.
 ///
 /// See the module-level examples for a minimal live demo.
-pub struct SpikeDetector<const S: usize, const L: usize> {
-    dur_s: f64,
-    dur_l: f64,
-    start_t: f64,
-    end_t: f64,
+pub struct SpikeDetector<const S: usize, const L: usize, F = f64> {
+    dur_s: F,
+    dur_l: F,
+    start_t: F,
+    end_t: F,
     last_ts: Option<Instant>,
     phase: Phase,
+    clock_policy: ClockPolicy,
+    model: Model,
+    tail_accuracy: TailAccuracy,
+    /// Gaps between events, for the negative binomial dispersion.
+    gaps: Moments<F>,
+    /// Short-horizon rate after each event, for z-scores.
+    rates: Moments<F>,
+    surprise_history: Option<WindowedQuantile>,
+}
+
+/// How the short-horizon activity is scored against the long horizon.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Model {
+    #[default]
+    Poisson,
+    /// Negative binomial, for chat that is burstier than Poisson even at
+    /// baseline. `dispersion` is the count's `variance / mean`; `None`
+    /// estimates it from the spread of gaps over the long horizon.
+    NegBinomial { dispersion: Option<f64> },
+    /// Log-likelihood ratio of the short and long horizons having their own
+    /// Poisson rates rather than a shared one, zero while the short rate is
+    /// not higher. Unlike the tail models it accounts for the long rate
+    /// being an estimate too, and does not saturate when `S` is large.
+    LikelihoodRatio,
+    /// How many standard deviations the short-horizon rate is above its
+    /// mean over the long horizon. Cheap, but with no tail model behind it.
+    ZScore,
+    /// Posterior predictive tail under a Gamma prior on the baseline rate
+    /// in events per second, updated with the long horizon's decayed count
+    /// and duration. A young stream is scored against the events it has
+    /// actually seen, widened by the uncertainty of so few; the model
+    /// agrees with `Poisson` once the long horizon fills. `shape / rate` is
+    /// the prior mean rate, and `shape` weighs it in events.
+    GammaPoisson { shape: f64, rate: f64 },
+}
+
+/// Exponentially decayed mean and variance of a series.
+#[derive(Clone, Copy, Default, Debug)]
+struct Moments<F> {
+    weight: F,
+    sum: F,
+    sum_sq: F,
+}
+
+impl<F: Float> Moments<F> {
+    fn push(&mut self, x: F, weight: F, decay: F) {
+        let d = decay.powf(weight);
+        let w = F::one() - d;
+        self.weight = self.weight * d + w;
+        self.sum = self.sum * d + x * w;
+        self.sum_sq = self.sum_sq * d + x * x * w;
+    }
+    fn mean(&self) -> F {
+        if self.weight > F::zero() {
+            self.sum / self.weight
+        } else {
+            F::zero()
+        }
+    }
+    fn variance(&self) -> F {
+        if self.weight > F::zero() {
+            (self.sum_sq / self.weight - self.mean().powi(2)).max(F::zero())
+        } else {
+            F::zero()
+        }
+    }
+}
+
+/// Quantile of the values pushed over roughly the last `window`.
+///
+/// Two [`P2Quantile`]s start half a window apart and each restarts after
+/// one window; answers come from the older, which has seen between half
+/// and all of the window.
+#[derive(Clone, Debug)]
+struct WindowedQuantile {
+    p: f64,
+    window: Duration,
+    estimators: [(Option<Instant>, P2Quantile); 2],
+}
+
+impl WindowedQuantile {
+    fn new(p: f64, window: Duration) -> Self {
+        Self {
+            p,
+            window,
+            estimators: [(None, P2Quantile::new(p)), (None, P2Quantile::new(p))],
+        }
+    }
+    fn push(&mut self, x: f64, ts: Instant) {
+        let [first, second] = &mut self.estimators;
+        let first_start = *first.0.get_or_insert(ts);
+        if second.0.is_none() && ts.saturating_duration_since(first_start) >= self.window / 2 {
+            second.0 = Some(ts);
+        }
+        for (start, estimator) in &mut self.estimators {
+            let Some(started) = *start else {
+                continue;
+            };
+            if ts.saturating_duration_since(started) >= self.window {
+                *start = Some(ts);
+                *estimator = P2Quantile::new(self.p);
+            }
+            estimator.push(x);
+        }
+    }
+    fn estimate(&self) -> Option<f64> {
+        self.estimators
+            .iter()
+            .filter_map(|(start, estimator)| Some((start.as_ref()?, estimator)))
+            .min_by_key(|(start, _)| **start)
+            .and_then(|(_, estimator)| estimator.estimate())
+    }
+}
+
+/// What `SpikeDetector` does with a timestamp earlier than the last one.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ClockPolicy {
+    /// Count the event with a zero time gap and keep the later clock.
+    #[default]
+    Clamp,
+    /// Drop the event without touching any statistics.
+    Skip,
+    /// Drop the event and report a [`NonMonotonicTimestamp`] error.
+    Reject,
+}
+
+/// Result of a successful [`SpikeDetector::try_push`].
+#[derive(Clone, Copy, Debug)]
+pub enum PushOutcome {
+    /// The timestamp was in order.
+    Accepted(SpikeEvent),
+    /// The timestamp went backwards and was counted with a zero gap.
+    Clamped(SpikeEvent),
+    /// The timestamp went backwards and was ignored.
+    Skipped,
+}
+
+impl PushOutcome {
+    /// The spike event raised by the push, `SpikeEvent::None` when skipped.
+    pub fn event(&self) -> SpikeEvent {
+        match *self {
+            PushOutcome::Accepted(e) | PushOutcome::Clamped(e) => e,
+            PushOutcome::Skipped => SpikeEvent::None,
+        }
+    }
+}
+
+/// A timestamp earlier than the last accepted one, under `ClockPolicy::Reject`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonMonotonicTimestamp {
+    pub last_ts: Instant,
+    pub ts: Instant,
+}
+
+impl std::fmt::Display for NonMonotonicTimestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "timestamp is {:?} earlier than the last one",
+            self.last_ts.duration_since(self.ts)
+        )
+    }
 }
 
+impl std::error::Error for NonMonotonicTimestamp {}
+
 #[derive(Clone, Copy, Default, Debug)]
 pub enum Phase {
     #[default]
@@ -70,299 +253,3381 @@ pub enum SpikeEvent {
     },
 }
 
-impl<const S: usize, const L: usize> Default for SpikeDetector<S, L> {
+impl SpikeEvent {
+    /// The surprise at a spike's begin or end, e.g. to report it as a
+    /// [`p_value`](Surprise::p_value).
+    pub fn surprise(&self) -> Option<Surprise> {
+        match *self {
+            SpikeEvent::None => None,
+            SpikeEvent::Begin { surprise } | SpikeEvent::End { surprise } => {
+                Some(Surprise(surprise))
+            }
+        }
+    }
+}
+
+impl<const S: usize, const L: usize, F: Float> Default for SpikeDetector<S, L, F> {
     fn default() -> Self {
         Self {
-            dur_s: 0.,
-            dur_l: 0.,
-            start_t: 2.5,
-            end_t: 1.25,
+            dur_s: F::zero(),
+            dur_l: F::zero(),
+            start_t: F::from_f64_lossy(2.5),
+            end_t: F::from_f64_lossy(1.25),
             last_ts: None,
             phase: Phase::Idle,
+            clock_policy: ClockPolicy::default(),
+            model: Model::default(),
+            tail_accuracy: TailAccuracy::default(),
+            gaps: Moments::default(),
+            rates: Moments::default(),
+            surprise_history: None,
         }
     }
 }
 
-impl<const S: usize, const L: usize> SpikeDetector<S, L> {
-    pub fn with_threshold(mut self, start_t: f64, end_t: f64) -> Self {
+impl<const S: usize, const L: usize, F: Float> SpikeDetector<S, L, F> {
+    pub fn with_threshold(mut self, start_t: F, end_t: F) -> Self {
         self.start_t = start_t;
         self.end_t = end_t;
         self
     }
-    pub fn current_surprise(&self) -> f64 {
-        let λ_null = self.dur_s * (L as f64) / self.dur_l;
-        neg_ln_poisson_tail(S as f64, λ_null)
+    pub fn with_clock_policy(mut self, policy: ClockPolicy) -> Self {
+        self.clock_policy = policy;
+        self
+    }
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+    /// How precisely the Poisson tail of [`Model::Poisson`] is evaluated.
+    pub fn with_tail_accuracy(mut self, accuracy: TailAccuracy) -> Self {
+        self.tail_accuracy = accuracy;
+        self
+    }
+    /// Track the `p` quantile of the surprise after each event over about
+    /// the last `window`, see [`surprise_quantile`](Self::surprise_quantile).
+    pub fn with_surprise_quantile(mut self, p: f64, window: Duration) -> Self {
+        self.surprise_history = Some(WindowedQuantile::new(p, window));
+        self
+    }
+    /// The tracked surprise quantile, e.g. to set thresholds from what this
+    /// stream's surprise usually is. Estimated in constant memory over
+    /// between half and all of the window; `None` if not tracked or before
+    /// any event.
+    pub fn surprise_quantile(&self) -> Option<f64> {
+        self.surprise_history.as_ref()?.estimate()
+    }
+    /// The surprise score under the configured [`Model`]; zero, meaning no
+    /// evidence of a spike, before the first event or while the rates are
+    /// degenerate.
+    pub fn current_surprise(&self) -> F {
+        self.try_current_surprise().unwrap_or(F::zero())
+    }
+    /// The surprise score, or `None` before the first event or when the
+    /// rates give no meaningful score, e.g. a zero long-horizon duration.
+    pub fn try_current_surprise(&self) -> Option<F> {
+        let rates_ok = self.dur_l > F::zero() && self.dur_l.is_finite() && self.dur_s.is_finite();
+        let surprise = self.surprise();
+        (rates_ok && !surprise.is_nan()).then_some(surprise)
+    }
+    fn surprise(&self) -> F {
+        let l = F::from_f64_lossy(L as f64);
+        let λ_null = self.dur_s * l / self.dur_l;
+        let k = F::from_f64_lossy(S as f64);
+        match self.model {
+            Model::Poisson => neg_ln_poisson_tail_with(k, λ_null, self.tail_accuracy),
+            Model::NegBinomial { dispersion } => {
+                let dispersion = dispersion.map_or_else(|| self.dispersion(), F::from_f64_lossy);
+                neg_ln_neg_binomial_tail(k, λ_null, dispersion)
+            }
+            // `S` events took less time than the long rate expects.
+            Model::LikelihoodRatio if λ_null < k => ln_rate_ratio(k, self.dur_s, l, self.dur_l),
+            Model::LikelihoodRatio => F::zero(),
+            Model::GammaPoisson { shape, rate } => {
+                let shape = F::from_f64_lossy(shape) + self.gaps.weight * l;
+                let rate = F::from_f64_lossy(rate) + self.dur_l;
+                // Gamma mixture of Poissons: negative binomial.
+                let mean = shape * self.dur_s / rate;
+                neg_ln_neg_binomial_tail(k, mean, F::one() + self.dur_s / rate)
+            }
+            Model::ZScore => {
+                let sd = self.rates.variance().sqrt();
+                let rate = k / self.dur_s;
+                if sd > F::zero() {
+                    (rate - self.rates.mean()) / sd
+                } else {
+                    F::zero()
+                }
+            }
+        }
+    }
+    /// Index of dispersion of the baseline counts, estimated as the squared
+    /// coefficient of variation of the gaps between events; one for a
+    /// Poisson stream, and never below.
+    pub fn dispersion(&self) -> F {
+        let mean = self.gaps.mean();
+        if mean > F::zero() {
+            (self.gaps.variance() / (mean * mean)).max(F::one())
+        } else {
+            F::one()
+        }
     }
     /// Feed the next timestamp and return a spike event, if any.
+    ///
+    /// Out-of-order timestamps follow the configured [`ClockPolicy`]; a
+    /// rejected timestamp yields `SpikeEvent::None`. Use
+    /// [`try_push`](Self::try_push) to observe what happened.
     pub fn push(&mut self, ts: Instant) -> SpikeEvent {
-        let decay_s = 1. - 1. / (S as f64);
-        let decay_l = 1. - 1. / (L as f64);
+        self.push_weighted(ts, 1.)
+    }
+    /// Feed a timestamp that counts as `weight` events.
+    ///
+    /// The weighted event is treated as `weight` events spread evenly over
+    /// the gap since the previous one. A weight of zero or less, or one that
+    /// is not finite, is ignored and its gap carries over to the next event.
+    pub fn push_weighted(&mut self, ts: Instant, weight: f64) -> SpikeEvent {
+        self.try_push_weighted(ts, weight)
+            .map_or(SpikeEvent::None, |o| o.event())
+    }
+    /// Feed the next timestamp, reporting how the clock policy applied.
+    pub fn try_push(&mut self, ts: Instant) -> Result<PushOutcome, NonMonotonicTimestamp> {
+        self.try_push_weighted(ts, 1.)
+    }
+    pub fn try_push_weighted(
+        &mut self,
+        ts: Instant,
+        weight: f64,
+    ) -> Result<PushOutcome, NonMonotonicTimestamp> {
+        match self.last_ts {
+            Some(last_ts) if ts < last_ts => match self.clock_policy {
+                ClockPolicy::Clamp => Ok(PushOutcome::Clamped(self.advance(last_ts, weight))),
+                ClockPolicy::Skip => Ok(PushOutcome::Skipped),
+                ClockPolicy::Reject => Err(NonMonotonicTimestamp { last_ts, ts }),
+            },
+            _ => Ok(PushOutcome::Accepted(self.advance(ts, weight))),
+        }
+    }
+    fn advance(&mut self, ts: Instant, weight: f64) -> SpikeEvent {
+        if !(weight > 0. && weight.is_finite()) {
+            return SpikeEvent::None;
+        }
         let time_gap = self
             .last_ts
-            .map_or(0.0, |prev| ts.duration_since(prev).as_secs_f64())
-            .max(f64::EPSILON);
-        self.dur_s = self.dur_s * decay_s + time_gap;
-        self.dur_l = self.dur_l * decay_l + time_gap;
+            .map_or(F::zero(), |prev| {
+                F::from_f64_lossy(ts.duration_since(prev).as_secs_f64())
+            })
+            .max(F::epsilon());
+        let weight = F::from_f64_lossy(weight);
+        let decay_l = F::one() - F::one() / F::from_f64_lossy(L as f64);
+        (self.dur_s, self.dur_l) = self.durations_after(time_gap, weight);
+        if self.last_ts.is_some() {
+            self.gaps.push(time_gap / weight, weight, decay_l);
+            let rate = F::from_f64_lossy(S as f64) / self.dur_s;
+            self.rates.push(rate, weight, decay_l);
+        }
         self.last_ts = Some(ts);
-        let surprise = self.current_surprise();
+        let Some(surprise) = self.try_current_surprise() else {
+            return SpikeEvent::None;
+        };
+        if let Some(history) = self.surprise_history.as_mut() {
+            history.push(surprise.as_f64(), ts);
+        }
         match self.phase {
             Phase::Idle if surprise > self.start_t => {
                 self.phase = Phase::InSpike;
-                SpikeEvent::Begin { surprise }
+                SpikeEvent::Begin {
+                    surprise: surprise.as_f64(),
+                }
             }
             Phase::InSpike if surprise < self.end_t => {
                 self.phase = Phase::Idle;
-                SpikeEvent::End { surprise }
+                SpikeEvent::End {
+                    surprise: surprise.as_f64(),
+                }
+            }
+            _ => SpikeEvent::None,
+        }
+    }
+    /// Short and long durations once an event of `weight` follows a gap of
+    /// `time_gap` seconds.
+    fn durations_after(&self, time_gap: F, weight: F) -> (F, F) {
+        let one = F::one();
+        let ewma = |dur: F, window: usize| {
+            let decay = one - one / F::from_f64_lossy(window as f64);
+            let d = decay.powf(weight);
+            dur * d + time_gap * (one - d) / (one - decay) / weight
+        };
+        (ewma(self.dur_s, S), ewma(self.dur_l, L))
+    }
+    /// End a spike that went quiet: if an event arriving at `now` would
+    /// already score below the end threshold, leave the spike without
+    /// recording that event.
+    ///
+    /// A stream that stops chatting never pushes the event that would end
+    /// its spike, so call this periodically with the current time.
+    pub fn tick(&mut self, now: Instant) -> SpikeEvent {
+        let Some(last_ts) = self.last_ts else {
+            return SpikeEvent::None;
+        };
+        if !matches!(self.phase, Phase::InSpike) || now <= last_ts {
+            return SpikeEvent::None;
+        }
+        let saved = (self.dur_s, self.dur_l);
+        let time_gap = F::from_f64_lossy(now.duration_since(last_ts).as_secs_f64());
+        (self.dur_s, self.dur_l) = self.durations_after(time_gap, F::one());
+        let surprise = self.try_current_surprise();
+        (self.dur_s, self.dur_l) = saved;
+        match surprise {
+            Some(surprise) if surprise < self.end_t => {
+                self.phase = Phase::Idle;
+                SpikeEvent::End {
+                    surprise: surprise.as_f64(),
+                }
+            }
+            _ => SpikeEvent::None,
+        }
+    }
+}
+
+/// CUSUM changepoint detector over the gaps between events.
+///
+/// Each gap adds its log-likelihood ratio of coming from a rate `shift`
+/// times the baseline rather than from the baseline, which is the mean gap
+/// over the last `L` events; the sum is floored at zero. A spike begins
+/// once the sum rises above `start_t` and ends once it falls below
+/// `end_t`. Evidence accumulates over every event of a sustained shift, so
+/// it catches moderate level shifts that [`SpikeDetector`]'s fixed `S`
+/// window barely notices.
+#[derive(Clone, Debug)]
+pub struct CusumDetector<const L: usize> {
+    shift: f64,
+    start_t: f64,
+    end_t: f64,
+    cusum: f64,
+    gaps: Moments<f64>,
+    last_ts: Option<Instant>,
+    phase: Phase,
+}
+
+impl<const L: usize> Default for CusumDetector<L> {
+    fn default() -> Self {
+        Self {
+            shift: 2.,
+            start_t: 5.,
+            end_t: 1.,
+            cusum: 0.,
+            gaps: Moments::default(),
+            last_ts: None,
+            phase: Phase::Idle,
+        }
+    }
+}
+
+impl<const L: usize> CusumDetector<L> {
+    pub fn with_threshold(mut self, start_t: f64, end_t: f64) -> Self {
+        self.start_t = start_t;
+        self.end_t = end_t;
+        self
+    }
+    /// Ratio of the spike rate to the baseline rate to test for; smaller
+    /// shifts are caught later, larger ones sooner.
+    ///
+    /// # Panics
+    ///
+    /// If `shift` is not greater than one.
+    pub fn with_shift(mut self, shift: f64) -> Self {
+        assert!(shift > 1., "CUSUM shift must exceed 1, got {shift}");
+        self.shift = shift;
+        self
+    }
+    /// The CUSUM statistic, in nats of evidence for the shifted rate.
+    pub fn current_surprise(&self) -> f64 {
+        self.cusum
+    }
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+    /// Feed the next timestamp and return a spike event, if any. A
+    /// timestamp earlier than the last one counts as a zero gap.
+    pub fn push(&mut self, ts: Instant) -> SpikeEvent {
+        let prev = self.last_ts;
+        self.last_ts = Some(prev.map_or(ts, |prev| prev.max(ts)));
+        let Some(prev) = prev else {
+            return SpikeEvent::None;
+        };
+        let gap = ts.saturating_duration_since(prev).as_secs_f64();
+        let mean_gap = self.gaps.mean();
+        if mean_gap > 0. {
+            // Exponential gaps: ln(λ₁/λ₀) - (λ₁ - λ₀)·gap with λ₁ = shift·λ₀.
+            let llr = self.shift.ln() - (self.shift - 1.) * gap / mean_gap;
+            self.cusum = (self.cusum + llr).max(0.);
+        }
+        self.gaps.push(gap, 1., 1. - 1. / L as f64);
+        match self.phase {
+            Phase::Idle if self.cusum > self.start_t => {
+                self.phase = Phase::InSpike;
+                SpikeEvent::Begin {
+                    surprise: self.cusum,
+                }
+            }
+            Phase::InSpike if self.cusum < self.end_t => {
+                self.phase = Phase::Idle;
+                SpikeEvent::End {
+                    surprise: self.cusum,
+                }
             }
             _ => SpikeEvent::None,
         }
     }
 }
 
+type ChatPredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
 /// Sliding window of recent chats with TF-IDF-like weighting.
 ///
 /// Short/long horizons reuse the same `S`/`L` parameters as `SpikeDetector`.
+/// `S` also bounds the number of cached chats; [`with_max_len`] and
+/// [`with_max_age`] tighten that bound by count and by age. Chats are kept
+/// in a `Ring` of `S` by default; `R` may instead be a
+/// [`DynRing`](crate::ring::DynRing) sized at runtime, see
+/// [`with_ring`](Self::with_ring).
+///
+/// Token statistics live in a separate [`Dictionary`] passed to the
+/// `*_with_dict` methods, so one dictionary can outlive or be shared
+/// between windows. `K` picks how tokens are keyed in that dictionary.
+/// [`push`](Self::push) and [`summary`](Self::summary) use a
+/// [`MemoryDictionary`] owned by the window instead.
+///
+/// [`with_max_len`]: Self::with_max_len
+/// [`with_max_age`]: Self::with_max_age
 #[derive(Clone)]
-pub struct ChatWindow<const S: usize, const L: usize, D = ()> {
+pub struct ChatWindow<
+    const S: usize,
+    const L: usize,
+    D = (),
+    K = TextKey,
+    R = Ring<ChatCache<D>, S>,
+> {
     ngram_range: (usize, usize),
+    tokenizer: Option<Arc<dyn Tokenizer + Send + Sync>>,
+    #[cfg(feature = "unicode-normalization")]
+    unicode_form: Option<UnicodeForm>,
+    stopwords: Stopwords,
+    normalizer: NormalizerPipeline,
+    min_summary_score: f64,
+    summary_filter: Option<ChatPredicate>,
+    recency_bias: f64,
+    near_duplicate_distance: Option<u32>,
+    length_exponent: f64,
+    incremental: bool,
+    max_age: Option<Duration>,
+    max_len: usize,
+    author_policy: AuthorPolicy,
+    token_weights: Option<TokenWeights>,
+    novelty_boost: f64,
     last_chat_idx: u32,
-    recent_chats: Ring<ChatCache<D>, S>,
-    next_token_id: usize,
-    token_dict: HashMap<String, usize>,
-    token_stats: Vec<TokenStats>,
-}
-
-#[derive(Clone, Default)]
-pub struct TokenStats {
-    count_s: f64,
-    count_l: f64,
-    last_chat_idx: u32,
+    /// Newest timestamp pushed, which wall-time dictionaries count as of.
+    last_ts: Option<Instant>,
+    recent_chats: R,
+    /// Running sum of the frozen tf-idf vectors in the window, each scaled by
+    /// its chat's weight.
+    token_mass: HashMap<ChatToken, f64>,
+    /// Pool the window's tokens are allocated from, shared across chats,
+    /// unless the dictionary [interns](Dictionary::intern) them.
+    interner: Interner,
+    /// Interner size that triggers the next compaction.
+    compact_at: usize,
+    /// Statistics behind `push` and `summary`.
+    dict: MemoryDictionary<L>,
+    scratch: Scratch,
+    marker: PhantomData<(D, K)>,
 }
 
+/// Buffers `ChatWindow` reuses from push to push, so that pushing a chat
+/// like those before it allocates nothing once they have grown.
 #[derive(Clone, Default)]
-pub struct ChatCache<D> {
-    token_ids: Vec<usize>,
+struct Scratch {
+    /// Second buffer the normalizer steps alternate with.
+    text: String,
+    /// Tokens of the chat being pushed, before interning.
+    tokens: RawTokens,
+    /// Buffers of the last chat to leave the window, for the next one.
     chat: String,
-    data: Option<D>,
+    interned: Vec<ChatToken>,
+    kinds: Vec<TokenKind>,
+    weights: Vec<f64>,
 }
 
-impl<const S: usize, const L: usize, D> Default for ChatWindow<S, L, D> {
-    fn default() -> Self {
-        Self {
-            ngram_range: (1, 4),
-            last_chat_idx: 0,
-            recent_chats: Ring::default(),
-            next_token_id: 0,
-            token_dict: HashMap::default(),
-            token_stats: Vec::default(),
-        }
+impl Scratch {
+    /// Keep the buffers of a chat leaving the window.
+    fn recycle<D>(&mut self, c: ChatCache<D>) {
+        let mut interned = c.tokens;
+        interned.clear();
+        self.interned = interned;
+        self.chat = c.chat;
+        self.kinds = c.kinds;
+        self.weights = c.frozen_tfidf;
     }
 }
 
-impl<const S: usize, const L: usize, D> ChatWindow<S, L, D> {
-    pub fn with_ngram_range(mut self, min: usize, max: usize) -> Self {
-        self.ngram_range = (min, max);
-        self
+/// Tokens with their text copied back to back into one buffer, so that
+/// both outlive the chat they were split from.
+#[derive(Clone, Default)]
+struct RawTokens {
+    text: String,
+    /// Span of `text`, kind and fingerprint of each token.
+    tokens: Vec<(Range<usize>, TokenKind, Option<u64>)>,
+}
+
+impl RawTokens {
+    fn clear(&mut self) {
+        self.text.clear();
+        self.tokens.clear();
     }
-    /// Insert a chat line, updating token statistics.
-    pub fn push(&mut self, chat: String) {
-        self.push_with_data(chat, None)
+    fn push(&mut self, token: Token<'_>) {
+        let start = self.text.len();
+        self.text.push_str(&token.text);
+        self.tokens
+            .push((start..self.text.len(), token.kind, token.fingerprint));
     }
-    pub fn push_with_data(&mut self, chat: String, data: Option<D>) {
-        let decay_s = 1. - 1. / (S as f64);
-        let decay_l = 1. - 1. / (L as f64);
-        self.last_chat_idx += 1;
-        let chat = normalize(&chat);
-        let tokens = unique_char_ngrams(chat.as_str(), self.ngram_range.0, self.ngram_range.1);
-        let token_ids: Vec<_> = tokens
-            .into_iter()
-            .map(|token| {
-                *self.token_dict.entry(token).or_insert_with(|| {
-                    let id = self.next_token_id;
-                    self.token_stats.push(TokenStats::default());
-                    self.next_token_id += 1;
-                    id
-                })
-            })
-            .collect();
-        token_ids.iter().for_each(|&id| {
-            let stats = &mut self.token_stats[id];
-            let num_gap = (self.last_chat_idx - stats.last_chat_idx) as f64;
-            if num_gap < 10. * L as f64 {
-                stats.count_l = stats.count_l * decay_l.powf(num_gap) + 1.;
-                stats.count_s = stats.count_s * decay_s.powf(num_gap) + 1.;
-            } else {
-                stats.count_l = 1.;
-                stats.count_s = 1.;
-            }
-            stats.last_chat_idx = self.last_chat_idx;
-        });
-        self.recent_chats.push(ChatCache {
-            token_ids,
-            chat,
-            data,
+    /// Drop the stopwords, then sort and drop repeats like
+    /// [`dedup_tokens`](crate::tokenizer::dedup_tokens).
+    fn filter_and_dedup(&mut self, stopwords: &Stopwords) {
+        let text = &self.text;
+        if !stopwords.is_empty() {
+            self.tokens
+                .retain(|(span, _, _)| !stopwords.contains(&text[span.clone()]));
+        }
+        self.tokens.sort_unstable_by(|a, b| {
+            (&text[a.0.clone()], a.2)
+                .cmp(&(&text[b.0.clone()], b.2))
+                .then(b.1.cmp(&a.1))
         });
+        self.tokens
+            .dedup_by(|a, b| text[a.0.clone()] == text[b.0.clone()] && a.2 == b.2);
     }
-
-    /// Return `(chat_text, Option<data>, score)` with the highest degree centrality.
-    pub fn summary(&self) -> Option<(&str, Option<&D>, f64)> {
-        let mut uv = HashMap::<usize, f64>::new();
-        for ChatCache { token_ids, .. } in self.recent_chats.iter() {
-            let norm2: f64 = token_ids
-                .iter()
-                .map(|&t| ((L as f64) / self.token_stats[t].count_l).ln().powi(2))
-                .sum::<f64>()
-                .sqrt();
-            for (id, u) in token_ids
-                .iter()
-                .map(move |&t| (t, ((L as f64) / self.token_stats[t].count_l).ln() / norm2))
-            {
-                uv.entry(id).and_modify(|v| *v += u).or_insert(u);
-            }
-        }
-        self.recent_chats
+    fn iter(&self) -> impl Iterator<Item = (&str, TokenKind, Option<u64>)> {
+        self.tokens
             .iter()
-            .map(
-                |ChatCache {
-                     token_ids,
-                     chat,
-                     data,
-                 }| {
-                    let norm2: f64 = token_ids
-                        .iter()
-                        .map(|&t| ((L as f64) / self.token_stats[t].count_l).ln().powi(2))
-                        .sum::<f64>()
-                        .sqrt();
-                    let degree_centrality = token_ids
-                        .iter()
-                        .map(move |&t| (((L as f64) / self.token_stats[t].count_l).ln() / norm2, t))
-                        .map(|(u, t)| u * uv.get(&t).unwrap_or(&0.))
-                        .sum::<f64>()
-                        - 1.0;
-                    let degree_centrality = if degree_centrality.is_nan() {
-                        0.0
-                    } else {
-                        degree_centrality
-                    };
-                    (chat.as_str(), data.as_ref(), degree_centrality)
-                },
-            )
-            .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Less))
+            .map(|(span, kind, fingerprint)| (&self.text[span.clone()], *kind, *fingerprint))
     }
 }
 
-/// Combines timestamp-based burst detection with content-based summaries.
-#[derive(Default)]
-pub struct ChatSpikeDetector<const S: usize, const L: usize, D = ()> {
-    spike: SpikeDetector<S, L>,
-    recent_chats: ChatWindow<S, L, D>,
+/// How repeated chats from one author within the window are weighted.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum AuthorPolicy {
+    /// Every chat counts fully.
+    #[default]
+    Off,
+    /// Chats beyond the first `n` from an author count zero.
+    Cap(usize),
+    /// The `k`-th chat (from zero) from an author counts `(k + 1)^-p`.
+    Decay(f64),
 }
 
-/// High-level event emitted by `ChatSpikeDetector`.
-#[derive(Clone, Copy, Default, Debug)]
-pub enum Event<'a, D> {
-    #[default]
-    None,
-    SpikeBegin {
-        summary: Option<&'a str>,
-        data: Option<&'a D>,
-        surprise: f64,
-    },
-    SpikeEnd {
-        summary: Option<&'a str>,
-        data: Option<&'a D>,
-        surprise: f64,
-    },
+/// Per-kind multipliers on token idf weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenWeights {
+    pub text: f64,
+    pub emote: f64,
+    pub mention: f64,
 }
 
-impl<const S: usize, const L: usize, D> ChatSpikeDetector<S, L, D> {
-    pub fn with_ngram_range(mut self, min: usize, max: usize) -> Self {
-        self.recent_chats = self.recent_chats.with_ngram_range(min, max);
-        self
+impl Default for TokenWeights {
+    fn default() -> Self {
+        Self {
+            text: 1.,
+            emote: 1.,
+            mention: 1.,
+        }
     }
-    pub fn with_threshold(mut self, start_t: f64, end_t: f64) -> Self {
-        self.spike = self.spike.with_threshold(start_t, end_t);
-        self
+}
+
+impl TokenWeights {
+    pub fn of(&self, kind: TokenKind) -> f64 {
+        match kind {
+            TokenKind::Text => self.text,
+            TokenKind::Emote => self.emote,
+            TokenKind::Mention => self.mention,
+        }
     }
+}
 
-    /// Add a chat message and return an event when a spike starts or ends.
-    pub fn update_and_detect(&mut self, chat: String, ts: Instant) -> Event<D> {
-        self.update_and_detect_with_data(chat, ts, None)
+/// A token as `ChatWindow` keeps it: its interned text, or only the hash of
+/// an [opaque token](Token::fingerprint). Displays as the text, or the hash
+/// in hex.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChatToken {
+    Text(Arc<str>),
+    Fingerprint(u64),
+}
+
+impl ChatToken {
+    /// Text of the token, unless it is opaque.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Fingerprint(_) => None,
+        }
     }
-    pub fn update_and_detect_with_data(
-        &mut self,
-        chat: String,
-        ts: Instant,
-        data: Option<D>,
-    ) -> Event<D> {
-        self.recent_chats.push_with_data(chat, data);
-        match self.spike.push(ts) {
-            SpikeEvent::Begin { surprise } => {
-                let summary = self.recent_chats.summary();
-                Event::SpikeBegin {
-                    summary: summary.map(|s| s.0),
-                    data: summary.and_then(|s| s.1),
-                    surprise,
-                }
-            }
-            SpikeEvent::End { surprise } => {
-                let summary = self.recent_chats.summary();
-                Event::SpikeEnd {
-                    summary: summary.map(|s| s.0),
-                    data: summary.and_then(|s| s.1),
-                    surprise,
-                }
+    /// Call `f` with the key of the token.
+    fn with_key<K: TokenKey, R>(&self, f: impl FnOnce(&K::Key) -> R) -> R {
+        match self {
+            Self::Text(text) => K::with_key(text, f),
+            Self::Fingerprint(h) => K::with_opaque(*h, f),
+        }
+    }
+    /// Hash the SimHash of a chat is built from.
+    fn simhash_input(&self) -> u64 {
+        match self {
+            Self::Text(text) => {
+                let mut hasher = DefaultHasher::new();
+                text.hash(&mut hasher);
+                hasher.finish()
             }
-            _ => Event::None,
+            Self::Fingerprint(h) => *h,
         }
     }
+}
 
-    pub fn current_surprise(&self) -> f64 {
-        self.spike.current_surprise()
+impl From<&str> for ChatToken {
+    fn from(text: &str) -> Self {
+        Self::Text(text.into())
     }
-    pub fn current_phase(&self) -> Phase {
-        self.spike.phase
+}
+
+impl PartialEq<str> for ChatToken {
+    fn eq(&self, other: &str) -> bool {
+        self.as_text() == Some(other)
     }
-    pub fn last_updated_at(&self) -> Option<Instant> {
-        self.spike.last_ts
+}
+
+impl fmt::Display for ChatToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Fingerprint(h) => write!(f, "{h:x}"),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Clone, Default)]
+pub struct ChatCache<D> {
+    tokens: Vec<ChatToken>,
+    /// Kind of each token; empty unless token weights are configured.
+    kinds: Vec<TokenKind>,
+    /// tf-idf weights frozen at push time.
+    frozen_tfidf: Vec<f64>,
+    fingerprint: u64,
+    idx: u32,
+    ts: Option<Instant>,
+    author: Option<String>,
+    /// Multiplier on the chat's say in summaries.
+    weight: f64,
+    chat: String,
+    data: Option<D>,
+}
+
+impl<D> ChatCache<D> {
+    /// Normalized chat text.
+    pub fn chat(&self) -> &str {
+        &self.chat
+    }
+    pub fn data(&self) -> Option<&D> {
+        self.data.as_ref()
+    }
+    /// Unique tokens of the normalized chat, interned by the window.
+    pub fn tokens(&self) -> &[ChatToken] {
+        &self.tokens
+    }
+    /// Kind of each token, parallel to [`tokens`](Self::tokens).
+    pub fn token_kinds(&self) -> &[TokenKind] {
+        &self.kinds
+    }
+    /// SimHash of the tokens.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+    /// Sequence number the chat was observed under.
+    pub fn idx(&self) -> u32 {
+        self.idx
+    }
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+    /// Weight of the chat in summaries, e.g. from the author policy.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+    /// Receive time, for chats pushed with a timestamp.
+    pub fn ts(&self) -> Option<Instant> {
+        self.ts
+    }
+}
+
+impl<const S: usize, const L: usize, D, K, R: RingBuffer<ChatCache<D>> + Default> Default
+    for ChatWindow<S, L, D, K, R>
+{
+    fn default() -> Self {
+        Self::with_ring(R::default())
+    }
+}
+
+impl<const S: usize, const L: usize, D, K, R: RingBuffer<ChatCache<D>>> ChatWindow<S, L, D, K, R> {
+    /// Window caching chats in `ring`, whose capacity then bounds the
+    /// number of chats in place of `S`. The ring's [`OverflowPolicy`] applies
+    /// whenever the window is full: `Reject` drops the new chat before its
+    /// tokens are observed, and `Callback` receives each chat evicted to make
+    /// room, after the window has forgotten it.
+    pub fn with_ring(ring: R) -> Self {
+        Self {
+            ngram_range: (1, 4),
+            tokenizer: None,
+            #[cfg(feature = "unicode-normalization")]
+            unicode_form: None,
+            stopwords: Stopwords::default(),
+            normalizer: NormalizerPipeline::default(),
+            min_summary_score: f64::NEG_INFINITY,
+            summary_filter: None,
+            recency_bias: 0.,
+            near_duplicate_distance: None,
+            length_exponent: 0.,
+            incremental: true,
+            max_age: None,
+            max_len: ring.capacity(),
+            author_policy: AuthorPolicy::Off,
+            token_weights: None,
+            novelty_boost: 0.,
+            last_chat_idx: 0,
+            last_ts: None,
+            recent_chats: ring,
+            token_mass: HashMap::default(),
+            interner: Interner::default(),
+            compact_at: 1024,
+            dict: MemoryDictionary::default(),
+            scratch: Scratch::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<const S: usize, const L: usize, D, R: RingBuffer<ChatCache<D>>>
+    ChatWindow<S, L, D, TextKey, R>
+{
+    /// Insert a chat line, updating the window's own token statistics.
+    pub fn push(&mut self, chat: String) {
+        self.push_with_data(chat, None)
+    }
+    pub fn push_with_data(&mut self, chat: String, data: Option<D>) {
+        let mut dict = std::mem::take(&mut self.dict);
+        self.push_with_data_and_dict(chat, data, &mut dict);
+        self.dict = dict;
+    }
+    /// Return `(chat_text, Option<data>, score)` with the highest degree
+    /// centrality over the window's own token statistics.
+    pub fn summary(&self) -> Option<(&str, Option<&D>, f64)> {
+        DegreeCentrality.summarize(self, &self.dict)
+    }
+}
+
+impl<const S: usize, const L: usize, D, K: TokenKey, R: RingBuffer<ChatCache<D>>>
+    ChatWindow<S, L, D, K, R>
+{
+    pub fn with_ngram_range(mut self, min: usize, max: usize) -> Self {
+        self.ngram_range = (min, max);
+        self
+    }
+    /// Split chats with `tokenizer` instead of the built-in character
+    /// n-grams, e.g. into words or morphemes. The n-gram range is then
+    /// ignored, and token kinds come from the tokenizer.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + Send + Sync + 'static) -> Self {
+        self.tokenizer = Some(Arc::new(tokenizer));
+        self
+    }
+    /// Normalize chats with `pipeline` instead of the default repeat
+    /// collapsing and Korean interjection splitting.
+    ///
+    /// ```rust
+    /// use chat_spike::{normalizer::NormalizerPipeline, spike::ChatWindow, text::Interjections};
+    ///
+    /// let window = ChatWindow::<30, 100>::default().with_normalizer(
+    ///     NormalizerPipeline::new()
+    ///         .derepeat(3, 4)
+    ///         .interjections(Interjections::japanese()),
+    /// );
+    /// ```
+    pub fn with_normalizer(mut self, pipeline: NormalizerPipeline) -> Self {
+        self.normalizer = pipeline;
+        self
+    }
+    /// Apply Unicode normalization `form` before the other normalization
+    /// steps, so compatibility variants of a chat share tokens.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn with_unicode_form(mut self, form: UnicodeForm) -> Self {
+        self.unicode_form = Some(form);
+        self
+    }
+    /// Characters and words split off the words they are glued to during
+    /// normalization. Defaults to [`Interjections::korean`]. This resets
+    /// the normalizer to the default steps with `interjections` swapped in.
+    pub fn with_interjections(mut self, interjections: Interjections) -> Self {
+        self.normalizer = NormalizerPipeline::new()
+            .derepeat(3, 4)
+            .interjections(interjections);
+        self
+    }
+    fn normalize(&self, chat: &str) -> String {
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = self.unicode_form {
+            return self.normalizer.normalize(&normalize_unicode(chat, form));
+        }
+        self.normalizer.normalize(chat)
+    }
+    /// [`normalize`](Self::normalize) into `out`, reusing the scratch text.
+    fn normalize_into(&mut self, chat: &str, out: &mut String) {
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = self.unicode_form {
+            let chat = normalize_unicode(chat, form);
+            self.normalizer
+                .normalize_with_scratch(&chat, out, &mut self.scratch.text);
+            return;
+        }
+        self.normalizer
+            .normalize_with_scratch(chat, out, &mut self.scratch.text);
+    }
+    /// Drop these tokens right after tokenization, e.g.
+    /// [`Stopwords::korean`] for particles.
+    pub fn with_stopwords(mut self, stopwords: Stopwords) -> Self {
+        self.stopwords = stopwords;
+        self
+    }
+    /// Tokenize into word n-grams with `n` in `range` instead of character
+    /// n-grams, e.g. `1..=2` for words and word pairs.
+    pub fn with_word_tokens(self, range: RangeInclusive<usize>) -> Self {
+        self.with_tokenizer(WordNgrams::from(range))
+    }
+    /// Report no summary when the best centrality is below `min_score`.
+    pub fn with_min_summary_score(mut self, min_score: f64) -> Self {
+        self.min_summary_score = min_score;
+        self
+    }
+    /// Only chats whose normalized text passes `predicate` may be picked as
+    /// summaries. Rejected chats still count toward everyone else's score.
+    ///
+    /// ```rust
+    /// use chat_spike::{spike::ChatWindow, text::is_symbol_only};
+    ///
+    /// let window = ChatWindow::<30, 100>::default().with_summary_filter(|c| !is_symbol_only(c));
+    /// ```
+    pub fn with_summary_filter(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.summary_filter = Some(Arc::new(predicate));
+        self
+    }
+    /// Weight the chat `k` places before the newest by `exp(-alpha * k)`,
+    /// both as a summary candidate and as a neighbour of other chats.
+    ///
+    /// `alpha = 0` (the default) weighs the whole window equally.
+    pub fn with_recency_bias(mut self, alpha: f64) -> Self {
+        self.recency_bias = alpha;
+        self
+    }
+    /// Collapse chats whose SimHash fingerprints differ in at most
+    /// `max_distance` bits before scoring.
+    ///
+    /// A group of `m` near-duplicates counts as one chat weighted by
+    /// `1 + ln(m)`, so copy-pasta floods no longer dominate the summary.
+    pub fn with_near_duplicate_collapsing(mut self, max_distance: u32) -> Self {
+        self.near_duplicate_distance = Some(max_distance);
+        self
+    }
+    /// Scale each chat's score by `(mean_len / len)^exponent`, where `len` is
+    /// its token count, so long messages don't win on sheer n-gram volume.
+    ///
+    /// `0` (the default) disables it; `1` fully cancels the length advantage.
+    pub fn with_length_normalization(mut self, exponent: f64) -> Self {
+        self.length_exponent = exponent;
+        self
+    }
+    /// Score summaries with each chat's tf-idf vector as frozen when it was
+    /// pushed, and with their sum, kept up to date as chats enter and leave
+    /// the window. This is the default.
+    ///
+    /// Summaries then skip all dictionary lookups, at the cost of weighting
+    /// older chats with the idf values of their own time. Without recency
+    /// bias, novelty boost or near-duplicate collapsing the summed vector is
+    /// reused as is.
+    pub fn with_incremental_centrality(mut self) -> Self {
+        self.incremental = true;
+        self
+    }
+    /// Re-weight every chat with the current idf values on each summary
+    /// instead, at the cost of a dictionary lookup per token in the window.
+    pub fn with_exact_centrality(mut self) -> Self {
+        self.incremental = false;
+        self
+    }
+    /// Drop chats more than `max_age` older than the newest timestamped chat,
+    /// on top of the `S`-chat capacity.
+    ///
+    /// Only chats inserted with [`push_at_with_dict`](Self::push_at_with_dict)
+    /// carry a timestamp; the others are evicted by count alone.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+    /// Keep at most `max_len` chats (capped at the ring's capacity).
+    ///
+    /// Combined with [`with_max_age`](Self::with_max_age) this gives a hybrid
+    /// window: busy channels are bounded by count, slow ones by age.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.min(self.recent_chats.capacity());
+        self
+    }
+    /// How repeated chats from the same author in the window are weighted.
+    pub fn with_author_policy(mut self, policy: AuthorPolicy) -> Self {
+        self.author_policy = policy;
+        self
+    }
+    /// Keep emotes and mentions as whole tokens and scale the idf weight of
+    /// every token by the multiplier for its kind.
+    ///
+    /// ```rust
+    /// use chat_spike::spike::{ChatWindow, TokenWeights};
+    ///
+    /// let window = ChatWindow::<30, 100>::default().with_token_weights(TokenWeights {
+    ///     emote: 1.5,
+    ///     mention: 0.2,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn with_token_weights(mut self, weights: TokenWeights) -> Self {
+        self.token_weights = Some(weights);
+        self
+    }
+    /// Weight each chat by `1 + beta * mean_novelty` of its tokens, so chats
+    /// introducing new memes or emotes are favoured as summaries. Needs a
+    /// dictionary that reports [`novelty`](Dictionary::novelty), such as a
+    /// `MemoryDictionary` with novelty tracking.
+    pub fn with_novelty_boost(mut self, beta: f64) -> Self {
+        self.novelty_boost = beta;
+        self
+    }
+    /// Insert a chat line, updating token statistics in `dict`.
+    pub fn push_with_dict(&mut self, chat: String, dict: &mut impl Dictionary<K::Key>) {
+        self.push_with_data_and_dict(chat, None, dict)
+    }
+    pub fn push_with_data_and_dict(
+        &mut self,
+        chat: String,
+        data: Option<D>,
+        dict: &mut impl Dictionary<K::Key>,
+    ) {
+        self.insert(chat, None, None, 1., data, dict)
+    }
+    /// Insert a chat line received at `ts`, evicting chats older than the
+    /// maximum age.
+    pub fn push_at_with_dict(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        data: Option<D>,
+        dict: &mut impl Dictionary<K::Key>,
+    ) {
+        self.insert(chat, Some(ts), None, 1., data, dict)
+    }
+    /// Insert a chat from `author`, weighted in summaries by the author
+    /// policy. Returns the weight that was applied.
+    pub fn push_by_author_with_dict(
+        &mut self,
+        chat: String,
+        author: String,
+        ts: Instant,
+        data: Option<D>,
+        dict: &mut impl Dictionary<K::Key>,
+    ) -> f64 {
+        let weight = self.author_weight(&author);
+        self.insert(chat, Some(ts), Some(author), weight, data, dict);
+        weight
+    }
+    /// Weight the next chat from `author` would get under the author policy.
+    pub fn author_weight(&self, author: &str) -> f64 {
+        let seen = || {
+            self.recent_chats
+                .iter()
+                .filter(|c| c.author.as_deref() == Some(author))
+                .count()
+        };
+        match self.author_policy {
+            AuthorPolicy::Off => 1.,
+            AuthorPolicy::Cap(n) => {
+                if seen() < n {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            AuthorPolicy::Decay(p) => ((seen() + 1) as f64).powf(-p),
+        }
+    }
+    fn insert(
+        &mut self,
+        chat: String,
+        ts: Option<Instant>,
+        author: Option<String>,
+        weight: f64,
+        data: Option<D>,
+        dict: &mut impl Dictionary<K::Key>,
+    ) {
+        if let Some(cutoff) = ts
+            .zip(self.max_age)
+            .and_then(|(ts, age)| ts.checked_sub(age))
+        {
+            self.truncate_older_than(cutoff);
+        }
+        if self.is_full() && matches!(self.recent_chats.overflow(), OverflowPolicy::Reject) {
+            return;
+        }
+        self.last_chat_idx += 1;
+        let mut text = std::mem::take(&mut self.scratch.chat);
+        self.normalize_into(&chat, &mut text);
+        let chat = text;
+        let ngrams = CharNgrams {
+            min: self.ngram_range.0,
+            max: self.ngram_range.1,
+        };
+        let mut raw = std::mem::take(&mut self.scratch.tokens);
+        raw.clear();
+        let push = &mut |t| raw.push(t);
+        match (&self.tokenizer, self.token_weights) {
+            (Some(tokenizer), _) => tokenizer.for_each_token(&chat, push),
+            (None, Some(_)) => TypedCharNgrams(ngrams).for_each_token(&chat, push),
+            (None, None) => ngrams.for_each_token(&chat, push),
+        }
+        raw.filter_and_dedup(&self.stopwords);
+        let mut tokens = std::mem::take(&mut self.scratch.interned);
+        let mut kinds = std::mem::take(&mut self.scratch.kinds);
+        kinds.clear();
+        for (text, kind, fingerprint) in raw.iter() {
+            let token = match (fingerprint, dict.intern(text)) {
+                (Some(h), _) => ChatToken::Fingerprint(h),
+                (None, Some(text)) => ChatToken::Text(text),
+                (None, None) if K::FINGERPRINTED => ChatToken::Fingerprint(token_fingerprint(text)),
+                (None, None) => ChatToken::Text(self.interner.intern_arc(text)),
+            };
+            tokens.push(token);
+            kinds.push(kind);
+        }
+        self.scratch.tokens = raw;
+        if let Some(ts) = ts {
+            self.last_ts = Some(self.last_ts.map_or(ts, |last| last.max(ts)));
+        }
+        let idx = self.last_chat_idx;
+        tokens.iter().for_each(|token| {
+            token.with_key::<K, _>(|k| match ts {
+                Some(ts) => dict.observe_timed(k, idx, ts, 1.),
+                None => dict.observe(k, idx),
+            })
+        });
+        let fingerprint = simhash_hashed(tokens.iter().map(ChatToken::simhash_input));
+        let mut frozen_tfidf = std::mem::take(&mut self.scratch.weights);
+        self.tfidf_weights(&tokens, &kinds, dict, &mut frozen_tfidf);
+        frozen_tfidf
+            .iter_mut()
+            .filter(|u| u.is_nan())
+            .for_each(|u| *u = 0.);
+        for (token, u) in tokens.iter().zip(frozen_tfidf.iter()) {
+            *self.token_mass.entry(token.clone()).or_insert(0.) += weight * u;
+        }
+        while self.is_full() {
+            match self.recent_chats.pop_oldest() {
+                Some(c) => self.evict(c),
+                None => break,
+            }
+        }
+        self.recent_chats.push(ChatCache {
+            tokens,
+            kinds,
+            frozen_tfidf,
+            fingerprint,
+            idx: self.last_chat_idx,
+            ts,
+            author,
+            weight,
+            chat,
+            data,
+        });
+        if self.interner.len() >= self.compact_at {
+            self.interner.compact();
+            self.compact_at = (2 * self.interner.len()).max(1024);
+        }
+    }
+    /// Drop every chat from the window. The dictionary is left untouched.
+    pub fn clear(&mut self) {
+        self.recent_chats.drain();
+        self.token_mass.clear();
+    }
+    /// Drop chats received before `ts`, oldest first, stopping at the first
+    /// chat that is newer or has no timestamp.
+    pub fn truncate_older_than(&mut self, ts: Instant) {
+        while let Some(oldest) = self.recent_chats.front() {
+            match oldest.ts {
+                Some(t) if t < ts => {
+                    let evicted = self.recent_chats.pop_oldest();
+                    evicted.into_iter().for_each(|c| self.forget(c));
+                }
+                _ => break,
+            }
+        }
+    }
+    /// Remove every chat matching `predicate` from the window and undo its
+    /// token observations in `dict`, returning the removed chats.
+    pub fn retract_with_dict(
+        &mut self,
+        mut predicate: impl FnMut(&ChatCache<D>) -> bool,
+        dict: &mut impl Dictionary<K::Key>,
+    ) -> Vec<ChatCache<D>> {
+        let (removed, kept): (Vec<_>, Vec<_>) =
+            self.recent_chats.drain().partition(|c| predicate(c));
+        kept.into_iter().for_each(|c| {
+            self.recent_chats.push(c);
+        });
+        for c in removed.iter() {
+            c.tokens.iter().for_each(|t| {
+                t.with_key::<K, _>(|k| match c.ts {
+                    Some(ts) => dict.retract_timed(k, c.idx, ts),
+                    None => dict.retract(k, c.idx),
+                })
+            });
+            for (token, u) in c.tokens.iter().zip(c.frozen_tfidf.iter()) {
+                if let Some(mass) = self.token_mass.get_mut(token) {
+                    *mass -= c.weight * u;
+                }
+            }
+        }
+        self.token_mass.retain(|_, mass| mass.abs() >= 1e-9);
+        removed
+    }
+    /// Forget a chat evicted to make room, handing it to the ring's
+    /// overflow callback if it has one.
+    fn evict(&mut self, evicted: ChatCache<D>) {
+        match self.recent_chats.overflow() {
+            OverflowPolicy::Callback(f) => {
+                let f = f.clone();
+                self.unmass(&evicted);
+                f(evicted);
+            }
+            _ => self.forget(evicted),
+        }
+    }
+    /// Remove a chat leaving the window from the running token mass.
+    fn forget(&mut self, evicted: ChatCache<D>) {
+        self.unmass(&evicted);
+        self.scratch.recycle(evicted);
+    }
+    fn unmass(&mut self, evicted: &ChatCache<D>) {
+        for (token, u) in evicted.tokens.iter().zip(evicted.frozen_tfidf.iter()) {
+            if let Some(mass) = self.token_mass.get_mut(token) {
+                *mass -= evicted.weight * u;
+                if mass.abs() < 1e-9 {
+                    self.token_mass.remove(token);
+                }
+            }
+        }
+    }
+
+    /// Sequence number of the most recently pushed chat.
+    pub fn last_chat_idx(&self) -> u32 {
+        self.last_chat_idx
+    }
+    /// Chats in the window as `(chat_text, Option<data>)`, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&D>)> {
+        self.recent_chats
+            .iter()
+            .map(|c| (c.chat.as_str(), c.data.as_ref()))
+    }
+    /// Number of chats in the window.
+    pub fn len(&self) -> usize {
+        self.recent_chats.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Whether the next push will evict the oldest chat by count.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.max_len
+    }
+    /// Cached chats, oldest first.
+    pub fn chat_caches(&self) -> impl Iterator<Item = &ChatCache<D>> {
+        self.recent_chats.iter()
+    }
+    /// Inverse-frequency weight `ln(L / count)` of `token` as of the latest chat.
+    pub fn token_weight(&self, token: &str, dict: &impl Dictionary<K::Key>) -> f64 {
+        ((L as f64) / K::with_key(token, |k| self.key_count(k, dict))).ln()
+    }
+    /// Count of `token` in `dict` as of the latest chat.
+    fn token_count(&self, token: &ChatToken, dict: &impl Dictionary<K::Key>) -> f64 {
+        token.with_key::<K, _>(|k| self.key_count(k, dict))
+    }
+    fn key_count(&self, key: &K::Key, dict: &impl Dictionary<K::Key>) -> f64 {
+        match self.last_ts {
+            Some(ts) => dict.count_timed(key, self.last_chat_idx, ts),
+            None => dict.count(key, self.last_chat_idx),
+        }
+    }
+
+    /// Return `(chat_text, Option<data>, score)` with the highest degree centrality.
+    pub fn summary_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+    ) -> Option<(&str, Option<&D>, f64)> {
+        self.scored_vectors(dict)
+            .into_iter()
+            .filter(|c| c.eligible)
+            .map(|c| (c.cache.chat.as_str(), c.cache.data.as_ref(), c.score))
+            .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Less))
+            .filter(|s| s.2 >= self.min_summary_score)
+    }
+
+    /// Every recent chat as `(chat_text, Option<data>, score)`, oldest first,
+    /// scored by degree centrality.
+    ///
+    /// With near-duplicate collapsing on, each group of near-identical chats
+    /// appears once, by its latest member; see
+    /// [`scored_clusters_with_dict`](Self::scored_clusters_with_dict) for
+    /// the group sizes.
+    pub fn scored_chats_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+    ) -> Vec<(&str, Option<&D>, f64)> {
+        self.scored_vectors(dict)
+            .into_iter()
+            .map(|c| (c.cache.chat.as_str(), c.cache.data.as_ref(), c.score))
+            .collect()
+    }
+
+    /// Like [`scored_chats_with_dict`](Self::scored_chats_with_dict), with
+    /// the number of chats each entry stands for, which is 1 unless
+    /// near-duplicate collapsing is on.
+    pub fn scored_clusters_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+    ) -> Vec<(&str, Option<&D>, f64, usize)> {
+        self.scored_vectors(dict)
+            .into_iter()
+            .map(|c| {
+                let (chat, data) = (c.cache.chat.as_str(), c.cache.data.as_ref());
+                (chat, data, c.score, c.members)
+            })
+            .collect()
+    }
+
+    /// Like [`summary_with_dict`](Self::summary_with_dict), also returning
+    /// the `k` tokens that contributed most to the chosen chat's score.
+    pub fn summary_explained_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        k: usize,
+    ) -> Option<SummaryExplanation<'_, D>> {
+        let scored = self.scored_vectors(dict);
+        let uv = token_mass(scored.iter().map(|c| (c.vector.as_slice(), c.weight)));
+        let best = scored
+            .iter()
+            .filter(|c| c.eligible)
+            .max_by(|a, b| {
+                a.score
+                    .partial_cmp(&b.score)
+                    .unwrap_or(std::cmp::Ordering::Less)
+            })
+            .filter(|c| c.score >= self.min_summary_score)?;
+        let mut tokens: Vec<_> = best
+            .vector
+            .iter()
+            .map(|&(token, weight)| TokenContribution {
+                token,
+                weight,
+                // Exclude the chat's own vector, mirroring the score.
+                contribution: best.length_factor
+                    * best.weight
+                    * weight
+                    * (uv.get(token).unwrap_or(&0.) - best.weight * weight),
+            })
+            .collect();
+        tokens.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+        tokens.truncate(k);
+        Some(SummaryExplanation {
+            chat: best.cache.chat.as_str(),
+            data: best.cache.data.as_ref(),
+            score: best.score,
+            tokens,
+        })
+    }
+
+    /// The `k` tokens with the largest tf-idf mass summed over the window,
+    /// heaviest first, e.g. to draw a word cloud of the current chat.
+    ///
+    /// Each chat contributes its unit-normalised tf-idf vector scaled by the
+    /// same weight it has in summaries.
+    pub fn top_tokens_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        k: usize,
+    ) -> Vec<(&ChatToken, f64)> {
+        let scored = self.scored_vectors(dict);
+        let mut tokens: Vec<_> = token_mass(scored.iter().map(|c| (c.vector.as_slice(), c.weight)))
+            .into_iter()
+            .filter(|(_, mass)| mass.is_finite())
+            .collect();
+        tokens.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        tokens.truncate(k);
+        tokens
+    }
+
+    /// The `k` tokens whose share of chats in the window most exceeds their
+    /// long-horizon rate, as `(token, ln(window_rate / long_rate))`, fastest
+    /// risers first.
+    ///
+    /// `window_rate` is the fraction of window chats containing the token and
+    /// `long_rate` is its decayed dictionary count divided by `L`. Tokens in
+    /// fewer than `min_chats` window chats, or not rising, are left out.
+    /// This does not depend on whether a spike is in progress.
+    pub fn trending_tokens_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        k: usize,
+        min_chats: usize,
+    ) -> Vec<(&ChatToken, f64)> {
+        let mut chats_with = HashMap::<&ChatToken, usize>::new();
+        for c in self.recent_chats.iter() {
+            for token in c.tokens.iter() {
+                *chats_with.entry(token).or_insert(0) += 1;
+            }
+        }
+        let n = self.len() as f64;
+        let mut tokens: Vec<_> = chats_with
+            .into_iter()
+            .filter(|&(_, m)| m >= min_chats.max(1))
+            .map(|(token, m)| {
+                let long_rate = self.token_count(token, dict) / L as f64;
+                (token, (m as f64 / n / long_rate).ln())
+            })
+            .filter(|(_, ratio)| *ratio > 0. && !ratio.is_nan())
+            .collect();
+        tokens.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        tokens.truncate(k);
+        tokens
+    }
+
+    /// How far the window's token distribution has moved from the
+    /// dictionary's, e.g. to tell a topic shift from more of the usual chat
+    /// or to attribute a spike to the tokens behind it.
+    ///
+    /// The window distribution counts token occurrences over its chats.
+    /// The long-horizon one gives each of the window's tokens its decayed
+    /// dictionary count per chat, `(count + smoothing) / L`, with
+    /// `smoothing` keeping new tokens from making the divergence infinite,
+    /// and puts the rest of an average window chat's tokens on all other
+    /// tokens.
+    pub fn token_divergence_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        smoothing: f64,
+    ) -> TokenDivergence<'_> {
+        if self.is_empty() {
+            return TokenDivergence::default();
+        }
+        let mut counts = HashMap::<&ChatToken, f64>::new();
+        for c in self.recent_chats.iter() {
+            for token in c.tokens.iter() {
+                *counts.entry(token).or_insert(0.) += 1.;
+            }
+        }
+        let (tokens, mut window): (Vec<&ChatToken>, Vec<f64>) = counts.into_iter().unzip();
+        let mut long: Vec<f64> = tokens
+            .iter()
+            .map(|token| {
+                let count = self.token_count(token, dict);
+                (count + smoothing) / L as f64
+            })
+            .collect();
+        // Per-chat rates, with one more outcome for every other token.
+        let per_chat = window.iter().sum::<f64>() / self.len() as f64;
+        window.iter_mut().for_each(|n| *n /= self.len() as f64);
+        window.push(0.);
+        long.push((per_chat - long.iter().sum::<f64>()).max(0.));
+        let mut contributions: Vec<_> = tokens.into_iter().zip(kl_terms(&window, &long)).collect();
+        contributions.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        TokenDivergence {
+            kl: contributions.iter().map(|(_, term)| term).sum(),
+            js: js_divergence(&window, &long),
+            contributions,
+        }
+    }
+
+    /// Pick up to `k` summaries by maximal marginal relevance.
+    ///
+    /// Each step selects the chat maximising
+    /// `lambda · centrality / max_centrality − (1 − lambda) · max_sim`,
+    /// where `max_sim` is its highest cosine similarity to an already
+    /// selected chat. `lambda = 1` reduces to the top-`k` by centrality.
+    /// Chats below the minimum summary score are never selected.
+    /// Scores in the result are the raw degree centralities.
+    pub fn summaries_mmr_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        k: usize,
+        lambda: f64,
+    ) -> Vec<(&str, Option<&D>, f64)> {
+        let mut candidates = self.scored_vectors(dict);
+        candidates.retain(|c| c.eligible && c.score >= self.min_summary_score);
+        let max_score = candidates
+            .iter()
+            .map(|c| c.score)
+            .fold(f64::EPSILON, f64::max);
+        let mut selected: Vec<ScoredChat<D>> = Vec::new();
+        while selected.len() < k && !candidates.is_empty() {
+            let (best, _) = candidates
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let max_sim = selected
+                        .iter()
+                        .map(|s| cosine(&c.vector, &s.vector))
+                        .fold(0.0, f64::max);
+                    (i, lambda * c.score / max_score - (1. - lambda) * max_sim)
+                })
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Less))
+                .expect("candidates is not empty");
+            selected.push(candidates.swap_remove(best));
+        }
+        selected
+            .into_iter()
+            .map(|c| (c.cache.chat.as_str(), c.cache.data.as_ref(), c.score))
+            .collect()
+    }
+
+    /// Group the window into topics and return one summary per topic,
+    /// largest topic first.
+    ///
+    /// Chats are clustered by average-linkage agglomeration over the cosine
+    /// similarity of their tf-idf vectors, merging until no two clusters are
+    /// at least `min_similarity` alike. Each topic is represented by its
+    /// member with the highest degree centrality.
+    pub fn topic_summaries_with_dict(
+        &self,
+        dict: &impl Dictionary<K::Key>,
+        min_similarity: f64,
+    ) -> Vec<TopicSummary<'_, D>> {
+        let scored = self.scored_vectors(dict);
+        let n = scored.len();
+        let mut sim = vec![vec![0.; n]; n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let s = cosine(&scored[i].vector, &scored[j].vector);
+                sim[i][j] = s;
+                sim[j][i] = s;
+            }
+        }
+        let mut clusters: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+        loop {
+            let best = (0..n)
+                .filter(|&a| !clusters[a].is_empty())
+                .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+                .filter(|&(_, b)| !clusters[b].is_empty())
+                .max_by(|&(a, b), &(c, d)| sim[a][b].total_cmp(&sim[c][d]));
+            let Some((a, b)) = best.filter(|&(a, b)| sim[a][b] >= min_similarity) else {
+                break;
+            };
+            // Lance-Williams update for average linkage.
+            let (na, nb) = (clusters[a].len() as f64, clusters[b].len() as f64);
+            for k in 0..n {
+                if k != a && k != b && !clusters[k].is_empty() {
+                    let s = (na * sim[a][k] + nb * sim[b][k]) / (na + nb);
+                    sim[a][k] = s;
+                    sim[k][a] = s;
+                }
+            }
+            let merged = std::mem::take(&mut clusters[b]);
+            clusters[a].extend(merged);
+        }
+        let mut topics: Vec<_> = clusters
+            .into_iter()
+            .filter_map(|members| {
+                let size = members.len();
+                let best = members
+                    .into_iter()
+                    .map(|i| &scored[i])
+                    .filter(|c| c.eligible)
+                    .max_by(|a, b| a.score.total_cmp(&b.score))?;
+                Some(TopicSummary {
+                    chat: best.cache.chat.as_str(),
+                    data: best.cache.data.as_ref(),
+                    score: best.score,
+                    size,
+                })
+            })
+            .filter(|t| t.score >= self.min_summary_score)
+            .collect();
+        topics.sort_by(|a, b| b.size.cmp(&a.size).then(b.score.total_cmp(&a.score)));
+        topics
+    }
+
+    /// Unit-normalised tf-idf vector of a chat.
+    fn tfidf<'a>(
+        &self,
+        tokens: &'a [ChatToken],
+        kinds: &[TokenKind],
+        dict: &impl Dictionary<K::Key>,
+    ) -> Vec<(&'a ChatToken, f64)> {
+        let mut weights = Vec::with_capacity(tokens.len());
+        self.tfidf_weights(tokens, kinds, dict, &mut weights);
+        tokens.iter().zip(weights).collect()
+    }
+    /// [`tfidf`](Self::tfidf) weights alone, into `out`.
+    fn tfidf_weights(
+        &self,
+        tokens: &[ChatToken],
+        kinds: &[TokenKind],
+        dict: &impl Dictionary<K::Key>,
+        out: &mut Vec<f64>,
+    ) {
+        let kind_weight = |i: usize| match (self.token_weights, kinds.get(i)) {
+            (Some(weights), Some(&kind)) => weights.of(kind),
+            _ => 1.,
+        };
+        out.clear();
+        out.extend(tokens.iter().enumerate().map(|(i, t)| {
+            let count = self.token_count(t, dict);
+            kind_weight(i) * ((L as f64) / count).ln()
+        }));
+        let norm2: f64 = out.iter().map(|w| w.powi(2)).sum::<f64>().sqrt();
+        out.iter_mut().for_each(|w| *w /= norm2);
+    }
+
+    /// Every recent chat with its tf-idf vector and degree centrality.
+    ///
+    /// With near-duplicate collapsing on, each group of near-identical chats
+    /// is represented once, by its latest member.
+    fn scored_vectors(&self, dict: &impl Dictionary<K::Key>) -> Vec<ScoredChat<'_, D>> {
+        let caches: Vec<_> = self.recent_chats.iter().collect();
+        let n = caches.len();
+        // (index of the representative chat, multiplicity)
+        let nodes: Vec<(usize, usize)> = match self.near_duplicate_distance {
+            None => (0..n).map(|i| (i, 1)).collect(),
+            Some(max_distance) => {
+                // (anchor fingerprint, latest member, multiplicity)
+                let mut groups: Vec<(u64, usize, usize)> = Vec::new();
+                for (i, c) in caches.iter().enumerate() {
+                    match groups
+                        .iter_mut()
+                        .find(|g| (g.0 ^ c.fingerprint).count_ones() <= max_distance)
+                    {
+                        Some(g) => {
+                            g.1 = i;
+                            g.2 += 1;
+                        }
+                        None => groups.push((c.fingerprint, i, 1)),
+                    }
+                }
+                groups.into_iter().map(|(_, i, m)| (i, m)).collect()
+            }
+        };
+        let vectors: Vec<_> = nodes
+            .into_iter()
+            .map(|(i, members)| {
+                let c = caches[i];
+                let m = 1. + (members as f64).ln();
+                let mut w = m * c.weight * (-self.recency_bias * (n - 1 - i) as f64).exp();
+                if self.novelty_boost != 0. && !c.tokens.is_empty() {
+                    let novelty: f64 = c
+                        .tokens
+                        .iter()
+                        .filter_map(|t| t.with_key::<K, _>(|k| dict.novelty(k)))
+                        .sum::<f64>();
+                    w *= 1. + self.novelty_boost * novelty / c.tokens.len() as f64;
+                }
+                let v = if self.incremental {
+                    c.tokens
+                        .iter()
+                        .zip(c.frozen_tfidf.iter().copied())
+                        .collect()
+                } else {
+                    self.tfidf(&c.tokens, &c.kinds, dict)
+                };
+                (c, v, w, members)
+            })
+            .collect();
+        let maintained = self.incremental
+            && self.recency_bias == 0.
+            && self.novelty_boost == 0.
+            && self.near_duplicate_distance.is_none();
+        let uv = (!maintained)
+            .then(|| token_mass(vectors.iter().map(|(_, v, w, _)| (v.as_slice(), *w))));
+        let mass = |t: &ChatToken| match &uv {
+            Some(uv) => uv.get(t).copied().unwrap_or(0.),
+            None => self.token_mass.get(t).copied().unwrap_or(0.),
+        };
+        let mean_len = vectors.iter().map(|(_, v, _, _)| v.len()).sum::<usize>() as f64
+            / vectors.len().max(1) as f64;
+        vectors
+            .into_iter()
+            .map(|(c, v, w, members)| {
+                let length_factor = (mean_len / v.len() as f64).powf(self.length_exponent);
+                let degree_centrality =
+                    length_factor * w * (v.iter().map(|(t, u)| u * mass(t)).sum::<f64>() - w);
+                let degree_centrality = if degree_centrality.is_nan() {
+                    0.0
+                } else {
+                    degree_centrality
+                };
+                ScoredChat {
+                    eligible: self.summary_filter.as_ref().is_none_or(|f| f(&c.chat)),
+                    cache: c,
+                    vector: v,
+                    weight: w,
+                    members,
+                    length_factor,
+                    score: degree_centrality,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Summary chat with the tokens that explain its score.
+#[derive(Clone, Debug)]
+pub struct SummaryExplanation<'a, D> {
+    pub chat: &'a str,
+    pub data: Option<&'a D>,
+    pub score: f64,
+    /// Top tokens, highest contribution first.
+    pub tokens: Vec<TokenContribution<'a>>,
+}
+
+/// Representative chat of one topic cluster.
+#[derive(Clone, Copy, Debug)]
+pub struct TopicSummary<'a, D> {
+    pub chat: &'a str,
+    pub data: Option<&'a D>,
+    pub score: f64,
+    /// Number of window chats in the topic.
+    pub size: usize,
+}
+
+/// A token's share of a summary's degree centrality.
+#[derive(Clone, Copy, Debug)]
+pub struct TokenContribution<'a> {
+    pub token: &'a ChatToken,
+    /// Unit-normalised tf-idf weight within the chat.
+    pub weight: f64,
+    /// Similarity this token adds to the other window chats.
+    pub contribution: f64,
+}
+
+/// A window chat together with its tf-idf vector and degree centrality.
+struct ScoredChat<'a, D> {
+    cache: &'a ChatCache<D>,
+    /// Whether the chat passes the summary filter.
+    eligible: bool,
+    vector: Vec<(&'a ChatToken, f64)>,
+    /// Recency and multiplicity weight of the chat as a graph node.
+    weight: f64,
+    /// Number of near-identical chats the node stands for.
+    members: usize,
+    length_factor: f64,
+    score: f64,
+}
+
+/// Weighted sum of tf-idf vectors, keyed by token.
+fn token_mass<'a: 'b, 'b>(
+    vectors: impl Iterator<Item = (&'b [(&'a ChatToken, f64)], f64)>,
+) -> HashMap<&'a ChatToken, f64> {
+    let mut uv = HashMap::<&ChatToken, f64>::new();
+    for (v, w) in vectors {
+        for &(token, u) in v {
+            let u = w * u;
+            uv.entry(token).and_modify(|v| *v += u).or_insert(u);
+        }
+    }
+    uv
+}
+
+/// Cosine similarity of two unit-normalised sparse vectors.
+fn cosine(a: &[(&ChatToken, f64)], b: &[(&ChatToken, f64)]) -> f64 {
+    let b: HashMap<&ChatToken, f64> = b.iter().copied().collect();
+    let sim: f64 = a.iter().map(|(t, u)| u * b.get(t).unwrap_or(&0.)).sum();
+    if sim.is_nan() { 0.0 } else { sim }
+}
+
+/// Divergence of a window's token distribution from the long horizon, see
+/// [`ChatWindow::token_divergence_with_dict`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenDivergence<'a> {
+    /// `KL(window ‖ long horizon)` in nats.
+    pub kl: f64,
+    /// Jensen-Shannon divergence in nats, at most `ln 2`.
+    pub js: f64,
+    /// Each token's term of `kl`, largest first: the tokens driving the
+    /// shift.
+    pub contributions: Vec<(&'a ChatToken, f64)>,
+}
+
+/// Picks the chat that best represents the current window.
+///
+/// `R` is the ring the window keeps its chats in, see
+/// [`ChatWindow::with_ring`].
+pub trait Summarizer<const S: usize, const L: usize, D, R = Ring<ChatCache<D>, S>> {
+    /// Return `(chat_text, Option<data>, score)` for the chosen chat.
+    fn summarize<'a>(
+        &self,
+        window: &'a ChatWindow<S, L, D, TextKey, R>,
+        dict: &impl Dictionary,
+    ) -> Option<(&'a str, Option<&'a D>, f64)>;
+}
+
+/// Default summarizer: the chat with the highest tf-idf degree centrality.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DegreeCentrality;
+
+impl<const S: usize, const L: usize, D, R: RingBuffer<ChatCache<D>>> Summarizer<S, L, D, R>
+    for DegreeCentrality
+{
+    fn summarize<'a>(
+        &self,
+        window: &'a ChatWindow<S, L, D, TextKey, R>,
+        dict: &impl Dictionary,
+    ) -> Option<(&'a str, Option<&'a D>, f64)> {
+        window.summary_with_dict(dict)
+    }
+}
+
+/// Combines timestamp-based burst detection with content-based summaries.
+///
+/// Chats are summarized from a [`ChatWindow`] keeping them in `R`, a
+/// `Ring` of `S` by default; see [`with_window`](Self::with_window).
+pub struct ChatSpikeDetector<
+    const S: usize,
+    const L: usize,
+    D = (),
+    Sm = DegreeCentrality,
+    Dc = MemoryDictionary<L>,
+    R = Ring<ChatCache<D>, S>,
+> {
+    spike: SpikeDetector<S, L>,
+    recent_chats: ChatWindow<S, L, D, TextKey, R>,
+    dict: Dc,
+    summarizer: Sm,
+    filters: Vec<Box<dyn MessageFilter>>,
+    reorder: Option<ReorderBuffer<D>>,
+    /// Whether per-chat weights also scale summary scoring.
+    weighted_summaries: bool,
+    transcript: Option<Transcript<D>>,
+    last_push: Option<Result<PushOutcome, NonMonotonicTimestamp>>,
+}
+
+impl<const S: usize, const L: usize, D, Sm, Dc, R> Default for ChatSpikeDetector<S, L, D, Sm, Dc, R>
+where
+    Sm: Default,
+    Dc: Default,
+    R: RingBuffer<ChatCache<D>> + Default,
+{
+    fn default() -> Self {
+        Self {
+            spike: SpikeDetector::default(),
+            recent_chats: ChatWindow::default(),
+            dict: Dc::default(),
+            summarizer: Sm::default(),
+            filters: Vec::new(),
+            reorder: None,
+            weighted_summaries: false,
+            transcript: None,
+            last_push: None,
+        }
+    }
+}
+
+/// Holds chats back for a short lag so they can be fed in timestamp order.
+struct ReorderBuffer<D> {
+    window: Duration,
+    latest_ts: Option<Instant>,
+    pending: VecDeque<Pending<D>>,
+}
+
+/// A chat held for reordering: text, timestamp, author and data.
+type Pending<D> = (String, Instant, Option<String>, Option<D>);
+
+impl<D> ReorderBuffer<D> {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            latest_ts: None,
+            pending: VecDeque::new(),
+        }
+    }
+    fn insert(&mut self, chat: String, ts: Instant, author: Option<String>, data: Option<D>) {
+        // Equal timestamps keep their arrival order.
+        let pos = self.pending.partition_point(|(_, t, _, _)| *t <= ts);
+        self.pending.insert(pos, (chat, ts, author, data));
+        self.latest_ts = Some(self.latest_ts.map_or(ts, |l| l.max(ts)));
+    }
+    /// Pop the oldest chat once it is at least `window` behind the newest one.
+    fn pop_ready(&mut self) -> Option<Pending<D>> {
+        self.pop_ready_at(self.latest_ts?)
+    }
+    /// Pop the oldest chat once it is at least `window` behind `latest`.
+    fn pop_ready_at(&mut self, latest: Instant) -> Option<Pending<D>> {
+        match self.pending.front() {
+            Some((_, ts, _, _)) if latest.duration_since(*ts) >= self.window => {
+                self.pending.pop_front()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Raw chats recorded over one spike, from the chat that began it to the
+/// chat that ended it.
+#[derive(Clone, Debug)]
+pub struct SpikeReport<D> {
+    pub begin_ts: Instant,
+    pub end_ts: Instant,
+    /// Highest surprise observed during the spike.
+    pub peak_surprise: f64,
+    pub chats: Vec<TranscriptChat<D>>,
+    /// Chats left out once the transcript reached its capacity.
+    pub dropped: usize,
+}
+
+/// One chat line of a [`SpikeReport`], as it was fed to the detector.
+#[derive(Clone, Debug)]
+pub struct TranscriptChat<D> {
+    pub chat: String,
+    pub author: Option<String>,
+    pub ts: Instant,
+    pub data: Option<D>,
+}
+
+struct Transcript<D> {
+    max_chats: usize,
+    /// Finished reports kept until taken, oldest dropped first.
+    max_reports: usize,
+    clone_data: fn(&D) -> D,
+    current: Option<SpikeReport<D>>,
+    finished: VecDeque<SpikeReport<D>>,
+}
+
+impl<D> Transcript<D> {
+    fn finish(&mut self) {
+        let Some(report) = self.current.take() else {
+            return;
+        };
+        if self.finished.len() >= self.max_reports {
+            self.finished.pop_front();
+        }
+        self.finished.push_back(report);
+    }
+    fn record(
+        &mut self,
+        event: SpikeEvent,
+        surprise: f64,
+        chat: &str,
+        author: Option<&str>,
+        ts: Instant,
+        data: Option<&D>,
+    ) {
+        if let SpikeEvent::Begin { .. } = event {
+            self.current = Some(SpikeReport {
+                begin_ts: ts,
+                end_ts: ts,
+                peak_surprise: surprise,
+                chats: Vec::new(),
+                dropped: 0,
+            });
+        }
+        let Some(report) = self.current.as_mut() else {
+            return;
+        };
+        report.end_ts = ts;
+        report.peak_surprise = report.peak_surprise.max(surprise);
+        if report.chats.len() < self.max_chats {
+            report.chats.push(TranscriptChat {
+                chat: chat.to_owned(),
+                author: author.map(str::to_owned),
+                ts,
+                data: data.map(self.clone_data),
+            });
+        } else {
+            report.dropped += 1;
+        }
+        if let SpikeEvent::End { .. } = event {
+            self.finish();
+        }
+    }
+}
+
+/// High-level event emitted by `ChatSpikeDetector`.
+#[derive(Clone, Copy, Default, Debug)]
+pub enum Event<'a, D> {
+    #[default]
+    None,
+    SpikeBegin {
+        summary: Option<&'a str>,
+        data: Option<&'a D>,
+        surprise: f64,
+    },
+    SpikeEnd {
+        summary: Option<&'a str>,
+        data: Option<&'a D>,
+        surprise: f64,
+    },
+}
+
+/// Owned counterpart of [`Event`] that does not borrow the detector.
+#[derive(Clone, Default, Debug)]
+pub enum OwnedEvent<D> {
+    #[default]
+    None,
+    SpikeBegin {
+        summary: Option<String>,
+        data: Option<D>,
+        surprise: f64,
+    },
+    SpikeEnd {
+        summary: Option<String>,
+        data: Option<D>,
+        surprise: f64,
+    },
+}
+
+impl<D: Clone> From<Event<'_, D>> for OwnedEvent<D> {
+    fn from(event: Event<'_, D>) -> Self {
+        match event {
+            Event::None => OwnedEvent::None,
+            Event::SpikeBegin {
+                summary,
+                data,
+                surprise,
+            } => OwnedEvent::SpikeBegin {
+                summary: summary.map(str::to_owned),
+                data: data.cloned(),
+                surprise,
+            },
+            Event::SpikeEnd {
+                summary,
+                data,
+                surprise,
+            } => OwnedEvent::SpikeEnd {
+                summary: summary.map(str::to_owned),
+                data: data.cloned(),
+                surprise,
+            },
+        }
+    }
+}
+
+impl<
+    const S: usize,
+    const L: usize,
+    D,
+    Sm: Summarizer<S, L, D, R>,
+    Dc: Dictionary,
+    R: RingBuffer<ChatCache<D>>,
+> ChatSpikeDetector<S, L, D, Sm, Dc, R>
+{
+    pub fn with_ngram_range(mut self, min: usize, max: usize) -> Self {
+        self.recent_chats = self.recent_chats.with_ngram_range(min, max);
+        self
+    }
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + Send + Sync + 'static) -> Self {
+        self.recent_chats = self.recent_chats.with_tokenizer(tokenizer);
+        self
+    }
+    pub fn with_word_tokens(mut self, range: RangeInclusive<usize>) -> Self {
+        self.recent_chats = self.recent_chats.with_word_tokens(range);
+        self
+    }
+    #[cfg(feature = "unicode-normalization")]
+    pub fn with_unicode_form(mut self, form: UnicodeForm) -> Self {
+        self.recent_chats = self.recent_chats.with_unicode_form(form);
+        self
+    }
+    pub fn with_interjections(mut self, interjections: Interjections) -> Self {
+        self.recent_chats = self.recent_chats.with_interjections(interjections);
+        self
+    }
+    pub fn with_normalizer(mut self, pipeline: NormalizerPipeline) -> Self {
+        self.recent_chats = self.recent_chats.with_normalizer(pipeline);
+        self
+    }
+    pub fn with_stopwords(mut self, stopwords: Stopwords) -> Self {
+        self.recent_chats = self.recent_chats.with_stopwords(stopwords);
+        self
+    }
+    /// Emit `summary: None` when the best centrality is below `min_score`.
+    pub fn with_min_summary_score(mut self, min_score: f64) -> Self {
+        self.recent_chats = self.recent_chats.with_min_summary_score(min_score);
+        self
+    }
+    /// Restrict which chats may be reported as summaries; see
+    /// [`ChatWindow::with_summary_filter`].
+    pub fn with_summary_filter(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.recent_chats = self.recent_chats.with_summary_filter(predicate);
+        self
+    }
+    /// Favour recent chats in summaries; see [`ChatWindow::with_recency_bias`].
+    pub fn with_recency_bias(mut self, alpha: f64) -> Self {
+        self.recent_chats = self.recent_chats.with_recency_bias(alpha);
+        self
+    }
+    /// Collapse near-duplicate chats in summaries; see
+    /// [`ChatWindow::with_near_duplicate_collapsing`].
+    pub fn with_near_duplicate_collapsing(mut self, max_distance: u32) -> Self {
+        self.recent_chats = self
+            .recent_chats
+            .with_near_duplicate_collapsing(max_distance);
+        self
+    }
+    /// Dampen the advantage of long chats; see
+    /// [`ChatWindow::with_length_normalization`].
+    pub fn with_length_normalization(mut self, exponent: f64) -> Self {
+        self.recent_chats = self.recent_chats.with_length_normalization(exponent);
+        self
+    }
+    /// Maintain summary centrality incrementally; see
+    /// [`ChatWindow::with_incremental_centrality`].
+    pub fn with_incremental_centrality(mut self) -> Self {
+        self.recent_chats = self.recent_chats.with_incremental_centrality();
+        self
+    }
+    /// See [`ChatWindow::with_exact_centrality`].
+    pub fn with_exact_centrality(mut self) -> Self {
+        self.recent_chats = self.recent_chats.with_exact_centrality();
+        self
+    }
+    /// Keep only chats from the last `max_age` in summaries; see
+    /// [`ChatWindow::with_max_age`].
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.recent_chats = self.recent_chats.with_max_age(max_age);
+        self
+    }
+    /// Summarize at most the last `max_len` chats; see
+    /// [`ChatWindow::with_max_len`].
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.recent_chats = self.recent_chats.with_max_len(max_len);
+        self
+    }
+    /// Down-weight authors repeating themselves, in both burst statistics
+    /// and summaries. Applies to chats fed with
+    /// [`update_and_detect_with_author`](Self::update_and_detect_with_author).
+    pub fn with_author_policy(mut self, policy: AuthorPolicy) -> Self {
+        self.recent_chats = self.recent_chats.with_author_policy(policy);
+        self
+    }
+    pub fn with_token_weights(mut self, weights: TokenWeights) -> Self {
+        self.recent_chats = self.recent_chats.with_token_weights(weights);
+        self
+    }
+    /// See [`ChatWindow::with_novelty_boost`]; pair it with a dictionary
+    /// that tracks novelty via [`with_dictionary`](Self::with_dictionary).
+    pub fn with_novelty_boost(mut self, beta: f64) -> Self {
+        self.recent_chats = self.recent_chats.with_novelty_boost(beta);
+        self
+    }
+    pub fn with_threshold(mut self, start_t: f64, end_t: f64) -> Self {
+        self.spike = self.spike.with_threshold(start_t, end_t);
+        self
+    }
+    /// Drop chats rejected by `filter` before they reach the detector.
+    /// Filters are applied in the order they were added.
+    pub fn with_filter(mut self, filter: impl MessageFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+    /// Replace the summarizer used for spike events.
+    pub fn with_summarizer<T: Summarizer<S, L, D, R>>(
+        self,
+        summarizer: T,
+    ) -> ChatSpikeDetector<S, L, D, T, Dc, R> {
+        ChatSpikeDetector {
+            spike: self.spike,
+            recent_chats: self.recent_chats,
+            dict: self.dict,
+            summarizer,
+            filters: self.filters,
+            reorder: self.reorder,
+            weighted_summaries: self.weighted_summaries,
+            transcript: self.transcript,
+            last_push: self.last_push,
+        }
+    }
+    /// Replace the token dictionary, e.g. with a
+    /// [`HashedDictionary`](crate::dict::HashedDictionary) or a persistent
+    /// one. Must be called before any chat is fed.
+    pub fn with_dictionary<T: Dictionary>(self, dict: T) -> ChatSpikeDetector<S, L, D, Sm, T, R> {
+        ChatSpikeDetector {
+            spike: self.spike,
+            recent_chats: self.recent_chats,
+            dict,
+            summarizer: self.summarizer,
+            filters: self.filters,
+            reorder: self.reorder,
+            weighted_summaries: self.weighted_summaries,
+            transcript: self.transcript,
+            last_push: self.last_push,
+        }
+    }
+    /// Replace the chat window, e.g. with one keeping its chats in a
+    /// [`DynRing`](crate::ring::DynRing) sized at runtime. Window settings
+    /// made through the detector so far are replaced as well, so call this
+    /// first. Must be called before any chat is fed.
+    pub fn with_window<T: RingBuffer<ChatCache<D>>>(
+        self,
+        window: ChatWindow<S, L, D, TextKey, T>,
+    ) -> ChatSpikeDetector<S, L, D, Sm, Dc, T> {
+        ChatSpikeDetector {
+            spike: self.spike,
+            recent_chats: window,
+            dict: self.dict,
+            summarizer: self.summarizer,
+            filters: self.filters,
+            reorder: self.reorder,
+            weighted_summaries: self.weighted_summaries,
+            transcript: self.transcript,
+            last_push: self.last_push,
+        }
+    }
+    /// How out-of-order timestamps are handled. Chats whose timestamp is
+    /// skipped or rejected are not added to the window either.
+    pub fn with_clock_policy(mut self, policy: ClockPolicy) -> Self {
+        self.spike = self.spike.with_clock_policy(policy);
+        self
+    }
+    /// See [`SpikeDetector::with_model`].
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.spike = self.spike.with_model(model);
+        self
+    }
+    /// See [`SpikeDetector::with_tail_accuracy`].
+    pub fn with_tail_accuracy(mut self, accuracy: TailAccuracy) -> Self {
+        self.spike = self.spike.with_tail_accuracy(accuracy);
+        self
+    }
+    /// See [`SpikeDetector::with_surprise_quantile`].
+    pub fn with_surprise_quantile(mut self, p: f64, window: Duration) -> Self {
+        self.spike = self.spike.with_surprise_quantile(p, window);
+        self
+    }
+    /// Buffer chats for up to `window` and feed them in timestamp order.
+    ///
+    /// Only [`enqueue`](Self::enqueue), [`update_many`](Self::update_many)
+    /// and [`flush`](Self::flush) go through the buffer; `update_and_detect`
+    /// always feeds the detector directly.
+    pub fn with_reorder_window(mut self, window: Duration) -> Self {
+        self.reorder = Some(ReorderBuffer::new(window));
+        self
+    }
+    /// Let the weights given to
+    /// [`update_and_detect_weighted`](Self::update_and_detect_weighted) also
+    /// scale the chat's say in summaries, not only the burst statistics.
+    pub fn with_weighted_summaries(mut self) -> Self {
+        self.weighted_summaries = true;
+        self
+    }
+    /// Record the raw chats of every spike, keeping at most `max_chats` per
+    /// spike. Finished spikes are collected with
+    /// [`take_reports`](Self::take_reports).
+    pub fn with_transcript(mut self, max_chats: usize) -> Self
+    where
+        D: Clone,
+    {
+        self.transcript = Some(Transcript {
+            max_chats,
+            max_reports: 64,
+            clone_data: D::clone,
+            current: None,
+            finished: VecDeque::new(),
+        });
+        self
+    }
+    /// Keep at most `max_reports` finished spikes awaiting
+    /// [`take_reports`](Self::take_reports), dropping the oldest beyond
+    /// that. Defaults to 64; has no effect before
+    /// [`with_transcript`](Self::with_transcript).
+    pub fn with_max_reports(mut self, max_reports: usize) -> Self {
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.max_reports = max_reports.max(1);
+        }
+        self
+    }
+
+    /// Add a chat message and return an event when a spike starts or ends.
+    pub fn update_and_detect(&mut self, chat: String, ts: Instant) -> Event<'_, D> {
+        self.update_and_detect_with_data(chat, ts, None)
+    }
+    pub fn update_and_detect_with_data(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        data: Option<D>,
+    ) -> Event<'_, D> {
+        self.detect(chat, ts, None, 1., data)
+    }
+    /// Add a chat counting as `weight` chats in the burst statistics, e.g.
+    /// scaled by cheered bits or a subscription tier. A non-positive or
+    /// non-finite weight leaves the burst statistics untouched, and a
+    /// non-finite one counts as zero in weighted summaries.
+    pub fn update_and_detect_weighted(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        weight: f64,
+        data: Option<D>,
+    ) -> Event<'_, D> {
+        self.detect(chat, ts, None, weight, data)
+    }
+    /// Add a chat from `author`, weighted by the author policy in both the
+    /// burst statistics and the summary.
+    pub fn update_and_detect_with_author(
+        &mut self,
+        chat: String,
+        author: String,
+        ts: Instant,
+        data: Option<D>,
+    ) -> Event<'_, D> {
+        self.detect(chat, ts, Some(author), 1., data)
+    }
+    fn detect(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        author: Option<String>,
+        chat_weight: f64,
+        data: Option<D>,
+    ) -> Event<'_, D> {
+        let event = self.observe(chat, ts, author, chat_weight, data);
+        self.summarize(event)
+    }
+    /// Feed a chat to the burst statistics, transcript and window without
+    /// summarizing.
+    fn observe(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        author: Option<String>,
+        chat_weight: f64,
+        data: Option<D>,
+    ) -> SpikeEvent {
+        let chat_weight = if chat_weight.is_finite() {
+            chat_weight
+        } else {
+            0.
+        };
+        if !self
+            .filters
+            .iter()
+            .all(|f| f.accept(&chat, author.as_deref()))
+        {
+            self.last_push = None;
+            return SpikeEvent::None;
+        }
+        let weight = author
+            .as_deref()
+            .map_or(1., |a| self.recent_chats.author_weight(a));
+        let pushed = self.spike.try_push_weighted(ts, weight * chat_weight);
+        self.last_push = Some(pushed);
+        let event = match pushed {
+            Ok(PushOutcome::Skipped) | Err(_) => return SpikeEvent::None,
+            Ok(outcome) => outcome.event(),
+        };
+        let weight = if self.weighted_summaries {
+            weight * chat_weight
+        } else {
+            weight
+        };
+        if let Some(transcript) = self.transcript.as_mut() {
+            let surprise = self.spike.current_surprise();
+            transcript.record(event, surprise, &chat, author.as_deref(), ts, data.as_ref());
+        }
+        self.recent_chats
+            .insert(chat, Some(ts), author, weight, data, &mut self.dict);
+        event
+    }
+    fn summarize(&self, event: SpikeEvent) -> Event<'_, D> {
+        match event {
+            SpikeEvent::Begin { surprise } => {
+                let summary = self.summarizer.summarize(&self.recent_chats, &self.dict);
+                Event::SpikeBegin {
+                    summary: summary.map(|s| s.0),
+                    data: summary.and_then(|s| s.1),
+                    surprise,
+                }
+            }
+            SpikeEvent::End { surprise } => {
+                let summary = self.summarizer.summarize(&self.recent_chats, &self.dict);
+                Event::SpikeEnd {
+                    summary: summary.map(|s| s.0),
+                    data: summary.and_then(|s| s.1),
+                    surprise,
+                }
+            }
+            _ => Event::None,
+        }
+    }
+
+    /// Feed a batch of chats and collect the spike events they produce.
+    ///
+    /// Dictionary housekeeping such as vacuuming runs once at the end of the
+    /// batch, so backfilling a long log costs little more than the token
+    /// bookkeeping. Each event is summarized as it is raised, as with
+    /// [`update_and_detect`](Self::update_and_detect). Chats that do not
+    /// start or end a spike yield no entry in the returned vector.
+    pub fn update_many(
+        &mut self,
+        chats: impl IntoIterator<Item = (String, Instant)>,
+    ) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        let mut events = Vec::new();
+        self.dict.begin_batch();
+        for (chat, ts) in chats {
+            let Some(reorder) = self.reorder.as_mut() else {
+                self.feed_into(chat, ts, None, None, &mut events);
+                continue;
+            };
+            reorder.insert(chat, ts, None, None);
+            while let Some((chat, ts, author, data)) =
+                self.reorder.as_mut().and_then(ReorderBuffer::pop_ready)
+            {
+                self.feed_into(chat, ts, author, data, &mut events);
+            }
+        }
+        self.dict.end_batch();
+        events
+    }
+
+    /// Add a chat through the reorder buffer, returning the events raised by
+    /// the chats released from it.
+    ///
+    /// Without a reorder window the chat is fed immediately.
+    pub fn enqueue(&mut self, chat: String, ts: Instant, data: Option<D>) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        self.enqueue_as(chat, ts, None, data)
+    }
+    /// Like [`enqueue`](Self::enqueue), weighing the chat by its author once
+    /// released, see
+    /// [`update_and_detect_with_author`](Self::update_and_detect_with_author).
+    pub fn enqueue_with_author(
+        &mut self,
+        chat: String,
+        author: String,
+        ts: Instant,
+        data: Option<D>,
+    ) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        self.enqueue_as(chat, ts, Some(author), data)
+    }
+    fn enqueue_as(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        author: Option<String>,
+        data: Option<D>,
+    ) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        let mut events = Vec::new();
+        let Some(reorder) = self.reorder.as_mut() else {
+            self.feed_into(chat, ts, author, data, &mut events);
+            return events;
+        };
+        reorder.insert(chat, ts, author, data);
+        while let Some((chat, ts, author, data)) =
+            self.reorder.as_mut().and_then(ReorderBuffer::pop_ready)
+        {
+            self.feed_into(chat, ts, author, data, &mut events);
+        }
+        events
+    }
+
+    /// Feed every chat still held in the reorder buffer.
+    pub fn flush(&mut self) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        let pending = self
+            .reorder
+            .as_mut()
+            .map(|r| std::mem::take(&mut r.pending))
+            .unwrap_or_default();
+        let mut events = Vec::new();
+        for (chat, ts, author, data) in pending {
+            self.feed_into(chat, ts, author, data, &mut events);
+        }
+        events
+    }
+
+    /// Handle the passage of time without a chat, e.g. from a periodic
+    /// timer: release the reorder buffer's chats that are at least its window
+    /// older than `now`, then end a spike that went quiet, see
+    /// [`SpikeDetector::tick`]. The end event summarizes the window as it is.
+    pub fn tick(&mut self, now: Instant) -> Vec<OwnedEvent<D>>
+    where
+        D: Clone,
+    {
+        let mut events = Vec::new();
+        while let Some((chat, ts, author, data)) =
+            self.reorder.as_mut().and_then(|r| r.pop_ready_at(now))
+        {
+            self.feed_into(chat, ts, author, data, &mut events);
+        }
+        if self.reorder.as_ref().is_some_and(|r| !r.pending.is_empty()) {
+            return events;
+        }
+        if let SpikeEvent::End { surprise } = self.spike.tick(now) {
+            if let Some(transcript) = self.transcript.as_mut() {
+                transcript.finish();
+            }
+            let summary = self.summarizer.summarize(&self.recent_chats, &self.dict);
+            events.push(OwnedEvent::SpikeEnd {
+                summary: summary.map(|s| s.0.to_owned()),
+                data: summary.and_then(|s| s.1.cloned()),
+                surprise,
+            });
+        }
+        events
+    }
+
+    fn feed_into(
+        &mut self,
+        chat: String,
+        ts: Instant,
+        author: Option<String>,
+        data: Option<D>,
+        events: &mut Vec<OwnedEvent<D>>,
+    ) where
+        D: Clone,
+    {
+        match self.detect(chat, ts, author, 1., data) {
+            Event::None => {}
+            event => events.push(event.into()),
+        }
+    }
+
+    pub fn current_surprise(&self) -> f64 {
+        self.spike.current_surprise()
+    }
+    /// See [`SpikeDetector::surprise_quantile`].
+    pub fn surprise_quantile(&self) -> Option<f64> {
+        self.spike.surprise_quantile()
+    }
+    /// Transcripts of the spikes that ended since the last call. Empty
+    /// unless enabled with [`with_transcript`](Self::with_transcript).
+    pub fn take_reports(&mut self) -> Vec<SpikeReport<D>> {
+        self.transcript
+            .as_mut()
+            .map(|t| t.finished.drain(..).collect())
+            .unwrap_or_default()
+    }
+    /// Spike currently being recorded, if any.
+    pub fn current_report(&self) -> Option<&SpikeReport<D>> {
+        self.transcript.as_ref()?.current.as_ref()
+    }
+    /// Remove the most recent chat whose text matches `chat` (after
+    /// normalization) from the window and dictionary, e.g. after a moderator
+    /// deleted it. Returns whether a chat was removed.
+    ///
+    /// The chat still counts toward the burst statistics.
+    pub fn retract_last_matching(&mut self, chat: &str) -> bool {
+        let chat = self.recent_chats.normalize(chat);
+        let Some(idx) = self
+            .recent_chats
+            .chat_caches()
+            .filter(|c| c.chat == chat)
+            .map(|c| c.idx)
+            .last()
+        else {
+            return false;
+        };
+        !self
+            .recent_chats
+            .retract_with_dict(|c| c.idx == idx, &mut self.dict)
+            .is_empty()
+    }
+    /// Drop every chat from the window, e.g. on a scene change. Burst
+    /// statistics and the dictionary are kept.
+    pub fn clear_window(&mut self) {
+        self.recent_chats.clear();
+    }
+    /// Drop window chats received before `ts`.
+    pub fn truncate_window_older_than(&mut self, ts: Instant) {
+        self.recent_chats.truncate_older_than(ts);
+    }
+    /// The window of recent chats used for summaries.
+    pub fn window(&self) -> &ChatWindow<S, L, D, TextKey, R> {
+        &self.recent_chats
+    }
+    pub fn dictionary(&self) -> &Dc {
+        &self.dict
+    }
+    pub fn current_phase(&self) -> Phase {
+        self.spike.phase
+    }
+    pub fn last_updated_at(&self) -> Option<Instant> {
+        self.spike.last_ts
+    }
+    /// How the burst statistics took the last chat fed, e.g. to notice a
+    /// chat dropped by the [`ClockPolicy`]. `None` before the first chat
+    /// and when a message filter dropped the last one.
+    pub fn last_push_outcome(&self) -> Option<Result<PushOutcome, NonMonotonicTimestamp>> {
+        self.last_push
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict::HashedDictionary;
+
+    #[test]
+    fn spike_detector_triggers_begin() {
+        let mut sd = SpikeDetector::<1, 2>::default().with_threshold(0.0, f64::INFINITY); // extremely low threshold
+
+        // First event should start a spike immediately.
+        let now = Instant::now();
+        assert!(matches!(sd.push(now), SpikeEvent::Begin { .. }));
+        assert!(matches!(sd.phase, Phase::InSpike));
+    }
+
+    #[test]
+    fn spike_detector_tail_accuracy() {
+        let accuracies = [
+            TailAccuracy::Fast,
+            TailAccuracy::Balanced,
+            TailAccuracy::Exact,
+        ];
+        let mut dets = accuracies.map(|a| SpikeDetector::<5, 50>::default().with_tail_accuracy(a));
+        let mut ts = Instant::now();
+        for i in 0..100 {
+            ts += Duration::from_millis(if i < 95 { 1000 } else { 300 });
+            dets.iter_mut().for_each(|d| _ = d.push(ts));
+        }
+        let [fast, balanced, exact] = dets.map(|d| d.current_surprise());
+        // λ is small here, where only the fast mode approximates.
+        assert_eq!(balanced, exact);
+        assert_ne!(fast, exact);
+        assert!((fast - exact).abs() < 0.5, "{fast} vs {exact}");
+    }
+
+    #[test]
+    fn spike_detector_threshold_from_p() {
+        use crate::math::threshold_from_p;
+        let mut sd = SpikeDetector::<1, 2>::default().with_threshold(threshold_from_p(1.), 0.);
+        let event = sd.push(Instant::now());
+        assert!(event.surprise().unwrap().p_value() < 1.);
+        assert_eq!(SpikeEvent::None.surprise(), None);
+    }
+
+    #[test]
+    fn spike_detector_degenerate_rates() {
+        let mut sd = SpikeDetector::<5, 50>::default().with_threshold(-1., -2.);
+        assert_eq!(sd.try_current_surprise(), None);
+        assert_eq!(sd.current_surprise(), 0.);
+        sd.dur_l = f64::INFINITY;
+        assert_eq!(sd.try_current_surprise(), None);
+        assert!(matches!(sd.push(Instant::now()), SpikeEvent::None));
+        assert!(matches!(sd.phase, Phase::Idle));
+    }
+
+    #[test]
+    fn spike_detector_clock_policy() {
+        let t0 = Instant::now();
+        let t1 = t0 + std::time::Duration::from_secs(1);
+
+        let mut sd = SpikeDetector::<1, 2>::default();
+        sd.push(t1);
+        assert!(matches!(sd.try_push(t0), Ok(PushOutcome::Clamped(_))));
+        assert_eq!(sd.last_ts, Some(t1));
+
+        let mut sd = SpikeDetector::<1, 2>::default().with_clock_policy(ClockPolicy::Skip);
+        sd.push(t1);
+        let dur_l = sd.dur_l;
+        assert!(matches!(sd.try_push(t0), Ok(PushOutcome::Skipped)));
+        assert_eq!(sd.dur_l, dur_l);
+
+        let mut sd = SpikeDetector::<1, 2>::default().with_clock_policy(ClockPolicy::Reject);
+        sd.push(t1);
+        assert_eq!(
+            sd.try_push(t0).unwrap_err(),
+            NonMonotonicTimestamp {
+                last_ts: t1,
+                ts: t0
+            }
+        );
+    }
+
+    #[test]
+    fn chat_window_summary_nonempty() {
+        let mut cw = ChatWindow::<3, 12>::default();
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("some noises".into(), &mut dict);
+        let summary = cw.summary_with_dict(&dict);
+        assert!(summary.is_some());
+        assert_eq!(summary.unwrap().0, "hello world");
+    }
+
+    #[test]
+    fn chat_window_summary_with_data() {
+        let mut cw = ChatWindow::<3, 12, usize>::default();
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_data_and_dict("hello world".into(), Some(1), &mut dict);
+        cw.push_with_data_and_dict("hello world".into(), Some(2), &mut dict);
+        cw.push_with_data_and_dict("some noises".into(), Some(3), &mut dict);
+        let summary = cw.summary_with_dict(&dict);
+        assert!(summary.is_some());
+        assert_eq!(summary.unwrap().0, "hello world");
+        assert_eq!(summary.unwrap().1, Some(&2));
+    }
+
+    #[test]
+    fn chat_window_own_dictionary() {
+        let mut cw = ChatWindow::<3, 12, usize>::default();
+        cw.push_with_data("hello world".into(), Some(1));
+        cw.push_with_data("hello world".into(), Some(2));
+        cw.push("some noises".into());
+        let summary = cw.summary().unwrap();
+        assert_eq!((summary.0, summary.1), ("hello world", Some(&2)));
+        assert!(cw.dict.count("hell", cw.last_chat_idx()) > 1.);
+    }
+
+    #[test]
+    fn chat_window_mmr_picks_distinct_topics() {
+        let mut cw = ChatWindow::<6, 24>::default();
+        let mut dict = MemoryDictionary::<24>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("what a play".into(), &mut dict);
+        cw.push_with_dict("what a play".into(), &mut dict);
+        cw.push_with_dict("zzz".into(), &mut dict);
+        let summaries = cw.summaries_mmr_with_dict(&dict, 2, 0.5);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].0, "hello world");
+        assert_eq!(summaries[1].0, "what a play");
+    }
 
     #[test]
-    fn spike_detector_triggers_begin() {
-        let mut sd = SpikeDetector::<1, 2>::default().with_threshold(0.0, f64::INFINITY); // extremely low threshold
+    fn chat_window_min_summary_score() {
+        let mut cw = ChatWindow::<3, 12>::default().with_min_summary_score(0.5);
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("abc".into(), &mut dict);
+        cw.push_with_dict("xyz".into(), &mut dict);
+        assert!(cw.summary_with_dict(&dict).is_none());
+        cw.push_with_dict("abc".into(), &mut dict);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "abc");
+    }
 
-        // First event should start a spike immediately.
-        let now = Instant::now();
-        assert!(matches!(sd.push(now), SpikeEvent::Begin { .. }));
-        assert!(matches!(sd.phase, Phase::InSpike));
+    #[test]
+    fn chat_window_recency_bias() {
+        let mut cw = ChatWindow::<4, 16>::default().with_exact_centrality();
+        let mut dict = MemoryDictionary::<16>::default();
+        cw.push_with_dict("old news".into(), &mut dict);
+        cw.push_with_dict("old news".into(), &mut dict);
+        cw.push_with_dict("fresh take".into(), &mut dict);
+        cw.push_with_dict("fresh take".into(), &mut dict);
+        let unbiased = cw.scored_chats_with_dict(&dict);
+        assert!((unbiased[0].2 - unbiased[2].2).abs() < 1e-9);
+        let cw = cw.with_recency_bias(1.0);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "fresh take");
     }
 
     #[test]
-    fn chat_window_summary_nonempty() {
+    fn chat_window_topic_summaries() {
+        let mut cw = ChatWindow::<6, 24>::default();
+        let mut dict = MemoryDictionary::<24>::default();
+        cw.push_with_dict("lets go".into(), &mut dict);
+        cw.push_with_dict("lets go".into(), &mut dict);
+        cw.push_with_dict("lets goooo".into(), &mut dict);
+        cw.push_with_dict("thanks for the sub".into(), &mut dict);
+        cw.push_with_dict("thanks for the sub".into(), &mut dict);
+        let topics = cw.topic_summaries_with_dict(&dict, 0.5);
+        assert_eq!(topics.len(), 2);
+        assert_eq!(topics[0].chat, "lets go");
+        assert_eq!(topics[0].size, 3);
+        assert_eq!(topics[1].chat, "thanks for the sub");
+        assert_eq!(topics[1].size, 2);
+    }
+
+    #[test]
+    fn chat_window_near_duplicate_collapsing() {
+        let mut cw = ChatWindow::<8, 32>::default();
+        let mut dict = MemoryDictionary::<32>::default();
+        for _ in 0..4 {
+            cw.push_with_dict("copy pasta spam line".into(), &mut dict);
+        }
+        cw.push_with_dict("what a goal".into(), &mut dict);
+        cw.push_with_dict("what a goal!".into(), &mut dict);
+        assert_eq!(cw.scored_chats_with_dict(&dict).len(), 6);
+        let cw = cw.with_near_duplicate_collapsing(0);
+        let scored = cw.scored_chats_with_dict(&dict);
+        assert_eq!(scored.len(), 3);
+        assert_eq!(scored[0].0, "copy pasta spam line");
+        let sizes: Vec<_> = cw
+            .scored_clusters_with_dict(&dict)
+            .iter()
+            .map(|c| c.3)
+            .collect();
+        assert_eq!(sizes, [4, 1, 1]);
+    }
+
+    #[test]
+    fn chat_window_length_normalization() {
         let mut cw = ChatWindow::<3, 12>::default();
-        cw.push("hello world".into());
-        cw.push("hello world".into());
-        cw.push("some noises".into());
-        let summary = cw.summary();
-        assert!(summary.is_some());
-        assert_eq!(summary.unwrap().0, "hello world");
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("gg".into(), &mut dict);
+        cw.push_with_dict("gg wp everyone that was a long game".into(), &mut dict);
+        cw.push_with_dict("gg wp".into(), &mut dict);
+        let plain = cw.scored_chats_with_dict(&dict);
+        let normalized_cw = cw.clone().with_length_normalization(1.0);
+        let normalized = normalized_cw.scored_chats_with_dict(&dict);
+        assert!(normalized[1].2 / normalized[0].2 < plain[1].2 / plain[0].2);
     }
 
     #[test]
-    fn chat_window_summary_with_data() {
-        let mut cw = ChatWindow::<3, 12, usize>::default();
-        cw.push_with_data("hello world".into(), Some(1));
-        cw.push_with_data("hello world".into(), Some(2));
-        cw.push_with_data("some noises".into(), Some(3));
-        let summary = cw.summary();
-        assert!(summary.is_some());
-        assert_eq!(summary.unwrap().0, "hello world");
-        assert_eq!(summary.unwrap().1, Some(&2));
+    fn chat_window_incremental_centrality() {
+        let chats = ["evicted soon", "hello world", "hello world", "some noises"];
+        let mut cw = ChatWindow::<3, 12>::default();
+        let mut dict = MemoryDictionary::<12>::default();
+        // Switched to incremental only after the pushes.
+        let mut late = ChatWindow::<3, 12>::default().with_exact_centrality();
+        let mut late_dict = MemoryDictionary::<12>::default();
+        for chat in chats {
+            cw.push_with_dict(chat.into(), &mut dict);
+            late.push_with_dict(chat.into(), &mut late_dict);
+        }
+        assert!(!cw.token_mass.contains_key(&ChatToken::from("evicted soon")));
+        // The running sum matches a recompute from the chats in the window.
+        for (token, &mass) in &cw.token_mass {
+            let sum: f64 = cw
+                .chat_caches()
+                .flat_map(|c| c.tokens.iter().zip(&c.frozen_tfidf))
+                .filter(|(t, _)| *t == token)
+                .map(|(_, u)| u)
+                .sum();
+            assert!((mass - sum).abs() < 1e-9, "{token}: {mass} vs {sum}");
+        }
+        let scored = cw.scored_chats_with_dict(&dict);
+        assert_eq!(scored.len(), 3);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "hello world");
+        assert!((scored[0].2 - scored[1].2).abs() < 1e-2);
+        let late = late.with_incremental_centrality();
+        assert_eq!(late.scored_chats_with_dict(&late_dict), scored);
+    }
+
+    #[test]
+    fn chat_window_max_age() {
+        let mut cw = ChatWindow::<4, 16>::default().with_max_age(Duration::from_secs(60));
+        let mut dict = MemoryDictionary::<16>::default();
+        let t0 = Instant::now();
+        cw.push_at_with_dict("ancient".into(), t0, None, &mut dict);
+        cw.push_at_with_dict(
+            "recent".into(),
+            t0 + Duration::from_secs(50),
+            None,
+            &mut dict,
+        );
+        cw.push_at_with_dict("now".into(), t0 + Duration::from_secs(100), None, &mut dict);
+        let chats: Vec<_> = cw.chat_caches().map(|c| c.chat()).collect();
+        assert_eq!(chats, vec!["recent", "now"]);
+    }
+
+    #[test]
+    fn chat_window_hybrid_limits() {
+        let mut cw = ChatWindow::<8, 16>::default()
+            .with_max_len(2)
+            .with_max_age(Duration::from_secs(60));
+        let mut dict = MemoryDictionary::<16>::default();
+        let t0 = Instant::now();
+        for (i, chat) in ["a", "b", "c"].into_iter().enumerate() {
+            cw.push_at_with_dict(
+                chat.into(),
+                t0 + Duration::from_secs(i as u64),
+                None,
+                &mut dict,
+            );
+        }
+        let chats: Vec<_> = cw.chat_caches().map(|c| c.chat()).collect();
+        assert_eq!(chats, vec!["b", "c"]);
+        cw.push_at_with_dict("d".into(), t0 + Duration::from_secs(62), None, &mut dict);
+        let chats: Vec<_> = cw.chat_caches().map(|c| c.chat()).collect();
+        assert_eq!(chats, vec!["c", "d"]);
+        cw.push_at_with_dict("e".into(), t0 + Duration::from_secs(200), None, &mut dict);
+        let chats: Vec<_> = cw.chat_caches().map(|c| c.chat()).collect();
+        assert_eq!(chats, vec!["e"]);
+    }
+
+    #[test]
+    fn chat_window_inspection() {
+        let mut cw = ChatWindow::<2, 8, usize>::default();
+        let mut dict = MemoryDictionary::<8>::default();
+        assert!(cw.is_empty());
+        cw.push_with_data_and_dict("a".into(), Some(1), &mut dict);
+        assert_eq!(cw.len(), 1);
+        assert!(!cw.is_full());
+        cw.push_with_data_and_dict("b".into(), Some(2), &mut dict);
+        cw.push_with_data_and_dict("c".into(), None, &mut dict);
+        assert!(cw.is_full());
+        assert_eq!(
+            cw.iter().collect::<Vec<_>>(),
+            vec![("b", Some(&2)), ("c", None)]
+        );
+    }
+
+    #[test]
+    fn chat_window_retract() {
+        let mut cw = ChatWindow::<4, 16>::default();
+        let mut dict = MemoryDictionary::<16>::default();
+        cw.push_with_dict("spam".into(), &mut dict);
+        cw.push_with_dict("hello".into(), &mut dict);
+        cw.push_with_dict("spam".into(), &mut dict);
+        let removed = cw.retract_with_dict(|c| c.chat() == "spam", &mut dict);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(cw.iter().map(|c| c.0).collect::<Vec<_>>(), vec!["hello"]);
+        assert_eq!(dict.count("spam", cw.last_chat_idx()), 0.);
+    }
+
+    #[test]
+    fn retract_last_matching() {
+        let mut det = ChatSpikeDetector::<4, 16>::default();
+        let t0 = Instant::now();
+        det.update_and_detect("spam".into(), t0);
+        det.update_and_detect("hello".into(), t0);
+        det.update_and_detect("spam".into(), t0);
+        assert!(det.retract_last_matching("spam"));
+        let chats: Vec<_> = det.window().iter().map(|c| c.0).collect();
+        assert_eq!(chats, vec!["spam", "hello"]);
+        assert!(!det.retract_last_matching("absent"));
+    }
+
+    #[test]
+    fn token_weights_scale_idf_by_kind() {
+        let weights = TokenWeights {
+            mention: 0.,
+            ..Default::default()
+        };
+        let mut cw = ChatWindow::<4, 16>::default().with_token_weights(weights);
+        let mut dict = MemoryDictionary::<16>::default();
+        cw.push_with_dict("@alice hi".into(), &mut dict);
+        let cache = cw.chat_caches().next().unwrap();
+        assert_eq!(&cache.tokens()[0], "@alice");
+        assert_eq!(cache.token_kinds()[0], TokenKind::Mention);
+        let vector = cw.tfidf(cache.tokens(), cache.token_kinds(), &dict);
+        assert_eq!(vector[0], (&ChatToken::from("@alice"), 0.));
+        assert!(vector[1..].iter().all(|(_, u)| *u > 0.));
+    }
+
+    #[test]
+    fn novelty_boost_favours_new_tokens() {
+        let mut dict = MemoryDictionary::<50>::default().with_novelty_tracking();
+        dict.import_corpus(std::iter::repeat_n("gg wp", 50), (1, 4));
+        let mut cw = ChatWindow::<4, 50>::default();
+        for chat in ["gg wp", "gg wp", "xyzzy", "xyzzy"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        let scores = |cw: &ChatWindow<4, 50>| {
+            let s = cw.scored_chats_with_dict(&dict);
+            s[2].2 / s[0].2
+        };
+        let plain = scores(&cw);
+        let boosted = scores(&cw.clone().with_novelty_boost(2.));
+        assert!(boosted > plain);
+    }
+
+    #[test]
+    fn chat_window_interns_tokens() {
+        let mut cw = ChatWindow::<2, 16>::default().with_ngram_range(1, 2);
+        let mut dict = HashedDictionary::<16>::default();
+        cw.push_with_dict("ab".into(), &mut dict);
+        cw.push_with_dict("abc".into(), &mut dict);
+        let caches: Vec<_> = cw.chat_caches().collect();
+        let text = |c: &ChatCache<()>| match &c.tokens()[0] {
+            ChatToken::Text(text) => text.clone(),
+            ChatToken::Fingerprint(_) => panic!("text token expected"),
+        };
+        assert!(Arc::ptr_eq(&text(caches[0]), &text(caches[1])));
+        for i in 0..1024 {
+            cw.push_with_dict(format!("{i}"), &mut dict);
+        }
+        assert!(cw.interner.len() < 1024);
+
+        // A `MemoryDictionary` lends the window its own copies.
+        let mut cw = ChatWindow::<2, 16>::default().with_ngram_range(1, 2);
+        let mut dict = MemoryDictionary::<16>::default();
+        cw.push_with_dict("ab".into(), &mut dict);
+        let token = text(cw.chat_caches().next().unwrap());
+        assert!(Arc::ptr_eq(&token, &dict.intern(&token).unwrap()));
+        assert!(cw.interner.is_empty());
+    }
+
+    #[test]
+    fn fingerprint_keyed_window_skips_interning() {
+        let mut cw =
+            ChatWindow::<2, 16, (), crate::dict::FingerprintKey>::default().with_ngram_range(2, 2);
+        let mut dict = HashedDictionary::<16>::default();
+        cw.push_with_dict("ab".into(), &mut dict);
+        let cache = cw.chat_caches().next().unwrap();
+        assert_eq!(
+            cache.tokens(),
+            [ChatToken::Fingerprint(token_fingerprint("ab"))]
+        );
+        assert!(cw.interner.is_empty());
+    }
+
+    #[test]
+    fn chat_window_custom_tokenizer() {
+        struct Words;
+        impl Tokenizer for Words {
+            fn tokens<'a>(&self, text: &'a str) -> crate::tokenizer::Tokens<'a> {
+                text.split_whitespace()
+                    .map(crate::tokenizer::Token::text)
+                    .collect()
+            }
+        }
+        let mut cw = ChatWindow::<3, 12>::default()
+            .with_tokenizer(Words)
+            .with_exact_centrality();
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("go go team".into(), &mut dict);
+        cw.push_with_dict("go team".into(), &mut dict);
+        cw.push_with_dict("nice".into(), &mut dict);
+        let tokens = cw.chat_caches().next().unwrap().tokens();
+        assert_eq!(tokens, [ChatToken::from("go"), ChatToken::from("team")]);
+        assert_eq!(dict.count("go", 2), 1. + 11. / 12.);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "go team");
+    }
+
+    #[test]
+    fn chat_window_word_tokens() {
+        let mut cw = ChatWindow::<4, 12>::default().with_word_tokens(1..=2);
+        let mut dict = MemoryDictionary::<12>::default();
+        for chat in ["what a play", "what a play lol", "a play", "brb"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        assert!(dict.count("a play", 4) > 2.);
+        assert_eq!(dict.count("pl", 4), 0.);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "what a play");
+    }
+
+    #[test]
+    fn chat_window_runtime_sized_ring() {
+        use crate::ring::DynRing;
+        let mut fixed = ChatWindow::<3, 12>::default();
+        let mut dynamic = ChatWindow::<0, 12, (), TextKey, DynRing<_>>::with_ring(DynRing::new(3));
+        let (mut a, mut b) = (
+            MemoryDictionary::<12>::default(),
+            MemoryDictionary::<12>::default(),
+        );
+        for chat in ["gg", "what a play", "what a play!", "what play", "lol"] {
+            fixed.push_with_dict(chat.into(), &mut a);
+            dynamic.push_with_dict(chat.into(), &mut b);
+        }
+        assert_eq!(dynamic.len(), 3);
+        assert_eq!(
+            dynamic.summary_with_dict(&b).unwrap().0,
+            fixed.summary_with_dict(&a).unwrap().0
+        );
+        assert!(dynamic.with_max_len(5).is_full());
+    }
+
+    #[test]
+    fn chat_window_overwrite_policy_forgets_the_oldest() {
+        use crate::ring::DynRing;
+        let mut cw = ChatWindow::<0, 12, (), TextKey, DynRing<_>>::with_ring(DynRing::new(2));
+        let mut dict = MemoryDictionary::<12>::default();
+        for chat in ["aaa", "bbb", "ccc"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        assert_eq!(
+            cw.iter().map(|(c, _)| c).collect::<Vec<_>>(),
+            ["bbb", "ccc"]
+        );
+        assert!(!cw.token_mass.contains_key(&ChatToken::from("aaa")));
+    }
+
+    #[test]
+    fn chat_window_reject_policy_skips_the_new_chat() {
+        use crate::ring::DynRing;
+        let ring = DynRing::new(2).with_overflow(OverflowPolicy::Reject);
+        let mut cw = ChatWindow::<0, 12, (), TextKey, DynRing<_>>::with_ring(ring);
+        let mut dict = MemoryDictionary::<12>::default();
+        for chat in ["aaa", "bbb", "ccc"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        assert_eq!(
+            cw.iter().map(|(c, _)| c).collect::<Vec<_>>(),
+            ["aaa", "bbb"]
+        );
+        // The rejected chat was never observed.
+        assert_eq!(cw.last_chat_idx(), 2);
+        assert_eq!(dict.count("ccc", 2), 0.);
+        assert!(!cw.token_mass.contains_key(&ChatToken::from("ccc")));
+    }
+
+    #[test]
+    fn chat_window_callback_policy_gets_forgotten_chats() {
+        use crate::ring::DynRing;
+        use std::sync::Mutex;
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = evicted.clone();
+        let ring = DynRing::new(2).with_overflow(OverflowPolicy::Callback(Arc::new(
+            move |c: ChatCache<()>| sink.lock().unwrap().push(c.chat),
+        )));
+        let mut cw = ChatWindow::<0, 12, (), TextKey, DynRing<_>>::with_ring(ring);
+        let mut dict = MemoryDictionary::<12>::default();
+        for chat in ["aaa", "bbb", "ccc", "ddd"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        assert_eq!(*evicted.lock().unwrap(), ["aaa", "bbb"]);
+        assert_eq!(
+            cw.iter().map(|(c, _)| c).collect::<Vec<_>>(),
+            ["ccc", "ddd"]
+        );
+        assert!(!cw.token_mass.contains_key(&ChatToken::from("aaa")));
+        assert!(!cw.token_mass.contains_key(&ChatToken::from("bbb")));
+    }
+
+    #[test]
+    fn chat_window_clear_and_truncate() {
+        let mut cw = ChatWindow::<4, 16>::default();
+        let mut dict = MemoryDictionary::<16>::default();
+        let t0 = Instant::now();
+        for (i, chat) in ["a", "b", "c"].into_iter().enumerate() {
+            cw.push_at_with_dict(
+                chat.into(),
+                t0 + Duration::from_secs(i as u64),
+                None,
+                &mut dict,
+            );
+        }
+        cw.truncate_older_than(t0 + Duration::from_secs(1));
+        assert_eq!(cw.iter().map(|c| c.0).collect::<Vec<_>>(), vec!["b", "c"]);
+        cw.clear();
+        assert!(cw.is_empty());
+        assert!(cw.token_mass.is_empty());
+        assert!(dict.count("a", cw.last_chat_idx()) > 0.);
+    }
+
+    #[test]
+    fn author_policy_weights() {
+        let mut cw = ChatWindow::<8, 16>::default().with_author_policy(AuthorPolicy::Cap(2));
+        let mut dict = MemoryDictionary::<16>::default();
+        let t0 = Instant::now();
+        let weights: Vec<_> = (0..3)
+            .map(|_| cw.push_by_author_with_dict("spam".into(), "bot".into(), t0, None, &mut dict))
+            .collect();
+        assert_eq!(weights, vec![1., 1., 0.]);
+        let cw = cw.with_author_policy(AuthorPolicy::Decay(1.));
+        assert_eq!(cw.author_weight("bot"), 0.25);
+        assert_eq!(cw.author_weight("someone"), 1.);
+
+        // A capped flood no longer carries the summary.
+        let summaries: Vec<_> = [AuthorPolicy::Off, AuthorPolicy::Cap(1)]
+            .into_iter()
+            .map(|policy| {
+                let mut cw = ChatWindow::<8, 16>::default().with_author_policy(policy);
+                let mut dict = MemoryDictionary::<16>::default();
+                for _ in 0..5 {
+                    cw.push_by_author_with_dict("spam".into(), "bot".into(), t0, None, &mut dict);
+                }
+                for author in ["a", "b"] {
+                    cw.push_by_author_with_dict(
+                        "nice play".into(),
+                        author.into(),
+                        t0,
+                        None,
+                        &mut dict,
+                    );
+                }
+                cw.summary_with_dict(&dict).unwrap().0.to_owned()
+            })
+            .collect();
+        assert_eq!(summaries, ["spam", "nice play"]);
+    }
+
+    #[test]
+    fn spike_detector_weighted_push() {
+        let t0 = Instant::now();
+        let mut one = SpikeDetector::<4, 16>::default();
+        let mut weighted = SpikeDetector::<4, 16>::default();
+        for i in 0..3 {
+            one.push(t0 + Duration::from_secs(i));
+            weighted.push_weighted(t0 + Duration::from_secs(i), 1.);
+        }
+        assert_eq!(one.dur_s, weighted.dur_s);
+        weighted.push_weighted(t0 + Duration::from_secs(10), 0.);
+        assert_eq!(one.dur_s, weighted.dur_s);
+        weighted.push_weighted(t0 + Duration::from_secs(10), 3.);
+        one.push(t0 + Duration::from_secs(10));
+        assert!(weighted.current_surprise() > one.current_surprise());
+    }
+
+    #[test]
+    fn spike_detector_ignores_non_finite_weights() {
+        let t0 = Instant::now();
+        let mut sd = SpikeDetector::<5, 50>::default();
+        for i in 0..20 {
+            sd.push(t0 + Duration::from_secs(i));
+        }
+        let dur_s = sd.dur_s;
+        sd.push_weighted(t0 + Duration::from_secs(20), f64::NAN);
+        sd.push_weighted(t0 + Duration::from_secs(20), f64::INFINITY);
+        assert_eq!(sd.dur_s, dur_s);
+        let begun = (0..100).any(|i| {
+            let ts = t0 + Duration::from_secs(20) + Duration::from_millis(10 * i);
+            matches!(sd.push(ts), SpikeEvent::Begin { .. })
+        });
+        assert!(begun);
+        assert!(sd.current_surprise() > 0.);
+
+        let mut det = ChatSpikeDetector::<5, 50>::default().with_weighted_summaries();
+        det.update_and_detect("gg".into(), t0);
+        det.update_and_detect("gg".into(), t0);
+        det.update_and_detect_weighted("gg".into(), t0, f64::NAN, None);
+        let (_, _, score) = DegreeCentrality
+            .summarize(det.window(), det.dictionary())
+            .unwrap();
+        assert!(score > 0.);
+    }
+
+    #[test]
+    fn spike_detector_neg_binomial_model() {
+        let t0 = Instant::now();
+        let nb = Model::NegBinomial { dispersion: None };
+        let poisson_like = Model::NegBinomial {
+            dispersion: Some(1.),
+        };
+        let mut dets = [Model::Poisson, nb, poisson_like].map(|model| {
+            SpikeDetector::<5, 50>::default()
+                .with_threshold(2., 1.)
+                .with_model(model)
+        });
+        // Bursty baseline: runs of quick chats separated by long pauses.
+        let mut ts = t0;
+        let mut spikes = [0; 3];
+        for i in 0..200 {
+            ts += Duration::from_millis(if i % 5 == 4 { 9800 } else { 50 });
+            for (det, n) in dets.iter_mut().zip(&mut spikes) {
+                *n += matches!(det.push(ts), SpikeEvent::Begin { .. }) as usize;
+            }
+        }
+        assert!(dets[1].dispersion() > 1.5, "{}", dets[1].dispersion());
+        assert!(spikes[1] < spikes[0], "{spikes:?}");
+        assert_eq!(spikes[2], spikes[0]);
+    }
+
+    #[test]
+    fn spike_detector_likelihood_ratio_model() {
+        let t0 = Instant::now();
+        let mut det = SpikeDetector::<20, 200>::default().with_model(Model::LikelihoodRatio);
+        let mut ts = t0;
+        for _ in 0..400 {
+            ts += Duration::from_secs(1);
+            assert!(matches!(det.push(ts), SpikeEvent::None));
+        }
+        assert!(det.current_surprise() < 1e-6);
+        let burst = (0..40).map(|_| {
+            ts += Duration::from_millis(100);
+            det.push(ts)
+        });
+        assert!(
+            burst
+                .collect::<Vec<_>>()
+                .iter()
+                .any(|e| matches!(e, SpikeEvent::Begin { .. }))
+        );
+    }
+
+    #[test]
+    fn spike_detector_z_score_model() {
+        let t0 = Instant::now();
+        let mut det = SpikeDetector::<5, 100>::default()
+            .with_threshold(4., 2.)
+            .with_model(Model::ZScore);
+        let mut ts = t0;
+        let mut begins = 0;
+        for i in 0..300 {
+            let gap = if (250..260).contains(&i) {
+                50
+            } else {
+                900 + i % 3 * 100
+            };
+            ts += Duration::from_millis(gap);
+            if matches!(det.push(ts), SpikeEvent::Begin { .. }) {
+                assert!(i >= 250, "false spike at {i}");
+                begins += 1;
+            }
+        }
+        assert_eq!(begins, 1);
+    }
+
+    #[test]
+    fn spike_detector_gamma_poisson_model() {
+        let gamma = Model::GammaPoisson {
+            shape: 1.,
+            rate: 1.,
+        };
+        let mut dets =
+            [Model::Poisson, gamma].map(|m| SpikeDetector::<5, 100>::default().with_model(m));
+        let mut ts = Instant::now();
+        let mut feed = |dets: &mut [SpikeDetector<5, 100>; 2], gap: u64, n: usize| {
+            for _ in 0..n {
+                ts += Duration::from_millis(gap);
+                dets.iter_mut().for_each(|d| _ = d.push(ts));
+            }
+        };
+        feed(&mut dets, 1000, 5);
+        feed(&mut dets, 100, 5);
+        // Less sure than a Poisson at the posterior mean rate.
+        let bayes = &dets[1];
+        let shape = 1. + bayes.gaps.weight * 100.;
+        let plug_in =
+            crate::math::neg_ln_poisson_tail(5., shape * bayes.dur_s / (1. + bayes.dur_l));
+        let b = bayes.current_surprise();
+        assert!(b < plug_in, "cold start: {b} vs {plug_in}");
+        feed(&mut dets, 1000, 1000);
+        feed(&mut dets, 100, 5);
+        let (p, b) = (dets[0].current_surprise(), dets[1].current_surprise());
+        assert!((b - p).abs() < 0.2 * p, "warm: {b} vs {p}");
+    }
+
+    #[test]
+    fn cusum_detector_catches_level_shift() {
+        let mut cusum = CusumDetector::<500>::default();
+        let mut tail = SpikeDetector::<5, 500>::default();
+        let mut ts = Instant::now();
+        let mut begins = (None, None);
+        for i in 0..1000 {
+            let gap = if i < 200 { 900 + i % 3 * 100 } else { 500 };
+            ts += Duration::from_millis(gap);
+            if matches!(cusum.push(ts), SpikeEvent::Begin { .. }) {
+                assert!(i >= 200, "false spike at {i}");
+                begins.0.get_or_insert(i);
+            }
+            if matches!(tail.push(ts), SpikeEvent::Begin { .. }) {
+                begins.1.get_or_insert(i);
+            }
+        }
+        let first = begins.0.unwrap();
+        assert!(first < 300, "{first}");
+        assert!(begins.1.is_none_or(|tail| first < tail), "{begins:?}");
+        // The baseline adapts to the new level and the spike ends.
+        assert!(matches!(cusum.phase(), Phase::Idle));
+    }
+
+    #[test]
+    #[should_panic(expected = "shift must exceed 1")]
+    fn cusum_detector_rejects_non_increasing_shift() {
+        let _ = CusumDetector::<500>::default().with_shift(1.);
+    }
+
+    #[test]
+    fn spike_detector_surprise_quantile() {
+        let hour = Duration::from_secs(3600);
+        let mut det = SpikeDetector::<5, 100>::default().with_surprise_quantile(0.9, hour);
+        assert_eq!(det.surprise_quantile(), None);
+        let mut ts = Instant::now();
+        let mut surprises = Vec::new();
+        for i in 0..5000u64 {
+            ts += Duration::from_millis(200 + i * 7919 % 1600);
+            det.push(ts);
+            surprises.push(det.current_surprise());
+        }
+        // About a second per event: the last 1800 to 3600 events count.
+        let recent = &mut surprises[5000 - 2500..];
+        recent.sort_by(f64::total_cmp);
+        let estimate = det.surprise_quantile().unwrap();
+        let lo = recent[(0.8 * recent.len() as f64) as usize];
+        let hi = recent[(0.95 * recent.len() as f64) as usize];
+        assert!(
+            lo <= estimate && estimate <= hi,
+            "{lo} <= {estimate} <= {hi}"
+        );
+    }
+
+    #[test]
+    fn spike_detector_f32_tracks_f64() {
+        let t0 = Instant::now();
+        let mut wide = SpikeDetector::<5, 50>::default();
+        let mut narrow = SpikeDetector::<5, 50, f32>::default();
+        let mut events = (Vec::new(), Vec::new());
+        let gaps = (0..100).map(|i| if (60..70).contains(&i) { 50 } else { 2000 });
+        let mut ts = t0;
+        for gap in gaps {
+            ts += Duration::from_millis(gap);
+            events.0.push(std::mem::discriminant(&wide.push(ts)));
+            events.1.push(std::mem::discriminant(&narrow.push(ts)));
+        }
+        assert_eq!(events.0, events.1);
+        assert!(
+            events
+                .0
+                .contains(&std::mem::discriminant(&SpikeEvent::Begin { surprise: 0. }))
+        );
+        let (a, b) = (wide.current_surprise(), narrow.current_surprise() as f64);
+        assert!((a - b).abs() < 1e-3 * (1. + a.abs()), "{a} vs {b}");
+    }
+
+    #[test]
+    fn filtered_chats_are_dropped() {
+        let mut det = ChatSpikeDetector::<4, 16>::default()
+            .with_filter(crate::filter::CommandFilter::default())
+            .with_filter(|chat: &str, _: Option<&str>| !chat.contains("buy"));
+        let t0 = Instant::now();
+        det.update_and_detect("!drops".into(), t0);
+        det.update_and_detect("buy followers".into(), t0);
+        det.update_and_detect("hello".into(), t0);
+        assert_eq!(
+            det.window().iter().map(|c| c.0).collect::<Vec<_>>(),
+            vec!["hello"]
+        );
+        assert_eq!(det.last_updated_at(), Some(t0));
+    }
+
+    #[test]
+    fn chat_window_summary_filter() {
+        let mut cw =
+            ChatWindow::<4, 16>::default().with_summary_filter(|c| !crate::text::is_symbol_only(c));
+        let mut dict = MemoryDictionary::<16>::default();
+        cw.push_with_dict("🔥🔥 🔥".into(), &mut dict);
+        cw.push_with_dict("🔥🔥 🔥".into(), &mut dict);
+        cw.push_with_dict("🔥🔥 🔥".into(), &mut dict);
+        cw.push_with_dict("nice 🔥".into(), &mut dict);
+        assert_eq!(cw.summary_with_dict(&dict).unwrap().0, "nice 🔥");
+    }
+
+    #[test]
+    fn chat_window_scored_chats() {
+        let mut cw = ChatWindow::<3, 12>::default();
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("some noises".into(), &mut dict);
+        let scored = cw.scored_chats_with_dict(&dict);
+        assert_eq!(scored.len(), 3);
+        assert_eq!(scored[2].0, "some noises");
+        assert!(scored[1].2 > scored[2].2);
+    }
+
+    #[test]
+    fn chat_window_summary_explained() {
+        let mut cw = ChatWindow::<3, 12>::default();
+        let mut dict = MemoryDictionary::<12>::default();
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("hello world".into(), &mut dict);
+        cw.push_with_dict("some noises".into(), &mut dict);
+        let explained = cw.summary_explained_with_dict(&dict, 3).unwrap();
+        assert_eq!(explained.chat, "hello world");
+        assert_eq!(explained.tokens.len(), 3);
+        let total: f64 = cw
+            .summary_explained_with_dict(&dict, usize::MAX)
+            .unwrap()
+            .tokens
+            .iter()
+            .map(|t| t.contribution)
+            .sum();
+        assert!((total - explained.score).abs() < 1e-9);
+        assert!(explained.tokens[0].contribution >= explained.tokens[2].contribution);
+    }
+
+    #[test]
+    fn chat_window_top_tokens() {
+        let mut cw = ChatWindow::<4, 100>::default().with_ngram_range(3, 3);
+        let mut dict = MemoryDictionary::<100>::default();
+        for chat in ["pog", "abc", "pog", "pog"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        let top = cw.top_tokens_with_dict(&dict, 1);
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].0, "pog");
+        assert!((top[0].1 - 3.).abs() < 1e-9);
+        assert_eq!(cw.top_tokens_with_dict(&dict, 10).len(), 2);
+    }
+
+    #[test]
+    fn chat_window_trending_tokens() {
+        let mut cw = ChatWindow::<4, 100>::default().with_ngram_range(3, 3);
+        let mut dict = MemoryDictionary::<100>::default();
+        for _ in 0..100 {
+            cw.push_with_dict("lol".into(), &mut dict);
+        }
+        for chat in ["gg!", "lol", "gg!", "lol"] {
+            cw.push_with_dict(chat.into(), &mut dict);
+        }
+        let trending = cw.trending_tokens_with_dict(&dict, 10, 2);
+        assert_eq!(trending.len(), 1);
+        assert_eq!(trending[0].0, "gg!");
+        assert!(trending[0].1 > 1.);
+    }
+
+    #[test]
+    fn chat_window_token_divergence() {
+        let mut cw = ChatWindow::<4, 100>::default().with_ngram_range(3, 3);
+        let mut dict = MemoryDictionary::<100>::default();
+        assert_eq!(cw.token_divergence_with_dict(&dict, 0.5).kl, 0.);
+        for i in 0..1000 {
+            cw.push_with_dict(if i % 2 == 0 { "lol" } else { "kek" }.into(), &mut dict);
+        }
+        let usual = cw.token_divergence_with_dict(&dict, 0.5);
+        assert!(usual.kl < 0.01, "{usual:?}");
+        let usual_js = usual.js;
+        for _ in 0..4 {
+            cw.push_with_dict("gg!".into(), &mut dict);
+        }
+        let shift = cw.token_divergence_with_dict(&dict, 0.5);
+        assert!(shift.kl > 1., "{shift:?}");
+        assert!(shift.js > usual_js && shift.js <= 2f64.ln());
+        assert_eq!(shift.contributions[0].0, "gg!");
+    }
+
+    #[test]
+    fn custom_summarizer() {
+        struct Latest;
+        impl<const S: usize, const L: usize, D> Summarizer<S, L, D> for Latest {
+            fn summarize<'a>(
+                &self,
+                window: &'a ChatWindow<S, L, D>,
+                _dict: &impl Dictionary,
+            ) -> Option<(&'a str, Option<&'a D>, f64)> {
+                window
+                    .chat_caches()
+                    .last()
+                    .map(|c| (c.chat(), c.data(), 0.))
+            }
+        }
+        let mut det = ChatSpikeDetector::<2, 4>::default()
+            .with_threshold(0.0, f64::INFINITY)
+            .with_summarizer(Latest);
+        let t0 = Instant::now();
+        let ev = det.update_and_detect("hi".into(), t0);
+        assert!(matches!(
+            ev,
+            Event::SpikeBegin {
+                summary: Some("hi"),
+                ..
+            }
+        ));
     }
 
     #[test]
@@ -374,4 +3639,245 @@ mod tests {
         assert!(matches!(det.current_phase(), Phase::InSpike));
         assert!(det.current_surprise() >= 0.0);
     }
+
+    #[test]
+    fn update_many_collects_owned_events() {
+        let mut det = ChatSpikeDetector::<1, 2>::default().with_threshold(0.0, f64::INFINITY);
+        let t0 = Instant::now();
+        let events = det.update_many(vec![("hi".to_string(), t0), ("hello".to_string(), t0)]);
+        assert_eq!(events.len(), 2);
+        // Each event is summarized when raised.
+        assert!(matches!(
+            &events[0],
+            OwnedEvent::SpikeBegin { summary: Some(s), .. } if s == "hi"
+        ));
+        assert!(matches!(
+            &events[1],
+            OwnedEvent::SpikeEnd { summary: Some(s), .. } if s == "hello"
+        ));
+
+        struct Counting(std::cell::Cell<usize>);
+        impl<const S: usize, const L: usize> Summarizer<S, L, ()> for Counting {
+            fn summarize<'a>(
+                &self,
+                window: &'a ChatWindow<S, L>,
+                dict: &impl Dictionary,
+            ) -> Option<(&'a str, Option<&'a ()>, f64)> {
+                self.0.set(self.0.get() + 1);
+                window.summary_with_dict(dict)
+            }
+        }
+        let mut det = ChatSpikeDetector::<1, 2>::default()
+            .with_threshold(0.0, f64::INFINITY)
+            .with_summarizer(Counting(Default::default()));
+        let chats = (0..10).map(|i| (format!("chat {i}"), t0 + Duration::from_secs(i)));
+        let events = det.update_many(chats);
+        assert!(events.len() > 2);
+        assert_eq!(det.summarizer.0.get(), events.len());
+    }
+
+    #[test]
+    fn weighted_chats_raise_surprise() {
+        let t0 = Instant::now();
+        let mut plain = ChatSpikeDetector::<5, 50>::default();
+        let mut cheered = ChatSpikeDetector::<5, 50>::default().with_weighted_summaries();
+        for i in 0..20 {
+            let ts = t0 + Duration::from_secs(i);
+            plain.update_and_detect("lol".into(), ts);
+            cheered.update_and_detect("lol".into(), ts);
+        }
+        let ts = t0 + Duration::from_secs(20);
+        plain.update_and_detect("cheer".into(), ts);
+        cheered.update_and_detect_weighted("cheer".into(), ts, 10., None);
+        assert!(cheered.current_surprise() > plain.current_surprise());
+        let weights: Vec<_> = cheered.window().chat_caches().map(|c| c.weight()).collect();
+        assert_eq!(weights.last(), Some(&10.));
+    }
+
+    #[test]
+    fn incremental_centrality_applies_chat_weights() {
+        let t0 = Instant::now();
+        let summaries: Vec<_> = [false, true]
+            .into_iter()
+            .map(|exact| {
+                let mut cw =
+                    ChatWindow::<20, 100>::default().with_author_policy(AuthorPolicy::Cap(1));
+                if exact {
+                    cw = cw.with_exact_centrality();
+                }
+                let mut dict = MemoryDictionary::<100>::default();
+                for _ in 0..10 {
+                    cw.push_by_author_with_dict(
+                        "spam spam".into(),
+                        "bot".into(),
+                        t0,
+                        None,
+                        &mut dict,
+                    );
+                }
+                for author in ["a", "b", "c"] {
+                    cw.push_by_author_with_dict(
+                        "nice play".into(),
+                        author.into(),
+                        t0,
+                        None,
+                        &mut dict,
+                    );
+                }
+                cw.summary_with_dict(&dict).unwrap().0.to_owned()
+            })
+            .collect();
+        assert_eq!(summaries, ["nice play", "nice play"]);
+
+        let mut plain = ChatSpikeDetector::<5, 100>::default();
+        let mut weighted = ChatSpikeDetector::<5, 100>::default().with_weighted_summaries();
+        for (i, (chat, weight)) in (0..2)
+            .map(|_| ("nice play", 1.))
+            .chain((0..3).map(|_| ("spam spam", 0.01)))
+            .enumerate()
+        {
+            let ts = t0 + Duration::from_secs(i as u64);
+            plain.update_and_detect_weighted(chat.into(), ts, weight, None);
+            weighted.update_and_detect_weighted(chat.into(), ts, weight, None);
+        }
+        let summary = |det: &ChatSpikeDetector<5, 100>| {
+            DegreeCentrality
+                .summarize(det.window(), det.dictionary())
+                .unwrap()
+                .0
+                .to_owned()
+        };
+        assert_eq!(summary(&plain), "spam spam");
+        assert_eq!(summary(&weighted), "nice play");
+    }
+
+    #[test]
+    fn transcript_records_spike_chats() {
+        let mut det = ChatSpikeDetector::<1, 2, u32>::default()
+            .with_threshold(0.0, f64::INFINITY)
+            .with_transcript(1);
+        let t0 = Instant::now();
+        det.update_and_detect_with_data("hi".into(), t0, Some(1));
+        assert_eq!(det.current_report().map(|r| r.chats.len()), Some(1));
+        det.update_and_detect_with_data("bye".into(), t0, Some(2));
+        let reports = det.take_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].chats[0].chat, "hi");
+        assert_eq!(reports[0].chats[0].data, Some(1));
+        assert_eq!(reports[0].dropped, 1);
+        assert!(det.current_report().is_none());
+        assert!(det.take_reports().is_empty());
+
+        // Untaken reports are capped, oldest dropped first.
+        let mut det = det.with_max_reports(2);
+        for i in 0..6 {
+            det.update_and_detect_with_data(format!("chat {i}"), t0, Some(i));
+        }
+        let reports = det.take_reports();
+        let firsts: Vec<_> = reports.iter().map(|r| r.chats[0].data).collect();
+        assert_eq!(firsts, [Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn reorder_window_sorts_late_chats() {
+        let mut det = ChatSpikeDetector::<1, 2>::default()
+            .with_threshold(0.0, f64::INFINITY)
+            .with_reorder_window(Duration::from_secs(1));
+        let t0 = Instant::now();
+        assert!(
+            det.enqueue("late".into(), t0 + Duration::from_millis(500), None)
+                .is_empty()
+        );
+        assert!(det.enqueue("early".into(), t0, None).is_empty());
+        let events = det.enqueue("next".into(), t0 + Duration::from_secs(2), None);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            OwnedEvent::SpikeBegin { summary: Some(s), .. } if s == "early"
+        ));
+        assert_eq!(det.flush().len(), 1);
+        assert_eq!(det.last_updated_at(), Some(t0 + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn detector_reports_push_outcomes() {
+        let t0 = Instant::now();
+        let t1 = t0 + Duration::from_secs(1);
+        let mut det = ChatSpikeDetector::<1, 2>::default().with_clock_policy(ClockPolicy::Reject);
+        assert!(det.last_push_outcome().is_none());
+        det.update_and_detect("a".into(), t1);
+        assert!(matches!(
+            det.last_push_outcome(),
+            Some(Ok(PushOutcome::Accepted(_)))
+        ));
+        det.update_and_detect("b".into(), t0);
+        assert_eq!(
+            det.last_push_outcome().unwrap().unwrap_err(),
+            NonMonotonicTimestamp {
+                last_ts: t1,
+                ts: t0
+            }
+        );
+        assert_eq!(det.window().len(), 1);
+
+        let mut det = ChatSpikeDetector::<1, 2>::default().with_clock_policy(ClockPolicy::Skip);
+        det.update_and_detect("a".into(), t1);
+        det.update_and_detect("b".into(), t0);
+        assert!(matches!(
+            det.last_push_outcome(),
+            Some(Ok(PushOutcome::Skipped))
+        ));
+    }
+
+    #[test]
+    fn detector_runs_on_a_runtime_sized_window() {
+        use crate::ring::DynRing;
+        let mut det = ChatSpikeDetector::<0, 12>::default()
+            .with_window(ChatWindow::with_ring(DynRing::new(3)));
+        let t0 = Instant::now();
+        for (i, chat) in ["gg", "what a play", "what a play!", "what play"]
+            .into_iter()
+            .enumerate()
+        {
+            det.update_and_detect(chat.into(), t0 + Duration::from_secs(i as u64));
+        }
+        assert_eq!(det.window().len(), 3);
+        let (summary, _, _) = DegreeCentrality
+            .summarize(det.window(), det.dictionary())
+            .unwrap();
+        assert_eq!(summary, "what a play");
+    }
+
+    #[test]
+    fn reorder_window_resequences_authored_chats() {
+        let mut det =
+            ChatSpikeDetector::<4, 8>::default().with_reorder_window(Duration::from_secs(1));
+        let t0 = Instant::now();
+        let ms = |ms| t0 + Duration::from_millis(ms);
+        det.enqueue("a".into(), ms(0), None);
+        det.enqueue_with_author("c".into(), "kim".into(), ms(600), None);
+        det.enqueue("b".into(), ms(400), None);
+        det.flush();
+        let fed: Vec<_> = det
+            .window()
+            .chat_caches()
+            .map(|c| (c.chat.as_str(), c.author.as_deref()))
+            .collect();
+        assert_eq!(fed, [("a", None), ("b", None), ("c", Some("kim"))]);
+    }
+
+    #[test]
+    fn reorder_window_resequences_a_late_chat() {
+        let mut det =
+            ChatSpikeDetector::<4, 8>::default().with_reorder_window(Duration::from_secs(1));
+        let t0 = Instant::now();
+        let ms = |ms| t0 + Duration::from_millis(ms);
+        for (chat, ts) in [("a", 0), ("b", 400), ("d", 900), ("c", 600), ("e", 2500)] {
+            det.enqueue(chat.into(), ms(ts), None);
+        }
+        // "c" arrived after "d" but within the window, so it was fed first.
+        let fed: Vec<_> = det.window().iter().map(|(chat, _)| chat).collect();
+        assert_eq!(fed, ["a", "b", "c", "d"]);
+        assert_eq!(det.last_updated_at(), Some(ms(900)));
+    }
 }
@@ -0,0 +1,188 @@
+//! Spike events from an asynchronous chat stream.
+//!
+//! [`SpikeStream`] drives a [`ChatSpikeDetector`] with the chats of a
+//! [`Stream`] and yields the spike events they raise. With
+//! [`with_tick`](SpikeStream::with_tick) it also wakes up periodically, so
+//! a spike still ends once the chat goes quiet.
+//!
+//! ```rust
+//! use std::time::{Duration, Instant};
+//! use futures::{StreamExt, stream};
+//! use chat_spike::ChatSpikeDetector;
+//!
+//! # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+//! let start = Instant::now();
+//! let chats = stream::iter((0..100).map(move |i| {
+//!     let gap = if i < 80 { 1000 } else { 10 };
+//!     (format!("chat {i}"), start + Duration::from_millis(i * gap))
+//! }));
+//! let events = ChatSpikeDetector::<5, 50>::default()
+//!     .into_stream(chats)
+//!     .with_tick(Duration::from_secs(1));
+//! let events: Vec<_> = events.collect().await;
+//! # });
+//! ```
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::dict::Dictionary;
+use crate::ring::{Ring, RingBuffer};
+use crate::spike::{ChatCache, ChatSpikeDetector, OwnedEvent, Summarizer};
+
+/// Stream of the spike events raised by a stream of `(chat, timestamp)`
+/// pairs, see the [module docs](self).
+///
+/// Only spike begins and ends are yielded. Once the chats run out, the
+/// reorder buffer is flushed and the stream ends.
+pub struct SpikeStream<St, const S: usize, const L: usize, D, Sm, Dc, R = Ring<ChatCache<D>, S>> {
+    chats: St,
+    detector: ChatSpikeDetector<S, L, D, Sm, Dc, R>,
+    tick: Option<Interval>,
+    pending: VecDeque<OwnedEvent<D>>,
+    done: bool,
+}
+
+// No field is pinned structurally; the chat stream is polled through `Unpin`.
+impl<St: Unpin, const S: usize, const L: usize, D, Sm, Dc, R> Unpin
+    for SpikeStream<St, S, L, D, Sm, Dc, R>
+{
+}
+
+impl<
+    const S: usize,
+    const L: usize,
+    D,
+    Sm: Summarizer<S, L, D, R>,
+    Dc: Dictionary,
+    R: RingBuffer<ChatCache<D>>,
+> ChatSpikeDetector<S, L, D, Sm, Dc, R>
+{
+    /// Drive this detector with `chats`, see [`SpikeStream`]. Box and pin a
+    /// stream that is not `Unpin`.
+    pub fn into_stream<St>(self, chats: St) -> SpikeStream<St, S, L, D, Sm, Dc, R>
+    where
+        St: Stream<Item = (String, Instant)> + Unpin,
+    {
+        SpikeStream {
+            chats,
+            detector: self,
+            tick: None,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<St, const S: usize, const L: usize, D, Sm, Dc, R> SpikeStream<St, S, L, D, Sm, Dc, R> {
+    /// Call [`ChatSpikeDetector::tick`] every `period` while no chat
+    /// arrives, ending spikes that went quiet and releasing held-back
+    /// chats. Ticks read the current time, so chat timestamps should follow
+    /// the wall clock rather than replay an old log.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn with_tick(mut self, period: Duration) -> Self {
+        let mut tick = tokio::time::interval(period);
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self.tick = Some(tick);
+        self
+    }
+    pub fn detector(&self) -> &ChatSpikeDetector<S, L, D, Sm, Dc, R> {
+        &self.detector
+    }
+    pub fn into_detector(self) -> ChatSpikeDetector<S, L, D, Sm, Dc, R> {
+        self.detector
+    }
+}
+
+impl<St, const S: usize, const L: usize, D, Sm, Dc, R> Stream
+    for SpikeStream<St, S, L, D, Sm, Dc, R>
+where
+    St: Stream<Item = (String, Instant)> + Unpin,
+    D: Clone,
+    Sm: Summarizer<S, L, D, R>,
+    Dc: Dictionary,
+    R: RingBuffer<ChatCache<D>>,
+{
+    type Item = OwnedEvent<D>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match Pin::new(&mut this.chats).poll_next(cx) {
+                Poll::Ready(Some((chat, ts))) => {
+                    this.pending.extend(this.detector.enqueue(chat, ts, None));
+                    continue;
+                }
+                Poll::Ready(None) => {
+                    this.pending.extend(this.detector.flush());
+                    this.done = true;
+                    continue;
+                }
+                Poll::Pending => {}
+            }
+            if let Some(tick) = this.tick.as_mut()
+                && tick.poll_tick(cx).is_ready()
+            {
+                let now = tokio::time::Instant::now().into_std();
+                this.pending.extend(this.detector.tick(now));
+                continue;
+            }
+            return Poll::Pending;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OwnedEvent;
+    use futures::StreamExt;
+    use futures::channel::mpsc;
+
+    #[tokio::test(start_paused = true)]
+    async fn tick_ends_a_spike_that_went_quiet() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut events = ChatSpikeDetector::<5, 100>::default()
+            .into_stream(rx)
+            .with_tick(Duration::from_secs(1));
+        let send = |tx: &mpsc::UnboundedSender<_>, i: usize| {
+            let ts = tokio::time::Instant::now().into_std();
+            tx.unbounded_send((format!("chat {i}"), ts)).unwrap();
+        };
+        for i in 0..200 {
+            send(&tx, i);
+            tokio::time::advance(Duration::from_millis(500)).await;
+        }
+        for i in 0..20 {
+            send(&tx, i);
+            tokio::time::advance(Duration::from_millis(10)).await;
+        }
+        assert!(matches!(
+            events.next().await,
+            Some(OwnedEvent::SpikeBegin { .. })
+        ));
+        // No further chat: only the tick can end the spike.
+        assert!(matches!(
+            events.next().await,
+            Some(OwnedEvent::SpikeEnd { .. })
+        ));
+        assert!(matches!(
+            events.detector().current_phase(),
+            crate::Phase::Idle
+        ));
+        drop(tx);
+        assert!(events.next().await.is_none());
+    }
+}
@@ -0,0 +1,186 @@
+//! A detector running on its own Tokio task.
+//!
+//! [`Event`](crate::spike::Event) borrows its summary from the detector, which
+//! makes it awkward to hold across an `.await`. A [`DetectorTask`] owns the
+//! detector and its dictionary on a spawned task instead: chats go in
+//! through one channel and [`OwnedEvent`]s come out of another.
+//!
+//! ```rust
+//! use std::time::Instant;
+//! use chat_spike::ChatSpikeDetector;
+//! use chat_spike::task::{ChatMsg, DetectorTask};
+//!
+//! # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+//! let (chats, mut events) = DetectorTask::new(ChatSpikeDetector::<5, 50>::default()).spawn();
+//! chats.send(ChatMsg::new("hello", Instant::now())).await.unwrap();
+//! drop(chats);
+//! assert!(events.recv().await.is_none());
+//! # });
+//! ```
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::dict::Dictionary;
+use crate::ring::{Ring, RingBuffer};
+use crate::spike::{ChatCache, ChatSpikeDetector, OwnedEvent, Summarizer};
+
+/// One chat sent to a [`DetectorTask`].
+#[derive(Clone, Debug)]
+pub struct ChatMsg<D = ()> {
+    pub chat: String,
+    pub ts: Instant,
+    pub author: Option<String>,
+    pub data: Option<D>,
+}
+
+impl<D> ChatMsg<D> {
+    pub fn new(chat: impl Into<String>, ts: Instant) -> Self {
+        Self {
+            chat: chat.into(),
+            ts,
+            author: None,
+            data: None,
+        }
+    }
+    /// Weigh the chat by its author, see
+    /// [`update_and_detect_with_author`](ChatSpikeDetector::update_and_detect_with_author).
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+    pub fn with_data(mut self, data: D) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+/// Configuration of a detector task, see the [module docs](self).
+pub struct DetectorTask<const S: usize, const L: usize, D, Sm, Dc, R = Ring<ChatCache<D>, S>> {
+    detector: ChatSpikeDetector<S, L, D, Sm, Dc, R>,
+    capacity: usize,
+    tick: Option<Duration>,
+}
+
+impl<const S: usize, const L: usize, D, Sm, Dc, R> DetectorTask<S, L, D, Sm, Dc, R>
+where
+    D: Clone + Send + 'static,
+    Sm: Summarizer<S, L, D, R> + Send + 'static,
+    Dc: Dictionary + Send + 'static,
+    R: RingBuffer<ChatCache<D>> + Send + 'static,
+{
+    /// Run `detector` as configured, with channels of 1024 messages.
+    pub fn new(detector: ChatSpikeDetector<S, L, D, Sm, Dc, R>) -> Self {
+        Self {
+            detector,
+            capacity: 1024,
+            tick: None,
+        }
+    }
+    /// Buffer up to `capacity` messages in each channel.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+    /// Tick the detector every `period` while no chat arrives, see
+    /// [`SpikeStream::with_tick`](crate::stream::SpikeStream::with_tick).
+    pub fn with_tick(mut self, period: Duration) -> Self {
+        self.tick = Some(period);
+        self
+    }
+    /// Spawn the task on the current Tokio runtime.
+    ///
+    /// Chats, with or without an author, go through the reorder buffer, if
+    /// any. Once every sender is dropped the task flushes the reorder
+    /// buffer, sends the remaining events and stops; it also stops once the
+    /// event receiver is dropped.
+    pub fn spawn(self) -> (Sender<ChatMsg<D>>, Receiver<OwnedEvent<D>>) {
+        let (chat_tx, chat_rx) = mpsc::channel(self.capacity);
+        let (event_tx, event_rx) = mpsc::channel(self.capacity);
+        let tick = self.tick.map(|period| {
+            let mut tick = tokio::time::interval(period);
+            tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            tick
+        });
+        tokio::spawn(run(self.detector, chat_rx, event_tx, tick));
+        (chat_tx, event_rx)
+    }
+}
+
+async fn run<const S: usize, const L: usize, D, Sm, Dc, R>(
+    mut detector: ChatSpikeDetector<S, L, D, Sm, Dc, R>,
+    mut chats: Receiver<ChatMsg<D>>,
+    events: Sender<OwnedEvent<D>>,
+    mut tick: Option<Interval>,
+) where
+    D: Clone,
+    Sm: Summarizer<S, L, D, R>,
+    Dc: Dictionary,
+    R: RingBuffer<ChatCache<D>>,
+{
+    loop {
+        let batch = tokio::select! {
+            msg = chats.recv() => match msg {
+                Some(ChatMsg { chat, ts, author: Some(author), data }) => {
+                    detector.enqueue_with_author(chat, author, ts, data)
+                }
+                Some(ChatMsg { chat, ts, author: None, data }) => detector.enqueue(chat, ts, data),
+                None => break,
+            },
+            now = next_tick(&mut tick) => detector.tick(now),
+        };
+        for event in batch {
+            if events.send(event).await.is_err() {
+                return;
+            }
+        }
+    }
+    for event in detector.flush() {
+        if events.send(event).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Wait for the next tick, or forever without a timer.
+async fn next_tick(tick: &mut Option<Interval>) -> Instant {
+    match tick {
+        Some(tick) => tick.tick().await.into_std(),
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn task_reports_spikes_over_channels() {
+        let detector = ChatSpikeDetector::<5, 100, u32>::default();
+        let (chats, mut events) = DetectorTask::new(detector)
+            .with_tick(Duration::from_secs(1))
+            .spawn();
+        let start = tokio::time::Instant::now().into_std();
+        for i in 0..220u32 {
+            let ts = start
+                + Duration::from_millis(
+                    500 * u64::from(i.min(200)) + 10 * u64::from(i.saturating_sub(200)),
+                );
+            let msg = ChatMsg::new(format!("chat {}", i % 3), ts).with_data(i);
+            chats.send(msg).await.unwrap();
+        }
+        tokio::time::advance(Duration::from_millis(500 * 200 + 200)).await;
+        match events.recv().await {
+            Some(OwnedEvent::SpikeBegin { data, .. }) => assert!(data.is_some()),
+            event => panic!("expected a spike to begin, got {event:?}"),
+        }
+        assert!(matches!(
+            events.recv().await,
+            Some(OwnedEvent::SpikeEnd { .. })
+        ));
+        drop(chats);
+        assert!(events.recv().await.is_none());
+    }
+}
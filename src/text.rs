@@ -1,20 +1,451 @@
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::iter;
 
 pub fn unique_char_ngrams(s: &str, min_n: usize, max_n: usize) -> Vec<String> {
-    let chars: Vec<char> = s.chars().collect();
-    if !chars.is_empty() {
-        let mut v = Vec::<String>::from_iter(
-            (min_n.min(chars.len())..=max_n.min(chars.len()))
-                .flat_map(|n| chars.windows(n).map(|t| t.iter().collect())),
-        );
-        v.sort_unstable();
-        v.dedup();
-        v
+    char_ngram_slices(s, min_n, max_n)
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Sorted, deduplicated character n-grams of `s` as slices of it, so no
+/// token is allocated until it is interned or copied.
+pub fn char_ngram_slices(s: &str, min_n: usize, max_n: usize) -> Vec<&str> {
+    let mut v: Vec<&str> = char_ngrams_iter(s, min_n, max_n).collect();
+    v.sort_unstable();
+    v
+}
+
+/// Distinct character n-grams of `s` as slices of it, shortest first and
+/// unsorted. Only the set of n-grams already yielded is allocated, so
+/// callers that sort or dedup anyway can skip [`char_ngram_slices`].
+///
+/// As there, a chat shorter than `min_n` characters yields itself whole.
+pub fn char_ngrams_iter(s: &str, min_n: usize, max_n: usize) -> impl Iterator<Item = &str> {
+    let mut seen = HashSet::new();
+    all_char_ngrams(s, min_n, max_n).filter(move |ngram| seen.insert(*ngram))
+}
+
+/// Every character n-gram of `s`, repeats included, without allocating.
+pub(crate) fn all_char_ngrams(s: &str, min_n: usize, max_n: usize) -> impl Iterator<Item = &str> {
+    let len = s.chars().count();
+    (min_n.min(len).max(1)..=max_n.min(len)).flat_map(move |n| {
+        s.char_indices().map_while(move |(start, _)| {
+            let rest = &s[start..];
+            let end = rest
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(iter::once(rest.len()))
+                .nth(n)?;
+            Some(&rest[..end])
+        })
+    })
+}
+
+/// 64-bit SimHash of a token set; similar sets differ in few bits.
+pub fn simhash<'a>(tokens: impl IntoIterator<Item = &'a str>) -> u64 {
+    simhash_hashed(tokens.into_iter().map(|token| {
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        hasher.finish()
+    }))
+}
+
+/// [`simhash`] of tokens already hashed to 64 bits.
+pub fn simhash_hashed(hashes: impl IntoIterator<Item = u64>) -> u64 {
+    let mut acc = [0i32; 64];
+    for h in hashes {
+        for (bit, a) in acc.iter_mut().enumerate() {
+            *a += if h >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    acc.iter()
+        .enumerate()
+        .filter(|(_, a)| **a > 0)
+        .fold(0, |fp, (bit, _)| fp | 1 << bit)
+}
+
+/// What a token stands for, so it can be weighted apart from plain text.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TokenKind {
+    #[default]
+    Text,
+    /// An emoji run or a `:name:` emote.
+    Emote,
+    /// An `@user` mention.
+    Mention,
+}
+
+/// Classify a whitespace-separated word.
+pub fn token_kind(word: &str) -> TokenKind {
+    let is_named_emote = word.len() > 2
+        && word.starts_with(':')
+        && word.ends_with(':')
+        && word[1..word.len() - 1]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_');
+    if word.len() > 1 && word.starts_with('@') {
+        TokenKind::Mention
+    } else if is_named_emote || (is_symbol_only(word) && !word.is_ascii()) {
+        TokenKind::Emote
+    } else {
+        TokenKind::Text
+    }
+}
+
+/// Like [`unique_char_ngrams`], but emote and mention words are kept whole
+/// and tagged with their kind; only the remaining text is split into n-grams.
+pub fn typed_tokens(s: &str, min_n: usize, max_n: usize) -> Vec<(String, TokenKind)> {
+    let mut plain = Vec::new();
+    let mut tokens = Vec::new();
+    for word in s.split_whitespace() {
+        match token_kind(word) {
+            TokenKind::Text => plain.push(word),
+            kind => tokens.push((word.to_owned(), kind)),
+        }
+    }
+    tokens.extend(
+        char_ngrams_iter(&plain.join(" "), min_n, max_n).map(|t| (t.to_owned(), TokenKind::Text)),
+    );
+    // A token seen both whole and as an n-gram keeps its most specific kind.
+    tokens.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    tokens.dedup_by(|a, b| a.0 == b.0);
+    tokens
+}
+
+/// Whether `c` is a pictographic emoji, from the common emoji blocks.
+pub fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x2300..=0x23FF
+    )
+}
+
+/// Whether `c` only modifies the emoji before it: joiners, variation
+/// selectors and skin tones.
+pub fn is_emoji_modifier(c: char) -> bool {
+    matches!(c as u32, 0x200D | 0xFE0E | 0xFE0F | 0x1F3FB..=0x1F3FF)
+}
+
+/// Whether `c` is a combining diacritical mark, as stacked by zalgo text.
+pub fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+    )
+}
+
+/// Keep at most `max` combining marks after each base character.
+pub fn limit_combining_marks(text: &str, max: usize) -> String {
+    let mut marks = 0;
+    text.chars()
+        .filter(|&c| {
+            if !is_combining_mark(c) {
+                marks = 0;
+                return true;
+            }
+            marks += 1;
+            marks <= max
+        })
+        .collect()
+}
+
+#[cfg(feature = "korean")]
+const LEADS: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+#[cfg(feature = "korean")]
+const VOWELS: [char; 21] = [
+    'ㅏ', 'ㅐ', 'ㅑ', 'ㅒ', 'ㅓ', 'ㅔ', 'ㅕ', 'ㅖ', 'ㅗ', 'ㅘ', 'ㅙ', 'ㅚ', 'ㅛ', 'ㅜ', 'ㅝ', 'ㅞ',
+    'ㅟ', 'ㅠ', 'ㅡ', 'ㅢ', 'ㅣ',
+];
+#[cfg(feature = "korean")]
+const TAILS: [char; 27] = [
+    'ㄱ', 'ㄲ', 'ㄳ', 'ㄴ', 'ㄵ', 'ㄶ', 'ㄷ', 'ㄹ', 'ㄺ', 'ㄻ', 'ㄼ', 'ㄽ', 'ㄾ', 'ㄿ', 'ㅀ', 'ㅁ',
+    'ㅂ', 'ㅄ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅊ', 'ㅋ', 'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+/// Split precomposed Hangul syllables into compatibility jamo, so `헐` and
+/// a stretched `허어얼` share n-grams, and `ㅋ` typed alone matches the `ㅋ`
+/// in `킄`.
+#[cfg(feature = "korean")]
+pub fn decompose_jamo(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 3);
+    for c in text.chars() {
+        let Some(idx) = (c as u32).checked_sub(0xAC00).filter(|&i| i < 11172) else {
+            out.push(c);
+            continue;
+        };
+        let idx = idx as usize;
+        out.push(LEADS[idx / 588]);
+        out.push(VOWELS[idx % 588 / 28]);
+        if let Some(tail) = (idx % 28).checked_sub(1) {
+            out.push(TAILS[tail]);
+        }
+    }
+    out
+}
+
+/// Half-width katakana U+FF61..=U+FF9D in full width.
+const FULL_WIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+/// Fold full-width ASCII and the ideographic space to ASCII, and half-width
+/// katakana to full width, so `ＬＯＬ` reads as `LOL` and `ｶﾞ` as `ガ`.
+pub fn fold_width(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c as u32 {
+            0xFF01..=0xFF5E => out.extend(char::from_u32(c as u32 - 0xFEE0)),
+            0x3000 => out.push(' '),
+            0xFF61..=0xFF9D => out.extend(FULL_WIDTH_KANA.chars().nth(c as usize - 0xFF61)),
+            // Sound marks combine with the kana before them where possible.
+            0xFF9E | 0xFF9F => {
+                let voiced = c == '\u{FF9E}';
+                let base = out.pop();
+                let combined = match base {
+                    Some('ウ') if voiced => Some('ヴ'),
+                    Some(b) if voiced && "カキクケコサシスセソタチツテトハヒフヘホ".contains(b) => {
+                        char::from_u32(b as u32 + 1)
+                    }
+                    Some(b) if !voiced && "ハヒフヘホ".contains(b) => {
+                        char::from_u32(b as u32 + 2)
+                    }
+                    _ => None,
+                };
+                match combined {
+                    Some(k) => out.push(k),
+                    None => {
+                        out.extend(base);
+                        out.push(if voiced { '゛' } else { '゜' });
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// How [`fold_case`] lowercases.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum CaseFold {
+    /// Unicode default lowercasing.
+    #[default]
+    Lower,
+    /// Turkish and Azerbaijani: `I` lowercases to dotless `ı`, and `İ` to `i`.
+    Turkic,
+}
+
+pub fn fold_case(text: &str, fold: CaseFold) -> String {
+    match fold {
+        CaseFold::Lower => text.to_lowercase(),
+        CaseFold::Turkic => text
+            .chars()
+            .map(|c| match c {
+                'I' => "ı".to_owned(),
+                'İ' => "i".to_owned(),
+                c => c.to_lowercase().collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Lookalikes of Latin letters from other scripts and letterlike symbols,
+/// and the letters they fold to, position by position.
+const CONFUSABLES: &str = "АВЕКМНОРСТХаеорсухіјѕԁԛԝΑΒΕΖΗΙΚΜΝΟΡΤΥΧοιℂℊℋℌℍℎℐℑℒℓℕℙℚℛℜℝℤℨℬℭℯℰℱℳℴ";
+const CONFUSABLE_LATIN: &str = "ABEKMHOPCTXaeopcyxijsdqwABEZHIKMNOPTYXoiCgHHHhIILlNPQRRRZZBCeEFMo";
+
+/// Fold characters that look like Latin letters or digits to them, in the
+/// spirit of the Unicode confusables skeleton: mathematical alphanumerics
+/// such as `𝓗𝓮𝓵𝓵𝓸`, circled letters, and Cyrillic or Greek lookalikes.
+/// Spam dressed up to dodge duplicate detection then shares tokens with
+/// the plain text.
+///
+/// Cyrillic and Greek text is folded too, so this suits channels chatting
+/// in Latin script.
+pub fn fold_confusables(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let code = c as u32;
+            let folded = match code {
+                0x1D400..=0x1D6A3 => {
+                    let i = (code - 0x1D400) % 52;
+                    if i < 26 {
+                        'A' as u32 + i
+                    } else {
+                        'a' as u32 + i - 26
+                    }
+                }
+                0x1D6A4 => 'i' as u32,
+                0x1D6A5 => 'j' as u32,
+                0x1D7CE..=0x1D7FF => '0' as u32 + (code - 0x1D7CE) % 10,
+                0x24B6..=0x24CF => 'A' as u32 + code - 0x24B6,
+                0x24D0..=0x24E9 => 'a' as u32 + code - 0x24D0,
+                _ => {
+                    return CONFUSABLES
+                        .chars()
+                        .position(|from| from == c)
+                        .and_then(|i| CONFUSABLE_LATIN.chars().nth(i))
+                        .unwrap_or(c);
+                }
+            };
+            char::from_u32(folded).unwrap_or(c)
+        })
+        .collect()
+}
+
+/// Replace each run of whitespace, newlines included, with one space and
+/// trim the ends.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The first `max_chars` characters of `text`, so a long paste weighs no
+/// more than a long chat.
+pub fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Decode HTML entities such as `&amp;` or `&#x1f602;` and drop markdown
+/// backslash escapes such as `\*`, as left in chat scraped from the web.
+/// Unknown or malformed entities are kept as they are.
+pub fn decode_markup(text: &str) -> String {
+    // Longest entity name decoded, e.g. `#x10ffff` or `#1114111`.
+    const MAX_ENTITY_LEN: usize = 10;
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(['&', '\\']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = if let Some(escaped) = rest.strip_prefix('\\') {
+            escaped
+                .chars()
+                .next()
+                .filter(char::is_ascii_punctuation)
+                .map(|c| (c, 1 + c.len_utf8()))
+        } else {
+            // Look for the `;` only as far as an entity can reach, so
+            // runs of unterminated `&` stay linear.
+            rest.as_bytes()[1..]
+                .iter()
+                .take(MAX_ENTITY_LEN + 1)
+                .position(|&b| b == b';')
+                .and_then(|end| Some((decode_entity(&rest[1..=end])?, end + 2)))
+        };
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The character an entity name such as `amp` or `#x1f602` stands for.
+fn decode_entity(name: &str) -> Option<char> {
+    let code = match name {
+        "amp" => '&' as u32,
+        "lt" => '<' as u32,
+        "gt" => '>' as u32,
+        "quot" => '"' as u32,
+        "apos" => '\'' as u32,
+        "nbsp" => ' ' as u32,
+        _ => {
+            let number = name.strip_prefix('#')?;
+            match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            }
+        }
+    };
+    char::from_u32(code)
+}
+
+/// Language of a chat as told by [`detect_lang`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lang {
+    Korean,
+    Japanese,
+    Chinese,
+    /// English and other languages written in Latin script.
+    Latin,
+}
+
+/// Guess the language of `text` from its scripts: Hangul is Korean, Han
+/// with any kana is Japanese, Han alone is Chinese. A CJK character counts
+/// as much as three Latin letters, roughly a word each. `None` if `text`
+/// has no letters in these scripts.
+pub fn detect_lang(text: &str) -> Option<Lang> {
+    let (mut hangul, mut kana, mut han, mut latin) = (0, 0, 0, 0);
+    for c in text.chars() {
+        match c as u32 {
+            0xAC00..=0xD7A3 | 0x1100..=0x11FF | 0x3130..=0x318F => hangul += 3,
+            0x3040..=0x30FF | 0xFF66..=0xFF9D => kana += 3,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => han += 3,
+            _ if c.is_ascii_alphabetic() => latin += 1,
+            0xC0..=0x24F if c.is_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+    let cjk = if kana > 0 {
+        (kana + han, Lang::Japanese)
     } else {
-        Vec::new()
+        (han, Lang::Chinese)
+    };
+    [(hangul, Lang::Korean), cjk, (latin, Lang::Latin)]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .reduce(|best, next| if next.0 > best.0 { next } else { best })
+        .map(|(_, lang)| lang)
+}
+
+/// A normalizer or tokenizer per language, picked by [`detect_lang`] for
+/// each chat, with a fallback for other chats.
+#[derive(Clone, Debug)]
+pub struct ByLanguage<T> {
+    by_lang: Vec<(Lang, T)>,
+    fallback: T,
+}
+
+impl<T> ByLanguage<T> {
+    pub fn new(fallback: T) -> Self {
+        Self {
+            by_lang: Vec::new(),
+            fallback,
+        }
+    }
+    /// Use `choice` for chats detected as `lang`.
+    pub fn with(mut self, lang: Lang, choice: T) -> Self {
+        self.by_lang.retain(|(l, _)| *l != lang);
+        self.by_lang.push((lang, choice));
+        self
+    }
+    /// The choice for `text`.
+    pub fn pick(&self, text: &str) -> &T {
+        let lang = detect_lang(text);
+        self.by_lang
+            .iter()
+            .find(|(l, _)| Some(*l) == lang)
+            .map_or(&self.fallback, |(_, choice)| choice)
     }
 }
 
+/// Whether `text` has no letters or digits, e.g. emoji or punctuation walls.
+pub fn is_symbol_only(text: &str) -> bool {
+    !text.chars().any(char::is_alphanumeric)
+}
+
 pub fn derepeat(text: &str, n: usize) -> String {
     let mut last_char: char = '𝕊';
     let mut repeat: usize = 0;
@@ -31,55 +462,189 @@ pub fn derepeat(text: &str, n: usize) -> String {
         .collect()
 }
 
+/// Like [`derepeat`], but also keeps at most `n` consecutive copies of any
+/// sequence of up to `max_period` characters, so `lolololol` and
+/// `ㅋㅎㅋㅎㅋㅎㅋㅎ` are collapsed too. Shorter periods are tried first.
+pub fn derepeat_periods(text: &str, n: usize, max_period: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    derepeat_periods_into(text, n, max_period, &mut out);
+    out
+}
+
+/// [`derepeat_periods`] into `out`, replacing its contents.
+pub fn derepeat_periods_into(text: &str, n: usize, max_period: usize, out: &mut String) {
+    out.clear();
+    let mut rest = text;
+    let mut remaining = text.chars().count();
+    'outer: while let Some(c) = rest.chars().next() {
+        // Byte ends of the first 1, 2, … characters of `rest`.
+        let mut ends = rest.char_indices().map(|(i, _)| i).skip(1);
+        for p in 1..=max_period.min(remaining / 2) {
+            let unit = &rest.as_bytes()[..ends.next().unwrap_or(rest.len())];
+            // Equal bytes mean equal characters, as UTF-8 is prefix-free.
+            let copies = rest
+                .as_bytes()
+                .chunks_exact(unit.len())
+                .take_while(|chunk| *chunk == unit)
+                .count();
+            if copies > n {
+                out.push_str(&rest[..n * unit.len()]);
+                rest = &rest[copies * unit.len()..];
+                remaining -= copies * p;
+                continue 'outer;
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+        remaining -= 1;
+    }
+}
+
+/// Characters and words that chat glues onto other words, such as Korean
+/// `ㅋㅋ` or Japanese `www`, so that they can be split off into tokens of
+/// their own.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interjections {
+    chars: Vec<char>,
+    suffixes: Vec<String>,
+}
+
+impl Interjections {
+    /// Laughter and crying jamo plus sentence punctuation.
+    pub fn korean() -> Self {
+        Self::default().with_chars("ㅋㅎㅜㅠㄷ!.,?".chars())
+    }
+    /// `w` laughter in half and full width, and `笑`.
+    pub fn japanese() -> Self {
+        Self::default().with_chars("wｗ笑!?！？。、".chars())
+    }
+    /// Sentence punctuation, and `lol`/`lmao` glued to the end of a word.
+    pub fn english() -> Self {
+        Self::default()
+            .with_chars("!.,?".chars())
+            .with_suffixes(["lol", "lmao"])
+    }
+    pub fn with_chars(mut self, chars: impl IntoIterator<Item = char>) -> Self {
+        self.chars.extend(chars);
+        self.chars.sort_unstable();
+        self.chars.dedup();
+        self
+    }
+    /// Words split off the end of longer words, e.g. `gglol` to `gg lol`.
+    pub fn with_suffixes(mut self, suffixes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.suffixes.extend(suffixes.into_iter().map(Into::into));
+        self
+    }
+    /// Union of two sets, e.g. for a bilingual channel.
+    pub fn and(self, other: Self) -> Self {
+        self.with_chars(other.chars).with_suffixes(other.suffixes)
+    }
+    pub fn contains(&self, c: char) -> bool {
+        self.chars.binary_search(&c).is_ok()
+    }
+}
+
+/// Put spaces around runs of two or more interjection characters glued to
+/// other text, and before interjection suffixes, so `대박ㅋㅋㅋ` becomes
+/// `대박 ㅋㅋㅋ`. Single characters are left alone so that `3.5` or `wow`
+/// survive.
+pub fn space_around_interjections(text: &str, interjections: &Interjections) -> String {
+    let mut out = String::with_capacity(text.len());
+    space_around_interjections_into(text, interjections, &mut out);
+    out
+}
+
+/// [`space_around_interjections`] into `out`, replacing its contents.
+/// Only splitting off suffixes allocates.
+pub fn space_around_interjections_into(
+    text: &str,
+    interjections: &Interjections,
+    out: &mut String,
+) {
+    out.clear();
+    let glued = |c: Option<char>| c.is_some_and(|c| !c.is_whitespace());
+    let mut before = None;
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let is_ic = interjections.contains(first);
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| interjections.contains(c) != is_ic)
+            .map_or(rest.len(), |(i, _)| i);
+        let run = &rest[..end];
+        rest = &rest[end..];
+        let split = is_ic && run.chars().nth(1).is_some();
+        if split && glued(before) {
+            out.push(' ');
+        }
+        out.push_str(run);
+        if split && glued(rest.chars().next()) {
+            out.push(' ');
+        }
+        before = run.chars().next_back();
+    }
+    if interjections.suffixes.is_empty() {
+        return;
+    }
+    let spaced = std::mem::take(out);
+    for (i, word) in spaced.split(' ').enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let suffix = interjections
+            .suffixes
+            .iter()
+            .find(|s| word.len() > s.len() && word.ends_with(s.as_str()));
+        match suffix {
+            Some(s) => {
+                out.push_str(&word[..word.len() - s.len()]);
+                out.push(' ');
+                out.push_str(s);
+            }
+            None => out.push_str(word),
+        }
+    }
+}
+
+/// [`space_around_interjections`] with the Korean preset.
 pub fn space_around_ic(text: &str) -> String {
-    let mut last_char: char = '𝕊';
-    let mut repeat: u32 = 0;
-    text.chars()
-        .flat_map(|c| {
-            let next_chars = if c != 'ㅋ'
-                || c != 'ㅎ'
-                || c != 'ㅜ'
-                || c != 'ㅠ'
-                || c != 'ㄷ'
-                || c != '!'
-                || c != '.'
-                || c != ','
-                || c != '?'
-            {
-                if last_char == 'ㅋ'
-                    || last_char == 'ㅎ'
-                    || last_char == 'ㅜ'
-                    || last_char == 'ㅠ'
-                    || last_char == 'ㄷ'
-                    || last_char == '!'
-                    || last_char == '.'
-                    || last_char == ','
-                    || last_char == '?'
-                {
-                    repeat += 1;
-                } else {
-                    repeat = 0;
-                }
-                iter::once(c).chain(iter::once('\0'))
-            } else if repeat >= 1 {
-                repeat = 0;
-                iter::once(c).chain(iter::once(' '))
-            } else {
-                iter::once(c).chain(iter::once('\0'))
-            };
-            last_char = c;
-            next_chars
-        })
-        .filter(|c| c != &'\0')
-        .collect()
+    space_around_interjections(text, &Interjections::korean())
+}
+
+/// Unicode normalization form for [`normalize_unicode`].
+#[cfg(feature = "unicode-normalization")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Canonical composition only.
+    Nfc,
+    /// Also fold compatibility forms such as full-width letters and
+    /// ligatures, so `ｐｏｇ` and `pog` become the same tokens.
+    Nfkc,
+}
+
+#[cfg(feature = "unicode-normalization")]
+pub fn normalize_unicode(text: &str, form: UnicodeForm) -> String {
+    use unicode_normalization::UnicodeNormalization;
+    match form {
+        UnicodeForm::Nfc => text.nfc().collect(),
+        UnicodeForm::Nfkc => text.nfkc().collect(),
+    }
 }
 
+/// [`normalize`] with a custom interjection set.
+pub fn normalize_with(text: &str, interjections: &Interjections) -> String {
+    space_around_interjections(&derepeat_periods(text, 3, 4), interjections)
+}
+
+/// Collapse repeats of up to four characters to three copies and split off
+/// Korean interjections.
 pub fn normalize(text: &str) -> String {
-    space_around_ic(&derepeat(text, 3))
+    space_around_ic(&derepeat_periods(text, 3, 4))
 }
 
+/// Word `ngram`-grams of `text`, words joined by a single space.
 pub fn tokenize(text: &str, ngram: usize) -> Vec<String> {
-    let tokens: Vec<_> = text.split(char::is_whitespace).collect();
+    let tokens: Vec<_> = text.split_whitespace().collect();
     if ngram > 1 {
         tokens.windows(ngram).map(|t| t.join(" ")).collect()
     } else {
@@ -100,5 +665,133 @@ mod tests {
         assert_eq!(expect, vec!["하나 둘", "둘 셋", "셋 넷"]);
         let expect = tokenize(text, 1);
         assert_eq!(expect, vec!["하나", "둘", "셋", "넷"]);
+        assert_eq!(tokenize("ㅋㅋ  gg ", 2), vec!["ㅋㅋ gg"]);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn nfkc_folds_compatibility_forms() {
+        assert_eq!(normalize_unicode("ｐｏｇ ﬁ", UnicodeForm::Nfkc), "pog fi");
+        assert_eq!(normalize_unicode("ｐｏｇ", UnicodeForm::Nfc), "ｐｏｇ");
+        assert_eq!(normalize_unicode("e\u{301}", UnicodeForm::Nfc), "é");
+    }
+
+    #[cfg(feature = "korean")]
+    #[test]
+    fn jamo_decomposition() {
+        assert_eq!(decompose_jamo("헐 킄ㅋ 값a"), "ㅎㅓㄹ ㅋㅡㅋㅋ ㄱㅏㅄa");
+        assert_eq!(decompose_jamo("가힣"), "ㄱㅏㅎㅣㅎ");
+    }
+
+    #[test]
+    fn detects_language_by_script() {
+        assert_eq!(detect_lang("대박 ㅋㅋ what"), Some(Lang::Korean));
+        assert_eq!(detect_lang("草生えるwww"), Some(Lang::Japanese));
+        assert_eq!(detect_lang("すごい play"), Some(Lang::Japanese));
+        assert_eq!(detect_lang("太强了"), Some(Lang::Chinese));
+        assert_eq!(detect_lang("qué jugada"), Some(Lang::Latin));
+        assert_eq!(detect_lang("🔥 123"), None);
+        let pick = ByLanguage::new("other").with(Lang::Korean, "ko");
+        assert_eq!(*pick.pick("ㅋㅋ"), "ko");
+        assert_eq!(*pick.pick("lol"), "other");
+    }
+
+    #[test]
+    fn width_and_case_folding() {
+        let folded = fold_case(&fold_width("ＬＯＬ\u{3000}LOL lol"), CaseFold::Lower);
+        assert_eq!(folded, "lol lol lol");
+        assert_eq!(fold_width("ｶﾞﾝﾊﾞﾚ ﾊﾟ ｳﾞ ｱﾞ"), "ガンバレ パ ヴ ア゛");
+        assert_eq!(fold_case("IŞIK İzmir", CaseFold::Turkic), "ışık izmir");
+    }
+
+    #[test]
+    fn whitespace_collapsed_and_truncated() {
+        assert_eq!(collapse_whitespace(" gg \n\t wp  "), "gg wp");
+        assert_eq!(truncate_chars("대박 play", 4), "대박 p");
+        assert_eq!(truncate_chars("gg", 4), "gg");
+    }
+
+    #[test]
+    fn confusables_fold_to_latin() {
+        assert_eq!(fold_confusables("𝓗𝓮𝓵𝓵𝓸 𝐰𝐨𝐫𝐥𝐝 𝟗𝟘"), "Hello world 90");
+        assert_eq!(fold_confusables("Ⓕⓡⓔⓔ ℎеllо ΡΟG"), "Free hello POG");
+        assert_eq!(fold_confusables("대박 ㅋ"), "대박 ㅋ");
+    }
+
+    #[test]
+    fn markup_is_decoded() {
+        assert_eq!(
+            decode_markup(r"Q&amp;A &#x1f602;&#128514; \*pog\* &lt;3 C:\path"),
+            r"Q&A 😂😂 *pog* <3 C:\path"
+        );
+        assert_eq!(decode_markup("&bogus; & &#xzz; &"), "&bogus; & &#xzz; &");
+        assert_eq!(decode_markup("&#x1f602 later;"), "&#x1f602 later;");
+        let unterminated = "&".repeat(1 << 20);
+        assert_eq!(decode_markup(&unterminated), unterminated);
+    }
+
+    #[test]
+    fn zalgo_marks_are_limited() {
+        let zalgo = "h\u{300}\u{301}\u{302}\u{303}i\u{304}";
+        assert_eq!(limit_combining_marks(zalgo, 1), "h\u{300}i\u{304}");
+        assert_eq!(limit_combining_marks(zalgo, 0), "hi");
+        assert_eq!(limit_combining_marks("café", 0), "café");
+    }
+
+    #[test]
+    fn derepeat_collapses_periods() {
+        assert_eq!(derepeat_periods("lolololol", 3, 4), "lololol");
+        assert_eq!(
+            derepeat_periods("하하하하하 ㅋㅋㅋㅋ", 3, 4),
+            "하하하 ㅋㅋㅋ"
+        );
+        assert_eq!(derepeat_periods("abcabcabcabc!", 2, 4), "abcabc!");
+        assert_eq!(derepeat_periods("aaaa bb", 1, 1), derepeat("aaaa bb", 1));
+    }
+
+    #[test]
+    fn interjections_are_split_off() {
+        assert_eq!(space_around_ic("대박ㅋㅋㅋ진짜"), "대박 ㅋㅋㅋ 진짜");
+        assert_eq!(space_around_ic("ㅋㅋ 3.5 ㅋ"), "ㅋㅋ 3.5 ㅋ");
+        let ja = Interjections::japanese();
+        assert_eq!(space_around_interjections("草www wow", &ja), "草 www wow");
+        let en = Interjections::english().and(Interjections::korean());
+        assert_eq!(
+            space_around_interjections("gglol what?!", &en),
+            "gg lol what ?!"
+        );
+        assert_eq!(normalize_with("niceㅋㅋㅋㅋㅋ", &en), "nice ㅋㅋㅋ");
+    }
+
+    #[test]
+    fn char_ngrams_iter_matches_sorted_slices() {
+        let chat = "가나 ab가나";
+        let mut ngrams: Vec<_> = char_ngrams_iter(chat, 1, 3).collect();
+        assert_eq!(ngrams[..4], ["가", "나", " ", "a"]);
+        ngrams.sort_unstable();
+        assert_eq!(ngrams, char_ngram_slices(chat, 1, 3));
+        assert_eq!(char_ngrams_iter("ab", 3, 4).collect::<Vec<_>>(), ["ab"]);
+        assert_eq!(char_ngrams_iter("", 1, 4).count(), 0);
+    }
+
+    #[test]
+    fn typed_tokens_keep_emotes_and_mentions_whole() {
+        let tokens = typed_tokens("gg @faker :pog: 🔥🔥 !!", 1, 2);
+        assert!(tokens.contains(&("@faker".to_owned(), TokenKind::Mention)));
+        assert!(tokens.contains(&(":pog:".to_owned(), TokenKind::Emote)));
+        assert!(tokens.contains(&("🔥🔥".to_owned(), TokenKind::Emote)));
+        assert!(tokens.contains(&("gg".to_owned(), TokenKind::Text)));
+        assert!(!tokens.iter().any(|(t, _)| t == "@f" || t == "po"));
+    }
+
+    #[test]
+    fn simhash_near_duplicates() {
+        let a = unique_char_ngrams("what a play by faker", 1, 4);
+        let b = unique_char_ngrams("what a play by faker!", 1, 4);
+        let c = unique_char_ngrams("thanks for the gifted subs", 1, 4);
+        let fa = simhash(a.iter().map(String::as_str));
+        let fb = simhash(b.iter().map(String::as_str));
+        let fc = simhash(c.iter().map(String::as_str));
+        assert!((fa ^ fb).count_ones() < (fa ^ fc).count_ones());
     }
 }
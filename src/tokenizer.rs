@@ -0,0 +1,677 @@
+//! Splitting normalized chats into the tokens `ChatWindow` weighs.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use std::iter;
+use std::ops::{Range, RangeInclusive};
+
+use smallvec::SmallVec;
+
+use crate::text::{
+    ByLanguage, TokenKind, all_char_ngrams, char_ngrams_iter, is_emoji, is_emoji_modifier,
+    token_kind, tokenize, typed_tokens,
+};
+
+/// A token of a chat, borrowed from the chat text where possible.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token<'a> {
+    pub text: Cow<'a, str>,
+    pub kind: TokenKind,
+    /// Hash standing in for the text of opaque tokens, see
+    /// [`fingerprint`](Self::fingerprint).
+    pub fingerprint: Option<u64>,
+}
+
+impl<'a> Token<'a> {
+    pub fn text(text: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            text: text.into(),
+            kind: TokenKind::Text,
+            fingerprint: None,
+        }
+    }
+    /// A token known only by a hash, with empty text. `ChatWindow` shows it
+    /// as the hash in hex and, when fingerprint-keyed, keys it by the hash
+    /// itself.
+    pub fn fingerprint(fingerprint: u64) -> Self {
+        Self {
+            text: Cow::Borrowed(""),
+            kind: TokenKind::Text,
+            fingerprint: Some(fingerprint),
+        }
+    }
+    pub fn with_kind(mut self, kind: TokenKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+/// Tokens of one chat. Most chats have fewer than 64 tokens, which then
+/// stay on the stack.
+pub type Tokens<'a> = SmallVec<[Token<'a>; 64]>;
+
+/// Splits a normalized chat into tokens.
+///
+/// The same token may be returned more than once; `ChatWindow` counts each
+/// distinct token once per chat, keeping its most specific kind.
+pub trait Tokenizer {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a>;
+    /// Hand each token of `text` to `f`, e.g. to copy it into a buffer
+    /// reused across chats. Tokenizers that can skip the intermediate
+    /// [`Tokens`] override this.
+    fn for_each_token<'a>(&self, text: &'a str, f: &mut dyn FnMut(Token<'a>)) {
+        self.tokens(text).into_iter().for_each(f);
+    }
+}
+
+/// Character n-grams of lengths `min..=max`, the default tokenization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CharNgrams {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Default for CharNgrams {
+    fn default() -> Self {
+        Self { min: 1, max: 4 }
+    }
+}
+
+impl Tokenizer for CharNgrams {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        char_ngrams_iter(text, self.min, self.max)
+            .map(Token::text)
+            .collect()
+    }
+    fn for_each_token<'a>(&self, text: &'a str, f: &mut dyn FnMut(Token<'a>)) {
+        all_char_ngrams(text, self.min, self.max)
+            .map(Token::text)
+            .for_each(f);
+    }
+}
+
+/// Character n-grams of the plain words, with emote and mention words kept
+/// whole and tagged, as in [`typed_tokens`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct TypedCharNgrams(pub CharNgrams);
+
+impl Tokenizer for TypedCharNgrams {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        typed_tokens(text, self.0.min, self.0.max)
+            .into_iter()
+            .map(|(token, kind)| Token::text(token).with_kind(kind))
+            .collect()
+    }
+}
+
+/// Word n-grams with `n` in `min..=max`, via [`tokenize`]. Summaries of
+/// space-delimited languages such as English or Spanish read better with
+/// word tokens than with character n-grams.
+///
+/// With [`with_skip_grams`](Self::with_skip_grams) it also emits word
+/// pairs with up to `skip` words between them, so paraphrases such as
+/// `what a play` and `what an insane play` share the token `what play`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WordNgrams {
+    pub min: usize,
+    pub max: usize,
+    /// Most words skipped between the two words of a skip-gram; zero for
+    /// no skip-grams.
+    pub skip: usize,
+}
+
+impl WordNgrams {
+    pub fn with_skip_grams(mut self, max_gap: usize) -> Self {
+        self.skip = max_gap;
+        self
+    }
+}
+
+impl From<RangeInclusive<usize>> for WordNgrams {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self {
+            min: *range.start(),
+            max: *range.end(),
+            skip: 0,
+        }
+    }
+}
+
+impl Tokenizer for WordNgrams {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        let mut tokens: Tokens = (self.min.max(1)..=self.max)
+            .flat_map(|n| tokenize(text, n))
+            .map(Token::text)
+            .collect();
+        if self.skip > 0 {
+            let words: Vec<_> = text.split_whitespace().collect();
+            for (i, first) in words.iter().enumerate() {
+                for second in words.iter().skip(i + 2).take(self.skip) {
+                    tokens.push(Token::text(format!("{first} {second}")));
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// Platform emotes and their variants, each mapped to one canonical token,
+/// so `catJAM`, `catjam` and `🐱` spike together as `catJAM`.
+///
+/// Names are matched case-insensitively. Lists exported from Twitch, 7TV
+/// or BTTV can be loaded with [`parse`](Self::parse).
+#[derive(Clone, Default, Debug)]
+pub struct EmoteRegistry {
+    /// Canonical token by lowercased name or variant.
+    canonical: HashMap<String, Arc<str>>,
+}
+
+impl EmoteRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register `name` along with `variants` that stand for the same emote.
+    pub fn with_emote(
+        mut self,
+        name: &str,
+        variants: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        let canonical: Arc<str> = name.into();
+        for alias in
+            iter::once(name.to_owned()).chain(variants.into_iter().map(|v| v.as_ref().to_owned()))
+        {
+            self.canonical
+                .insert(alias.to_lowercase(), canonical.clone());
+        }
+        self
+    }
+    /// Registry from an emote list, one emote per line: the canonical name
+    /// followed by its variants, separated by whitespace. Blank lines and
+    /// lines starting with `#` are skipped.
+    pub fn parse(list: &str) -> Self {
+        list.lines()
+            .map(str::split_whitespace)
+            .fold(Self::new(), |registry, mut words| match words.next() {
+                Some(name) if !name.starts_with('#') => registry.with_emote(name, words),
+                _ => registry,
+            })
+    }
+    /// Canonical token of an emote name or variant.
+    pub fn canonical(&self, word: &str) -> Option<&str> {
+        let key = if word.chars().any(char::is_uppercase) {
+            Cow::Owned(word.to_lowercase())
+        } else {
+            Cow::Borrowed(word)
+        };
+        self.canonical.get(&*key).map(|c| &**c)
+    }
+    pub fn len(&self) -> usize {
+        self.canonical.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.canonical.is_empty()
+    }
+}
+
+/// Emoji and emote names as whole [`TokenKind::Emote`] tokens, the rest of
+/// the chat as character n-grams.
+///
+/// An emote flood such as `PogChamp PogChamp` or `🔥🔥🔥` then spikes as
+/// one token instead of being shredded into n-grams that overlap other
+/// chats. Emoji are split out even when glued to text. Named emotes are
+/// the `:name:` form plus the platform emotes registered with
+/// [`with_emotes`](Self::with_emotes), matched case-sensitively, and the
+/// emotes of an [`EmoteRegistry`], which are replaced by their canonical
+/// token.
+#[derive(Clone, Default, Debug)]
+pub struct EmoteTokenizer {
+    ngrams: CharNgrams,
+    emotes: HashSet<String>,
+    registry: EmoteRegistry,
+}
+
+impl EmoteTokenizer {
+    pub fn new(ngrams: CharNgrams) -> Self {
+        Self {
+            ngrams,
+            emotes: HashSet::new(),
+            registry: EmoteRegistry::default(),
+        }
+    }
+    pub fn with_registry(mut self, registry: EmoteRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+    /// `emote` as a token, canonical if it is registered.
+    fn emote<'a>(&self, emote: &'a str) -> Token<'a> {
+        let text = match self.registry.canonical(emote) {
+            Some(canonical) => Cow::Owned(canonical.to_owned()),
+            None => Cow::Borrowed(emote),
+        };
+        Token::text(text).with_kind(TokenKind::Emote)
+    }
+    /// Treat these words as emotes, e.g. `PogChamp` or `Kappa`.
+    pub fn with_emotes(mut self, emotes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.emotes.extend(emotes.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl Tokenizer for EmoteTokenizer {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        let mut tokens = Tokens::new();
+        let mut plain = String::new();
+        for word in text.split_whitespace() {
+            let is_named = word.starts_with(':') && word.ends_with(':');
+            if self.emotes.contains(word)
+                || is_named && token_kind(word) == TokenKind::Emote
+                || self.registry.canonical(word).is_some()
+            {
+                tokens.push(self.emote(word));
+                continue;
+            }
+            // Start of the emoji cluster being read, and whether a joiner
+            // glues the next emoji onto it.
+            let mut cluster: Option<usize> = None;
+            let mut joined = false;
+            for (i, c) in word.char_indices() {
+                if let Some(start) = cluster {
+                    if is_emoji_modifier(c) || joined && is_emoji(c) {
+                        joined = c == '\u{200D}';
+                        continue;
+                    }
+                    tokens.push(self.emote(&word[start..i]));
+                    cluster = None;
+                }
+                if is_emoji(c) {
+                    cluster = Some(i);
+                    plain.push(' ');
+                } else {
+                    plain.push(c);
+                }
+            }
+            if let Some(start) = cluster {
+                tokens.push(self.emote(&word[start..]));
+            }
+            plain.push(' ');
+        }
+        let plain = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+        tokens.extend(
+            char_ngrams_iter(&plain, self.ngrams.min, self.ngrams.max)
+                .map(|t| Token::text(t.to_owned())),
+        );
+        tokens
+    }
+}
+
+/// Character n-grams over the jamo of Hangul syllables, see
+/// [`decompose_jamo`](crate::text::decompose_jamo). Typos and stretched
+/// words then share most n-grams, while summaries keep the original text.
+/// The n-gram lengths count jamo, so a range about three times the
+/// syllable range is comparable.
+#[cfg(feature = "korean")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JamoNgrams(pub CharNgrams);
+
+#[cfg(feature = "korean")]
+impl Default for JamoNgrams {
+    fn default() -> Self {
+        Self(CharNgrams { min: 2, max: 6 })
+    }
+}
+
+#[cfg(feature = "korean")]
+impl Tokenizer for JamoNgrams {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        let jamo = crate::text::decompose_jamo(text);
+        char_ngrams_iter(&jamo, self.0.min, self.0.max)
+            .map(|t| Token::text(t.to_owned()))
+            .collect()
+    }
+}
+
+/// Hashed byte n-grams of lengths `min..=max`, as opaque
+/// [fingerprint](Token::fingerprint) tokens.
+///
+/// Windows over UTF-8 bytes need no char decoding and treat every script
+/// and emoji alike, at the cost of tokens that cannot be read back: top
+/// tokens and explanations show hashes. With
+/// [`with_buckets`](Self::with_buckets) hashes are folded into a fixed
+/// number of buckets, bounding the dictionary however varied the chat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteNgrams {
+    pub min: usize,
+    pub max: usize,
+    pub buckets: Option<u64>,
+}
+
+impl Default for ByteNgrams {
+    fn default() -> Self {
+        Self {
+            min: 2,
+            max: 8,
+            buckets: None,
+        }
+    }
+}
+
+impl ByteNgrams {
+    pub fn with_buckets(mut self, buckets: u64) -> Self {
+        self.buckets = Some(buckets.max(1));
+        self
+    }
+}
+
+impl Tokenizer for ByteNgrams {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        let bytes = text.as_bytes();
+        (self.min.max(1)..=self.max.min(bytes.len()))
+            .flat_map(|n| bytes.windows(n))
+            .map(|window| {
+                let mut hasher = DefaultHasher::new();
+                window.hash(&mut hasher);
+                let h = hasher.finish();
+                Token::fingerprint(self.buckets.map_or(h, |b| h % b))
+            })
+            .collect()
+    }
+}
+
+/// Morphological analyzer splitting text into morphemes, given as byte
+/// ranges of the text.
+pub trait MorphemeAnalyzer {
+    fn morphemes(&self, text: &str) -> Vec<Range<usize>>;
+}
+
+impl<F: Fn(&str) -> Vec<Range<usize>>> MorphemeAnalyzer for F {
+    fn morphemes(&self, text: &str) -> Vec<Range<usize>> {
+        self(text)
+    }
+}
+
+/// lindera segmentation. Chats it fails on are split at whitespace.
+#[cfg(feature = "lindera")]
+impl MorphemeAnalyzer for lindera::segmenter::Segmenter {
+    fn morphemes(&self, text: &str) -> Vec<Range<usize>> {
+        match self.segment(Cow::Borrowed(text)) {
+            Ok(tokens) => tokens
+                .iter()
+                .filter(|t| !t.surface.trim().is_empty())
+                .map(|t| t.byte_start..t.byte_end)
+                .collect(),
+            Err(_) => text
+                .split_whitespace()
+                .map(|word| {
+                    let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                    start..start + word.len()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Morphemes from an analyzer such as lindera, and runs of `min..=max`
+/// adjacent morphemes as they appear in the chat. Japanese and Korean
+/// summaries then rest on real words rather than character n-grams.
+#[derive(Clone, Debug)]
+pub struct Morphemes<A> {
+    analyzer: A,
+    min: usize,
+    max: usize,
+}
+
+impl<A: MorphemeAnalyzer> Morphemes<A> {
+    /// Single morphemes only.
+    pub fn new(analyzer: A) -> Self {
+        Self {
+            analyzer,
+            min: 1,
+            max: 1,
+        }
+    }
+    pub fn with_ngram_range(mut self, min: usize, max: usize) -> Self {
+        self.min = min.max(1);
+        self.max = max;
+        self
+    }
+}
+
+impl<A: MorphemeAnalyzer> Tokenizer for Morphemes<A> {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        let spans = self.analyzer.morphemes(text);
+        (self.min..=self.max.min(spans.len()))
+            .flat_map(|n| spans.windows(n))
+            .map(|run| Token::text(&text[run[0].start..run[run.len() - 1].end]))
+            .collect()
+    }
+}
+
+const KOREAN_STOPWORDS: &[&str] = &[
+    "은", "는", "이", "가", "을", "를", "에", "의", "도", "와", "과", "로", "으로", "에서", "에게",
+    "한테", "께", "만", "까지", "부터", "보다", "처럼", "이나", "나", "랑", "이랑", "요",
+];
+
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "of", "to", "in", "on", "at", "for", "with", "by",
+    "from", "as", "so", "is", "are", "was", "were", "be", "been", "it", "its", "this", "that", "i",
+    "you", "he", "she", "we", "they", "me", "my", "your", "not", "no", "do", "does", "did", "have",
+    "has", "had", "will", "would", "can", "could", "just",
+];
+
+/// Tokens too common to carry meaning, dropped right after tokenization so
+/// they neither grow the dictionary nor dilute centrality.
+///
+/// Tokens are matched whole: with character n-grams, a particle set drops
+/// the particle's unigram but not the longer n-grams containing it.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Stopwords {
+    words: Arc<HashSet<String>>,
+}
+
+impl Stopwords {
+    /// Common particles and endings.
+    pub fn korean() -> Self {
+        Self::default().with_words(KOREAN_STOPWORDS.iter().copied())
+    }
+    /// Articles, pronouns, prepositions and auxiliaries.
+    pub fn english() -> Self {
+        Self::default().with_words(ENGLISH_STOPWORDS.iter().copied())
+    }
+    pub fn with_words(mut self, words: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Arc::make_mut(&mut self.words).extend(words.into_iter().map(Into::into));
+        self
+    }
+    /// Union of two sets, e.g. for a bilingual channel.
+    pub fn and(self, other: Self) -> Self {
+        self.with_words(other.words.iter().cloned())
+    }
+    pub fn contains(&self, token: &str) -> bool {
+        self.words.contains(token)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+    /// Drop the stopwords from `tokens`.
+    pub fn filter(&self, tokens: &mut Tokens<'_>) {
+        if !self.is_empty() {
+            tokens.retain(|t| !self.contains(&t.text));
+        }
+    }
+}
+
+impl<T: Tokenizer> Tokenizer for ByLanguage<T> {
+    fn tokens<'a>(&self, text: &'a str) -> Tokens<'a> {
+        self.pick(text).tokens(text)
+    }
+}
+
+/// Sort `tokens` and drop repeats, keeping the most specific kind of each.
+pub fn dedup_tokens(tokens: &mut Tokens<'_>) {
+    tokens.sort_unstable_by(|a, b| {
+        (&a.text, a.fingerprint)
+            .cmp(&(&b.text, b.fingerprint))
+            .then(b.kind.cmp(&a.kind))
+    });
+    tokens.dedup_by(|a, b| a.text == b.text && a.fingerprint == b.fingerprint);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "korean")]
+    #[test]
+    fn jamo_ngrams_match_stretched_words() {
+        let tokenizer = JamoNgrams(CharNgrams { min: 2, max: 2 });
+        let plain = tokenizer.tokens("헐");
+        let stretched = tokenizer.tokens("허얼");
+        assert_eq!(plain[..], [Token::text("ㅎㅓ"), Token::text("ㅓㄹ")]);
+        assert!(plain.iter().all(|t| stretched.contains(t)));
+        let syllables = CharNgrams { min: 1, max: 2 };
+        assert!(!syllables.tokens("허얼").contains(&Token::text("헐")));
+    }
+
+    #[test]
+    fn morphemes_from_custom_analyzer() {
+        // Splits off the particle `는` and the ending `다`.
+        let analyzer = |text: &str| -> Vec<Range<usize>> {
+            let particle = text.find('는').unwrap();
+            let ending = text.find('다').unwrap();
+            vec![
+                0..particle,
+                particle..particle + 3,
+                particle + 4..ending,
+                ending..text.len(),
+            ]
+        };
+        let tokens = Morphemes::new(analyzer)
+            .with_ngram_range(1, 2)
+            .tokens("나는 간다");
+        let texts: Vec<_> = tokens.iter().map(|t| &*t.text).collect();
+        assert_eq!(texts, ["나", "는", "간", "다", "나는", "는 간", "간다"]);
+    }
+
+    #[test]
+    fn stopwords_are_filtered() {
+        let stopwords = Stopwords::english().and(Stopwords::default().with_words(["lol"]));
+        let mut tokens = WordNgrams::from(1..=2).tokens("the play of the year lol");
+        stopwords.filter(&mut tokens);
+        let texts: Vec<_> = tokens.iter().map(|t| &*t.text).collect();
+        assert_eq!(
+            texts,
+            [
+                "play", "year", "the play", "play of", "of the", "the year", "year lol"
+            ]
+        );
+        assert!(Stopwords::korean().contains("는"));
+    }
+
+    #[test]
+    fn byte_ngrams_hash_windows() {
+        let texts = |tokenizer: ByteNgrams, chat| -> HashSet<u64> {
+            tokenizer
+                .tokens(chat)
+                .into_iter()
+                .map(|t| t.fingerprint.unwrap())
+                .collect()
+        };
+        let tokenizer = ByteNgrams {
+            min: 2,
+            max: 3,
+            buckets: None,
+        };
+        // "🔥" is 4 bytes: 3 bigrams and 2 trigrams.
+        assert_eq!(texts(tokenizer, "🔥").len(), 5);
+        assert_eq!(texts(tokenizer, "🔥🔥"), texts(tokenizer, "🔥🔥🔥"));
+        assert!(tokenizer.tokens("a").is_empty());
+        let bucketed = texts(tokenizer.with_buckets(4), "a longer chat 가나다");
+        assert!(bucketed.len() <= 4);
+        assert!(bucketed.iter().all(|&h| h < 4));
+    }
+
+    #[test]
+    fn word_ngrams() {
+        let tokens = WordNgrams::from(1..=2).tokens("what a  play");
+        let texts: Vec<_> = tokens.iter().map(|t| &*t.text).collect();
+        assert_eq!(texts, ["what", "a", "play", "what a", "a play"]);
+    }
+
+    #[test]
+    fn skip_grams_link_paraphrases() {
+        let tokenizer = WordNgrams::from(2..=2).with_skip_grams(2);
+        let texts = |chat| -> Vec<String> {
+            tokenizer
+                .tokens(chat)
+                .into_iter()
+                .map(|t| t.text.into_owned())
+                .collect()
+        };
+        assert_eq!(texts("what a play"), ["what a", "a play", "what play"]);
+        assert!(texts("what an insane play").contains(&"what play".to_owned()));
+        assert!(!texts("what an insane crazy play").contains(&"what play".to_owned()));
+    }
+
+    #[test]
+    fn emote_tokenizer_keeps_emotes_whole() {
+        let tokenizer =
+            EmoteTokenizer::new(CharNgrams { min: 1, max: 2 }).with_emotes(["PogChamp"]);
+        let mut tokens = tokenizer.tokens("PogChamp gg🔥🔥 :smile: 👨\u{200D}👩 ok");
+        dedup_tokens(&mut tokens);
+        let emotes: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Emote)
+            .map(|t| &*t.text)
+            .collect();
+        assert_eq!(emotes, [":smile:", "PogChamp", "👨\u{200D}👩", "🔥"]);
+        assert!(tokens.contains(&Token::text("gg")));
+        let text: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Text)
+            .collect();
+        assert!(
+            !text
+                .iter()
+                .any(|t| t.text.contains("Po") || t.text.contains('🔥'))
+        );
+    }
+
+    #[test]
+    fn emote_registry_canonicalizes_variants() {
+        let registry = EmoteRegistry::parse("# 7tv\ncatJAM 🐱 catjammer\n\nKEKW\n");
+        assert_eq!(registry.canonical("CATJAM"), Some("catJAM"));
+        assert_eq!(registry.canonical("kekw"), Some("KEKW"));
+        assert_eq!(registry.canonical("#"), None);
+        let tokenizer = EmoteTokenizer::new(CharNgrams { min: 1, max: 1 }).with_registry(registry);
+        let mut tokens = tokenizer.tokens("catjam 🐱 CatJammer go");
+        tokens.retain(|t| t.kind == TokenKind::Emote);
+        dedup_tokens(&mut tokens);
+        assert_eq!(
+            tokens[..],
+            [Token::text("catJAM").with_kind(TokenKind::Emote)]
+        );
+    }
+
+    #[test]
+    fn char_ngrams_borrow_from_text() {
+        let tokens = CharNgrams { min: 2, max: 2 }.tokens("abab");
+        assert_eq!(tokens[..], [Token::text("ab"), Token::text("ba")]);
+        assert!(tokens.iter().all(|t| matches!(t.text, Cow::Borrowed(_))));
+        assert!(!tokens.spilled());
+        let mut tokens: Tokens = smallvec::smallvec![
+            Token::text("b"),
+            Token::text("a"),
+            Token::text("b").with_kind(TokenKind::Emote),
+        ];
+        dedup_tokens(&mut tokens);
+        assert_eq!(
+            tokens[..],
+            [
+                Token::text("a"),
+                Token::text("b").with_kind(TokenKind::Emote)
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Allocation counts of `ChatWindow` pushes, under a counting global
+//! allocator that only this test binary uses.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use chat_spike::spike::ChatWindow;
+
+/// `System`, counting the allocations made by each thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|n| n.get())
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn chat_window_push_does_not_allocate_in_steady_state() {
+    let mut cw = ChatWindow::<4, 64>::default();
+    let mut chats: Vec<String> = ["hello world", "대박ㅋㅋㅋㅋ", "what a play!!", "gg"]
+        .iter()
+        .cycle()
+        .take(64)
+        .map(|chat| chat.to_string())
+        .collect();
+    let measured = chats.split_off(32);
+    chats.into_iter().for_each(|chat| cw.push(chat));
+    let before = allocations();
+    measured.into_iter().for_each(|chat| cw.push(chat));
+    assert_eq!(allocations() - before, 0);
+    assert_eq!(cw.len(), 4);
+    // The counter does see this thread's allocations.
+    let before = allocations();
+    cw.push("new chat".into());
+    assert!(allocations() > before);
+}